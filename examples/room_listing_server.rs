@@ -0,0 +1,144 @@
+//! Local stand-in for the room-listing service, keeping the public rooms in memory.
+//!
+//! Run with `cargo run --example room_listing_server [address]`, by default listening at `127.0.0.1:3537`,
+//! and point the game's room listing URL at `http://<address>/rooms`.
+
+use cosmic_spaceball_tactical_action_arena::{room_slug, RoomInfo};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3537";
+const ROOMS_PATH: &str = "/rooms";
+
+/// Rooms whose hosts have not been heard from for this long are considered abandoned.
+const ROOM_EXPIRY: Duration = Duration::from_secs(30);
+
+struct Response {
+    status: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn new(status: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+/// Rooms by their slugs, along with when they were last refreshed.
+type Rooms = HashMap<String, (RoomInfo, Instant)>;
+
+fn handle_request(rooms: &mut Rooms, method: &str, path: &str, body: &str) -> Response {
+    rooms.retain(|_, (_, refreshed_at)| refreshed_at.elapsed() < ROOM_EXPIRY);
+
+    match (method, path) {
+        // browsers ask before sending anything but the simplest requests
+        ("OPTIONS", _) => Response::new("204 No Content", ""),
+        ("GET", ROOMS_PATH) => {
+            let mut open_rooms: Vec<_> = rooms
+                .values()
+                .map(|(room, _)| room)
+                .filter(|room| room.is_open())
+                .collect();
+            open_rooms.sort_by(|a, b| a.name.cmp(&b.name));
+            match serde_json::to_string(&open_rooms) {
+                Ok(json) => Response::new("200 OK", json),
+                Err(error) => Response::new("500 Internal Server Error", error.to_string()),
+            }
+        }
+        ("POST", ROOMS_PATH) => match serde_json::from_str::<RoomInfo>(body) {
+            Ok(room) => {
+                println!(
+                    "Listing {} ({}/{})",
+                    room.name, room.players, room.max_players
+                );
+                rooms.insert(room_slug(&room.name), (room, Instant::now()));
+                Response::new("200 OK", "")
+            }
+            Err(error) => Response::new("400 Bad Request", error.to_string()),
+        },
+        ("DELETE", _) => match path
+            .strip_prefix(ROOMS_PATH)
+            .and_then(|rest| rest.strip_prefix('/'))
+        {
+            Some(slug) => {
+                if let Some((room, _)) = rooms.remove(slug) {
+                    println!("Unlisting {}", room.name);
+                }
+                Response::new("200 OK", "")
+            }
+            None => Response::new("404 Not Found", ""),
+        },
+        _ => Response::new("404 Not Found", ""),
+    }
+}
+
+fn handle_connection(rooms: &mut Rooms, stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut request_parts = request_line.split_whitespace();
+    let method = request_parts.next().unwrap_or_default().to_string();
+    let path = request_parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body);
+
+    let response = handle_request(rooms, &method, &path, &body);
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.0 {}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n\
+        Access-Control-Allow-Headers: Content-Type\r\n\
+        \r\n\
+        {}",
+        response.status,
+        response.body.len(),
+        response.body
+    )
+}
+
+fn main() -> std::io::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address)?;
+    println!("Listing rooms at http://{}{}", address, ROOMS_PATH);
+
+    let mut rooms = Rooms::new();
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| {
+            stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+            handle_connection(&mut rooms, stream)
+        });
+        if let Err(error) = result {
+            eprintln!("Failed to handle a request: {}", error);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use crate::network::confirmed::{
+    confirmed_frame, reset_confirmed_event_queue, ConfirmedEventQueue,
+};
+use crate::network::GGRSConfig;
+use crate::ui::user_settings::UserSettings;
+use crate::{GameState, GunPreset};
+use bevy::prelude::*;
+use bevy_ggrs::Session;
+use std::time::Duration;
+
+/// Minimum time between two empty-click sounds, so that holding the trigger through a reload doesn't turn into a buzz.
+const EMPTY_CLICK_INTERVAL: Duration = Duration::from_millis(250);
+
+const MUSIC_TRACK: &str = "sounds/music_arena.ogg";
+const MENU_HOVER_SOUND: &str = "sounds/ui_hover.ogg";
+const MENU_CLICK_SOUND: &str = "sounds/ui_click.ogg";
+
+/// Kind of a surface a projectile can hit, each sounding differently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Surface {
+    Character,
+    Obstacle,
+    Gear,
+    Glass,
+}
+
+/// Event of something audible happening in the simulation.
+/// Sent from the rollback systems, and played only once the frame it happened on is confirmed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundCue {
+    Fire(GunPreset),
+    Impact(Surface),
+    Reload,
+    EmptyClick,
+    Death,
+    Respawn,
+    Deploy,
+    Explosion,
+    Shatter,
+}
+
+impl SoundCue {
+    /// Get the path to the sound asset of the cue.
+    pub const fn path(&self) -> &'static str {
+        match self {
+            SoundCue::Fire(preset) => match preset {
+                GunPreset::Regular => "sounds/fire_regular.ogg",
+                GunPreset::Imprecise => "sounds/fire_imprecise.ogg",
+                GunPreset::RailGun => "sounds/fire_railgun.ogg",
+                GunPreset::Scattershot => "sounds/fire_scattershot.ogg",
+                GunPreset::Typhoon => "sounds/fire_typhoon.ogg",
+                GunPreset::LaserGun => "sounds/fire_laser_gun.ogg",
+                GunPreset::MinePlanter => "sounds/deploy.ogg",
+                GunPreset::TurretGun => "sounds/fire_turret.ogg",
+                GunPreset::RocketLauncher => "sounds/fire_rocket_launcher.ogg",
+                GunPreset::NanoSwarmLauncher => "sounds/fire_nano_swarm.ogg",
+                GunPreset::Boomerang => "sounds/fire_boomerang.ogg",
+            },
+            SoundCue::Impact(surface) => match surface {
+                Surface::Character => "sounds/impact_character.ogg",
+                Surface::Obstacle => "sounds/impact_obstacle.ogg",
+                Surface::Gear => "sounds/impact_gear.ogg",
+                Surface::Glass => "sounds/impact_glass.ogg",
+            },
+            SoundCue::Reload => "sounds/reload.ogg",
+            SoundCue::EmptyClick => "sounds/empty_click.ogg",
+            SoundCue::Death => "sounds/death.ogg",
+            SoundCue::Respawn => "sounds/respawn.ogg",
+            SoundCue::Deploy => "sounds/deploy.ogg",
+            SoundCue::Explosion => "sounds/explosion.ogg",
+            SoundCue::Shatter => "sounds/shatter.ogg",
+        }
+    }
+}
+
+/// Handle to the sink of the music currently playing, if any.
+#[derive(Resource, Debug, Default)]
+struct MusicSink(Option<Handle<AudioSink>>);
+
+/// System to play the queued sound cues of the frames that can no longer be rolled back.
+fn play_confirmed_sound_cues(
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    time: Res<Time>,
+    settings: Res<UserSettings>,
+    session: Option<Res<Session<GGRSConfig>>>,
+    mut queue: ResMut<ConfirmedEventQueue<SoundCue>>,
+    mut last_empty_click: Local<Option<Duration>>,
+) {
+    if queue.is_empty() {
+        return;
+    }
+
+    let mut played: Vec<(i32, SoundCue)> = vec![];
+    for (frame, cue) in queue.take_confirmed(confirmed_frame(session.as_deref())) {
+        // A shotgun blast shouldn't be a dozen times louder than a single pellet
+        if !played.contains(&(frame, cue)) {
+            played.push((frame, cue));
+        }
+    }
+
+    let volume = settings.effective_effects_volume();
+    if volume <= 0. {
+        return;
+    }
+    for (_, cue) in played {
+        if cue == SoundCue::EmptyClick {
+            let now = time.raw_elapsed();
+            if last_empty_click.is_some_and(|last| now - last < EMPTY_CLICK_INTERVAL) {
+                continue;
+            }
+            *last_empty_click = Some(now);
+        }
+        audio.play_with_settings(
+            asset_server.load(cue.path()),
+            PlaybackSettings::ONCE.with_volume(volume),
+        );
+    }
+}
+
+/// System to make buttons click and chirp when interacted with.
+fn play_menu_sounds(
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<UserSettings>,
+    query_buttons: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+) {
+    let volume = settings.effective_effects_volume();
+    for interaction in query_buttons.iter() {
+        let sound = match interaction {
+            Interaction::Hovered => MENU_HOVER_SOUND,
+            Interaction::Clicked => MENU_CLICK_SOUND,
+            Interaction::None => continue,
+        };
+        audio.play_with_settings(
+            asset_server.load(sound),
+            PlaybackSettings::ONCE.with_volume(volume),
+        );
+    }
+}
+
+fn start_music(
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    settings: Res<UserSettings>,
+    mut music: ResMut<MusicSink>,
+) {
+    let sink = audio.play_with_settings(
+        asset_server.load(MUSIC_TRACK),
+        PlaybackSettings::LOOP.with_volume(settings.effective_music_volume()),
+    );
+    // the handle returned is weak, and the sink would be dropped without a strong one
+    music.0 = Some(audio_sinks.get_handle(sink));
+}
+
+fn stop_music(audio_sinks: Res<Assets<AudioSink>>, mut music: ResMut<MusicSink>) {
+    if let Some(sink) = music.0.take().and_then(|handle| audio_sinks.get(&handle)) {
+        sink.stop();
+    }
+}
+
+/// System to keep the music volume in line with the settings.
+fn handle_music_volume_change(
+    settings: Res<UserSettings>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    music: Res<MusicSink>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(sink) = music.0.as_ref().and_then(|handle| audio_sinks.get(handle)) {
+        sink.set_volume(settings.effective_music_volume());
+    }
+}
+
+/// Plugin playing the game's sounds and music.
+/// The rollback system [`queue_confirmed_events`](crate::network::confirmed::queue_confirmed_events) of the sound cues must be scheduled separately.
+pub struct SpaceballsAudioPlugin;
+impl Plugin for SpaceballsAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SoundCue>()
+            .init_resource::<ConfirmedEventQueue<SoundCue>>()
+            .init_resource::<MusicSink>()
+            .add_system(
+                reset_confirmed_event_queue::<SoundCue>.in_schedule(OnEnter(GameState::InGame)),
+            )
+            .add_system(start_music.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(stop_music.in_schedule(OnExit(GameState::InGame)))
+            .add_system(play_confirmed_sound_cues.run_if(in_state(GameState::InGame)))
+            .add_system(play_menu_sounds)
+            .add_system(handle_music_volume_change);
+    }
+}
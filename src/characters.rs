@@ -1,21 +1,24 @@
 use crate::ai::AIActionRoutine;
+use crate::classes::{AbilityCooldown, CharacterClass};
 use crate::controls::CharacterActionInput;
 use crate::guns::{Equipped, Gun, GunBundle, GunPreset, LastUnequippedAt};
 use crate::health::{Health, HitPoints};
-use crate::network::PlayerHandle;
+use crate::modifiers::ModifierStack;
+use crate::network::{PlayerHandle, MAINTAINED_FPS_F64};
 use crate::physics::{
-    popular_collider, ActiveEvents, CollisionLayer, KinematicsBundle, OngoingCollisions, RigidBody,
-    Velocity,
+    popular_collider, ActiveEvents, CollisionLayer, KinematicsBundle, ObstacleMaterial,
+    OngoingCollisions, RigidBody, Velocity,
 };
-use crate::teams::{team_color, Team, TeamNumber};
+use crate::teams::{Team, TeamNumber, TeamPalette};
 use crate::EntropyGenerator;
 use bevy::hierarchy::{BuildChildren, Children};
 use bevy::math::{Vec2, Vec3};
 use bevy::prelude::{
-    Bundle, Changed, Commands, Component, Entity, Query, Res, Sprite, SpriteBundle, Time,
+    Bundle, Changed, Commands, Component, Entity, EulerRot, Query, Res, Sprite, SpriteBundle, Time,
     Transform, With, Without,
 };
 use bevy::utils::default;
+use bevy_rapier2d::prelude::{QueryFilter, RapierContext};
 use std::f32::consts::PI;
 use std::time::Duration;
 
@@ -27,6 +30,10 @@ pub const CHARACTER_SIZE: f32 = 50.0;
 pub const CHARACTER_SPEED: f32 = CHARACTER_SIZE * 4.0;
 /// Standard rotational speed at full capacity per second in radians.
 pub const CHARACTER_RAD_SPEED: f32 = PI;
+/// Share of the intended velocity a character touching ice gets each frame, the rest carried over from the previous one.
+const ICE_GRIP: f32 = 0.08;
+/// How far from ice a character still counts as touching it, so that resting against it counts too.
+const ICE_CONTACT_MARGIN: f32 = 1.0;
 
 /// The velocity of a gun when thrown. It is only a part of the calculation, the current character velocity is also taken into account.
 const GUN_THROW_SPEED: f32 = CHARACTER_SPEED * 2.0;
@@ -38,9 +45,15 @@ pub const CHARACTER_MAX_HEALTH: HitPoints = 100.0;
 
 /// Common trait for all character bodies/bundles when they're not referring to BaseCharacterBundle.
 pub trait BuildCharacter {
-    /// Make a new character bundle yet to be spawned, with the transform of the initial placement, the team and the online player handle assigned.
-    fn new(transform: Transform, team: TeamNumber, player_handle: usize) -> Self;
+    /// Make a new character bundle yet to be spawned, with the transform of the initial placement, the team, the online player handle and the class assigned.
+    fn new(
+        transform: Transform,
+        team: TeamNumber,
+        player_handle: usize,
+        class: CharacterClass,
+    ) -> Self;
     /// Spawn a character bundle and attach equipment to it, returning spawned entities, character first.
+    /// The class's own loadout is up to the caller, see [`CharacterClass::loadout`].
     fn spawn_with_equipment(
         self,
         commands: &mut Commands,
@@ -53,7 +66,10 @@ pub trait BuildCharacter {
 #[derive(Bundle)]
 pub struct BaseCharacterBundle {
     pub action_input: CharacterActionInput,
+    pub class: CharacterClass,
+    pub ability_cooldown: AbilityCooldown,
     pub health: Health,
+    pub modifiers: ModifierStack,
     pub team: Team,
     #[bundle]
     pub kinematics: KinematicsBundle,
@@ -63,10 +79,14 @@ pub struct BaseCharacterBundle {
 }
 
 impl BaseCharacterBundle {
-    fn new(transform: Transform, team: TeamNumber) -> Self {
+    fn new(mut transform: Transform, team: TeamNumber, class: CharacterClass) -> Self {
+        transform.scale *= Vec3::new(class.scale(), class.scale(), 1.0);
         Self {
             action_input: CharacterActionInput::default(),
-            health: Health::new(CHARACTER_MAX_HEALTH),
+            class,
+            ability_cooldown: AbilityCooldown::new(class),
+            health: Health::new(class.max_health()),
+            modifiers: ModifierStack::default(),
             team: Team(team),
             kinematics: KinematicsBundle::new(
                 popular_collider::square(CHARACTER_SIZE),
@@ -76,7 +96,8 @@ impl BaseCharacterBundle {
             active_physics_events: ActiveEvents::COLLISION_EVENTS,
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
-                    color: team_color(team),
+                    // repainted in the colors picked once spawned
+                    color: TeamPalette::default().team_color(team),
                     custom_size: Some(Vec2::new(CHARACTER_SIZE, CHARACTER_SIZE)),
                     ..default()
                 },
@@ -90,7 +111,6 @@ impl BaseCharacterBundle {
     pub fn spawn_equipment(
         commands: &mut Commands,
         character_id: Entity,
-        team: TeamNumber,
         mut random_state: EntropyGenerator,
         equipment: Vec<GunPreset>,
     ) -> Vec<Entity> {
@@ -98,7 +118,7 @@ impl BaseCharacterBundle {
 
         for gun_preset in equipment {
             let gun_id = commands
-                .spawn(GunBundle::new(gun_preset, None, random_state.fork()).with_paint_job(team))
+                .spawn(GunBundle::new(gun_preset, None, random_state.fork()))
                 .id();
 
             equip_gear(commands, character_id, gun_id, gun_preset, None);
@@ -109,7 +129,6 @@ impl BaseCharacterBundle {
 
     fn spawn_with_equipment<CharacterBundle: BuildCharacter + Bundle>(
         bundle: CharacterBundle,
-        team: TeamNumber,
         commands: &mut Commands,
         random_state: EntropyGenerator,
         equipment: Vec<GunPreset>,
@@ -119,7 +138,6 @@ impl BaseCharacterBundle {
         spawned_entities.append(&mut BaseCharacterBundle::spawn_equipment(
             commands,
             char_id,
-            team,
             random_state,
             equipment,
         ));
@@ -142,9 +160,14 @@ pub struct PlayerControlled {
 }
 
 impl BuildCharacter for PlayerCharacterBundle {
-    fn new(transform: Transform, team: TeamNumber, player_handle: usize) -> Self {
+    fn new(
+        transform: Transform,
+        team: TeamNumber,
+        player_handle: usize,
+        class: CharacterClass,
+    ) -> Self {
         Self {
-            character_bundle: BaseCharacterBundle::new(transform, team),
+            character_bundle: BaseCharacterBundle::new(transform, team, class),
             player_marker: PlayerControlled {
                 handle: player_handle,
             },
@@ -157,8 +180,7 @@ impl BuildCharacter for PlayerCharacterBundle {
         random_state: EntropyGenerator,
         equipment: Vec<GunPreset>,
     ) -> Vec<Entity> {
-        let team = self.character_bundle.team.0;
-        BaseCharacterBundle::spawn_with_equipment(self, team, commands, random_state, equipment)
+        BaseCharacterBundle::spawn_with_equipment(self, commands, random_state, equipment)
     }
 }
 
@@ -177,9 +199,14 @@ pub struct AIControlled;
 // pub peer_handle: usize,
 
 impl BuildCharacter for AICharacterBundle {
-    fn new(transform: Transform, team: TeamNumber, _player_handle: usize) -> Self {
+    fn new(
+        transform: Transform,
+        team: TeamNumber,
+        _player_handle: usize,
+        class: CharacterClass,
+    ) -> Self {
         Self {
-            character_bundle: BaseCharacterBundle::new(transform, team),
+            character_bundle: BaseCharacterBundle::new(transform, team, class),
             player_marker: AIControlled,
             ai_controller: AIActionRoutine::default(),
         }
@@ -191,14 +218,13 @@ impl BuildCharacter for AICharacterBundle {
         random_state: EntropyGenerator,
         equipment: Vec<GunPreset>,
     ) -> Vec<Entity> {
-        let team = self.character_bundle.team.0;
-        BaseCharacterBundle::spawn_with_equipment(self, team, commands, random_state, equipment)
+        BaseCharacterBundle::spawn_with_equipment(self, commands, random_state, equipment)
     }
 }
 
 /// Attach some equippable gear to a character and allow it to be interacted with.
-/// Unchecked if actually equippable, or if the equipping entity is a character!
-fn equip_gear(
+/// Unchecked if actually equippable, or if the equipping entity is a character (or a turret)!
+pub(crate) fn equip_gear(
     commands: &mut Commands,
     char_entity: Entity,
     gear_entity: Entity,
@@ -259,14 +285,77 @@ fn throw_away_gear(
     .with_rotation(char_transform.rotation);
 }
 
+/// Whether a character placed as in the transform touches any ice.
+///
+/// Asked of the character's own, rolled back, transform rather than of the contacts from the last physics step,
+/// so that a re-simulated frame sees the ice as it was then. Ice never moves, so the obstacles' side holds up.
+fn is_touching_ice(
+    rapier_context: &RapierContext,
+    query_obstacles: &Query<&ObstacleMaterial>,
+    transform: &Transform,
+) -> bool {
+    let shape = popular_collider::rect(
+        CHARACTER_SIZE * transform.scale.x + ICE_CONTACT_MARGIN * 2.0,
+        CHARACTER_SIZE * transform.scale.y + ICE_CONTACT_MARGIN * 2.0,
+    );
+    let predicate = |entity: Entity| {
+        query_obstacles
+            .get(entity)
+            .is_ok_and(|material| *material == ObstacleMaterial::Ice)
+    };
+    let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+    rapier_context
+        .intersection_with_shape(
+            transform.translation.truncate(),
+            angle,
+            &shape,
+            QueryFilter::default().predicate(&predicate),
+        )
+        .is_some()
+}
+
 /// System to convert a character's action input (human or not) to linear and angular velocities.
+/// A character touching ice only slowly gets a grip on where it wants to go, sliding along instead.
 pub fn calculate_character_velocity(
     // inputs: Res<PlayerInputs<GgrsConfig>>,
-    mut query: Query<(&mut Velocity, &Transform, &CharacterActionInput)>,
+    mut query: Query<(
+        &mut Velocity,
+        &Transform,
+        &CharacterActionInput,
+        &CharacterClass,
+        &ModifierStack,
+    )>,
+    query_obstacles: Query<&ObstacleMaterial>,
+    rapier_context: Res<RapierContext>,
 ) {
-    for (mut velocity, transform, action_input) in query.iter_mut() {
-        velocity.linvel = (transform.up() * action_input.speed() * CHARACTER_SPEED).truncate();
-        velocity.angvel = action_input.angular_speed() * -CHARACTER_RAD_SPEED;
+    for (mut velocity, transform, action_input, class, modifiers) in query.iter_mut() {
+        let speed = modifiers.speed(class.speed());
+        let is_on_ice = is_touching_ice(&rapier_context, &query_obstacles, transform);
+        // on ice, most of the previous momentum carries over
+        let steer = |linvel: Vec2, intended_linvel: Vec2| {
+            if is_on_ice {
+                linvel.lerp(intended_linvel, ICE_GRIP)
+            } else {
+                intended_linvel
+            }
+        };
+
+        if !action_input.twin_stick {
+            let intended_linvel = (transform.up() * action_input.speed() * speed).truncate();
+            velocity.linvel = steer(velocity.linvel, intended_linvel);
+            velocity.angvel = action_input.angular_speed() * -CHARACTER_RAD_SPEED;
+            continue;
+        }
+
+        let intended_linvel = action_input.world_direction() * speed;
+        velocity.linvel = steer(velocity.linvel, intended_linvel);
+        // turn towards the aim, but not so fast as to overshoot it within a frame
+        velocity.angvel = action_input.aim.map_or(0.0, |aim| {
+            let facing = transform.up().truncate();
+            let angle_to_aim = facing.angle_between(Vec2::from_angle(aim));
+            (angle_to_aim * MAINTAINED_FPS_F64 as f32)
+                .clamp(-CHARACTER_RAD_SPEED, CHARACTER_RAD_SPEED)
+        });
     }
 }
 
@@ -0,0 +1,274 @@
+//! Character classes, each with its own build, starting loadout and an active ability on a cooldown.
+
+use crate::audio::SoundCue;
+use crate::characters::{PlayerControlled, CHARACTER_MAX_HEALTH, CHARACTER_SIZE, CHARACTER_SPEED};
+use crate::controls::CharacterActionInput;
+use crate::deployables::{Deployable, DeployableBundle, DeployableKind};
+use crate::guns::GunPreset;
+use crate::health::{Health, HitPoints};
+use crate::modifiers::{ModifierStack, PowerUp};
+use crate::network::MAINTAINED_FPS_F64;
+use crate::teams::Team;
+use crate::EntropyGenerator;
+use bevy::prelude::{
+    Commands, Component, Entity, EventWriter, FromReflect, Query, Reflect, Res, ResMut, Time,
+    Timer, TimerMode, Transform,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Armor points the Tank's fortification grants.
+const FORTIFY_ARMOR: HitPoints = CHARACTER_MAX_HEALTH / 2.0;
+
+/// Build of a character, picked before spawning.
+#[derive(
+    Component,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Reflect,
+    FromReflect,
+    Serialize,
+    Deserialize,
+)]
+pub enum CharacterClass {
+    /// The all-rounder every character used to be.
+    #[default]
+    Soldier,
+    /// Big, slow and hard to bring down.
+    Tank,
+    /// Small, fast and fragile.
+    Scout,
+    /// Holds the ground with turrets.
+    Engineer,
+}
+
+/// Active ability of a class, used on demand and then left to cool down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClassAbility {
+    /// Projectiles deal more damage for a short while.
+    Adrenaline,
+    /// A layer of armor on top of the hit points.
+    Fortify,
+    /// A short burst of speed.
+    Sprint,
+    /// Set a turret down in front of the character.
+    Assemble,
+}
+
+impl ClassAbility {
+    /// Human-readable name of the ability.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            ClassAbility::Adrenaline => "Adrenaline",
+            ClassAbility::Fortify => "Fortify",
+            ClassAbility::Sprint => "Sprint",
+            ClassAbility::Assemble => "Turret",
+        }
+    }
+
+    /// Get the time it takes for the ability to be usable again.
+    pub const fn cooldown(&self) -> Duration {
+        match self {
+            ClassAbility::Adrenaline => Duration::from_secs(15),
+            ClassAbility::Fortify => Duration::from_secs(15),
+            ClassAbility::Sprint => Duration::from_secs(8),
+            ClassAbility::Assemble => Duration::from_secs(20),
+        }
+    }
+
+    /// Get the gear the ability sets down in front of the character, if that's what it does.
+    pub const fn deploys(&self) -> Option<DeployableKind> {
+        match self {
+            ClassAbility::Assemble => Some(DeployableKind::Turret),
+            _ => None,
+        }
+    }
+
+    /// Put the ability into effect on a character. Returns `false` if it had nothing to do, and is not to cool down.
+    /// Deploying gear is left to the caller, see [`ClassAbility::deploys`].
+    fn activate(&self, modifiers: &mut ModifierStack, health: &mut Health) -> bool {
+        match self {
+            ClassAbility::Adrenaline => {
+                modifiers.add_for(PowerUp::DamageBoost, Duration::from_secs(4));
+                true
+            }
+            ClassAbility::Fortify => health.add_armor(FORTIFY_ARMOR),
+            ClassAbility::Sprint => {
+                modifiers.add_for(PowerUp::SpeedBoost, Duration::from_secs(3));
+                true
+            }
+            ClassAbility::Assemble => false,
+        }
+    }
+}
+
+impl CharacterClass {
+    /// Human-readable name of the class.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            CharacterClass::Soldier => "Soldier",
+            CharacterClass::Tank => "Tank",
+            CharacterClass::Scout => "Scout",
+            CharacterClass::Engineer => "Engineer",
+        }
+    }
+
+    /// Get the class to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            CharacterClass::Soldier => CharacterClass::Tank,
+            CharacterClass::Tank => CharacterClass::Scout,
+            CharacterClass::Scout => CharacterClass::Engineer,
+            CharacterClass::Engineer => CharacterClass::Soldier,
+        }
+    }
+
+    /// Size of the character in comparison to the standard one.
+    pub const fn scale(&self) -> f32 {
+        match self {
+            CharacterClass::Soldier => 1.0,
+            CharacterClass::Tank => 1.35,
+            CharacterClass::Scout => 0.75,
+            CharacterClass::Engineer => 1.0,
+        }
+    }
+
+    /// Linear speed of the character per second at full capacity.
+    pub fn speed(&self) -> f32 {
+        CHARACTER_SPEED
+            * match self {
+                CharacterClass::Soldier => 1.0,
+                CharacterClass::Tank => 0.7,
+                CharacterClass::Scout => 1.4,
+                CharacterClass::Engineer => 0.9,
+            }
+    }
+
+    /// Hit points the character spawns with.
+    pub fn max_health(&self) -> HitPoints {
+        match self {
+            CharacterClass::Soldier => CHARACTER_MAX_HEALTH,
+            CharacterClass::Tank => CHARACTER_MAX_HEALTH * 2.0,
+            CharacterClass::Scout => CHARACTER_MAX_HEALTH * 0.6,
+            CharacterClass::Engineer => CHARACTER_MAX_HEALTH * 0.9,
+        }
+    }
+
+    /// Get the class's active ability.
+    pub const fn ability(&self) -> ClassAbility {
+        match self {
+            CharacterClass::Soldier => ClassAbility::Adrenaline,
+            CharacterClass::Tank => ClassAbility::Fortify,
+            CharacterClass::Scout => ClassAbility::Sprint,
+            CharacterClass::Engineer => ClassAbility::Assemble,
+        }
+    }
+
+    /// Get the guns the character spawns with. The Soldier makes do with whatever comes their way.
+    pub fn loadout(&self, random_state: &mut EntropyGenerator) -> Vec<GunPreset> {
+        match self {
+            CharacterClass::Soldier => vec![GunPreset::random(&mut random_state.0)],
+            CharacterClass::Tank => vec![GunPreset::Typhoon],
+            CharacterClass::Scout => vec![GunPreset::Imprecise],
+            CharacterClass::Engineer => vec![GunPreset::Scattershot],
+        }
+    }
+}
+
+/// Time left before a character's class ability can be used again.
+#[derive(Component, Clone, Debug, Default, Reflect, FromReflect)]
+pub struct AbilityCooldown(pub Timer);
+
+impl AbilityCooldown {
+    /// Make a cooldown for the class's ability, ready to be used right away.
+    pub fn new(class: CharacterClass) -> Self {
+        let cooldown = class.ability().cooldown();
+        let mut timer = Timer::new(cooldown, TimerMode::Once);
+        timer.tick(cooldown);
+        Self(timer)
+    }
+
+    /// Whether the ability can be used.
+    pub fn is_ready(&self) -> bool {
+        self.0.finished()
+    }
+
+    /// Time left before the ability can be used.
+    pub fn remaining(&self) -> Duration {
+        self.0.remaining()
+    }
+}
+
+/// System to cool the abilities down and to use them according to the characters' input.
+pub fn handle_class_abilities(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut random_state: ResMut<EntropyGenerator>,
+    mut sound_cues: EventWriter<SoundCue>,
+    mut query: Query<(
+        &CharacterActionInput,
+        &CharacterClass,
+        &mut AbilityCooldown,
+        &mut ModifierStack,
+        &mut Health,
+        &Team,
+        &Transform,
+        Option<&PlayerControlled>,
+    )>,
+    query_deployables: Query<(&Deployable, Entity)>,
+) {
+    for (
+        action_input,
+        class,
+        mut cooldown,
+        mut modifiers,
+        mut health,
+        team,
+        transform,
+        maybe_player,
+    ) in query.iter_mut()
+    {
+        if !cooldown.is_ready() {
+            // GGRS fixed ticks
+            cooldown
+                .0
+                .tick(Duration::from_secs_f64(1. / MAINTAINED_FPS_F64));
+            continue;
+        }
+        if !action_input.ability || health.is_dead() {
+            continue;
+        }
+
+        let ability = class.ability();
+        let is_activated = if let Some(kind) = ability.deploys() {
+            let deploy_offset = (CHARACTER_SIZE * transform.scale.y + kind.size()) / 2.0;
+            Deployable::deploy(
+                &mut commands,
+                &query_deployables,
+                DeployableBundle::new(
+                    kind,
+                    maybe_player.map(|player| player.handle),
+                    team.0,
+                    Transform::from_translation(
+                        transform.translation + transform.up() * deploy_offset,
+                    )
+                    .with_rotation(transform.rotation),
+                    time.elapsed(),
+                ),
+                &mut random_state,
+            );
+            sound_cues.send(SoundCue::Deploy);
+            true
+        } else {
+            ability.activate(&mut modifiers, &mut health)
+        };
+        if is_activated {
+            cooldown.0.reset();
+        }
+    }
+}
@@ -1,21 +1,30 @@
+pub mod bindings;
+pub mod devices;
+
 use crate::characters::PlayerControlled;
+use crate::controls::bindings::ControlScheme;
+use crate::controls::devices::{ConnectedGamepads, LocalInputDevice};
+use crate::network::session::LocalPlayers;
 use crate::network::{GGRSConfig, GGRSInput};
 use crate::ui::input_consumption::{
     ActiveInputConsumerLayers, GAME_INPUT_LAYER, PAUSE_INPUT_LAYER,
 };
+use crate::ui::touch::TouchActions;
+use crate::ui::user_settings::UserSettings;
 use crate::GamePauseEvent;
 use bevy::ecs::schedule::SystemSet;
 use bevy::input::{
     gamepad::{
-        Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, GamepadConnection,
-        GamepadConnectionEvent,
+        GamepadAxis, GamepadButton, GamepadButtonType, GamepadConnection, GamepadConnectionEvent,
     },
     Axis, Input,
 };
 use bevy::prelude::{
-    Commands, Component, EventReader, EventWriter, In, KeyCode, Local, Query, Res, Resource,
+    Camera, Component, EventReader, EventWriter, GlobalTransform, In, KeyCode, Local, MouseButton,
+    Query, Res, ResMut, Transform, Vec2, With,
 };
 use bevy::reflect::{FromReflect, Reflect};
+use bevy::window::{PrimaryWindow, Window};
 use bevy_ggrs::{ggrs, PlayerInputs};
 
 /// Set of systems for input handling for better organisation in the schedule.
@@ -46,6 +55,15 @@ pub struct CharacterActionInput {
     /// Whether an auxiliary environmental interactive action must be triggered this frame,
     /// such as throwing equipped guns away.
     pub interact_2: bool,
+    /// Whether the character's class ability must be used this frame.
+    pub ability: bool,
+
+    /// Whether `up` and `right` move along the world axes instead of the character's facing,
+    /// with the character turning towards `aim`.
+    pub twin_stick: bool,
+    /// Angle in radians to turn the character's facing to, counter-clockwise from the world's right.
+    /// Only followed in the twin-stick scheme.
+    pub aim: Option<f32>,
 }
 
 impl CharacterActionInput {
@@ -63,6 +81,11 @@ impl CharacterActionInput {
     pub fn angular_speed(&self) -> f32 {
         self.right.clamp(-1.0, 1.0)
     }
+
+    /// Get direction of movement along the world axes, for the twin-stick scheme.
+    pub fn world_direction(&self) -> Vec2 {
+        Vec2::new(self.right, self.up).clamp_length_max(1.0)
+    }
 }
 
 /// System to record the players' online inputs (local and received) to the input struct used by the actuator systems.
@@ -90,146 +113,81 @@ pub fn handle_online_player_input(
 }
 
 /// GGRS input system to record and convert local player input to the GGRS input structure.
+/// Called for every local player, each reading their own input device.
 pub fn process_input(
-    _: In<ggrs::PlayerHandle>,
+    handle: In<ggrs::PlayerHandle>,
     keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
     input_consumers: Res<ActiveInputConsumerLayers>,
-    connected_gamepad: Option<Res<GamepadWrapper>>,
+    settings: Res<UserSettings>,
+    local_players: Option<Res<LocalPlayers>>,
+    gamepads: Res<ConnectedGamepads>,
     gamepad_axes: Res<Axis<GamepadAxis>>,
     gamepad_buttons: Res<Input<GamepadButton>>,
-    //mut query: Query<&mut CharacterActionInput, With<PlayerControlled>>,
+    touch_actions: Res<TouchActions>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    character_query: Query<(&PlayerControlled, &Transform)>,
 ) -> GGRSInput {
     let mut player_actions = CharacterActionInput::default();
 
     if input_consumers.is_input_allowed_for_layer(&GAME_INPUT_LAYER) {
-        process_keyboard_input(&mut player_actions, &keyboard);
-        #[cfg(target_arch = "wasm32")]
-        process_js_joysticks_input(&mut player_actions);
-        process_gamepad_input(
-            &mut player_actions,
-            &connected_gamepad,
+        let local_index = local_players
+            .as_ref()
+            .and_then(|local_players| local_players.index_of(handle.0));
+        // a local player left without a device when the session started gets no say
+        let device = match local_players {
+            Some(local_players) => local_players.device_of(handle.0),
+            None => Some(LocalInputDevice::Everything),
+        };
+        let Some(device) = device else {
+            return player_actions.into();
+        };
+        let sources = device.sources(
+            &keyboard,
+            &mouse,
+            &gamepads,
             &gamepad_axes,
             &gamepad_buttons,
         );
-    }
-
-    player_actions.into()
-}
-
-/// Map external keyboard input to a player's character action input.
-fn process_keyboard_input(actions: &mut CharacterActionInput, keyboard: &Input<KeyCode>) {
-    let set_flag_if_keys_changed = |action: &mut bool, action_keys: Vec<KeyCode>| {
-        let any_key_pressed = keyboard.any_pressed(action_keys);
-        if any_key_pressed {
-            *action |= any_key_pressed;
+        settings.bindings.apply(&mut player_actions, &sources);
+        if device == LocalInputDevice::Everything {
+            touch_actions.apply(&mut player_actions);
         }
-    };
-
-    let set_axis_if_keys_changed =
-        |action: &mut f32, pos_action_keys: Vec<KeyCode>, neg_action_keys: Vec<KeyCode>| {
-            let any_pos_key_pressed = keyboard.any_pressed(pos_action_keys);
-            let any_neg_key_pressed = keyboard.any_pressed(neg_action_keys);
-
-            if any_pos_key_pressed || any_neg_key_pressed {
-                *action += any_pos_key_pressed as i32 as f32 - any_neg_key_pressed as i32 as f32;
-            }
-        };
-
-    set_axis_if_keys_changed(
-        &mut actions.up,
-        vec![KeyCode::W, KeyCode::Up],
-        vec![KeyCode::S, KeyCode::Down],
-    );
-    set_axis_if_keys_changed(
-        &mut actions.right,
-        vec![KeyCode::D, KeyCode::Right],
-        vec![KeyCode::A, KeyCode::Left],
-    );
-    set_flag_if_keys_changed(&mut actions.fire, vec![KeyCode::Space]);
-    set_flag_if_keys_changed(&mut actions.reload, vec![KeyCode::R]);
-    set_flag_if_keys_changed(&mut actions.interact_1, vec![KeyCode::F]);
-    set_flag_if_keys_changed(&mut actions.interact_2, vec![KeyCode::C]);
-}
-
-#[cfg(target_arch = "wasm32")]
-fn process_js_joysticks_input(actions: &mut CharacterActionInput) {
-    let js_input = crate::js_interop::get_sticks_positions_from_js();
-    actions.up += js_input[1];
-    actions.right += js_input[0];
-}
-
-/*
-#[cfg_attr(
-    feature = "serialize",
-    derive(serde::Serialize, serde::Deserialize),
-    reflect(Serialize, Deserialize)
-)]
- */
-/// To-be resource holding the connected gamepad ID.
-#[derive(Resource)]
-pub struct GamepadWrapper(Gamepad);
-
-/// Map gamepad input to a player's character action input.
-pub fn process_gamepad_input(
-    actions: &mut CharacterActionInput,
-    connected_gamepad: &Option<Res<GamepadWrapper>>,
-    axes: &Axis<GamepadAxis>,
-    buttons: &Input<GamepadButton>,
-) {
-    let gamepad = if let Some(gp) = connected_gamepad {
-        gp.0
-    } else {
-        // no gamepad is connected
-        return;
-    };
-
-    let axis_lx = GamepadAxis {
-        gamepad,
-        axis_type: GamepadAxisType::LeftStickX,
-    };
-    let axis_ly = GamepadAxis {
-        gamepad,
-        axis_type: GamepadAxisType::LeftStickY,
-    };
 
-    if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-        const DEAD_ZONE: f32 = 0.25;
-        if y.abs() > DEAD_ZONE {
-            actions.up += y;
-        }
-        if x.abs() > DEAD_ZONE {
-            actions.right += x;
+        if settings.control_scheme(local_index.unwrap_or(0)) == ControlScheme::TwinStick {
+            player_actions.twin_stick = true;
+            // the stick has the final say, then the mouse, and failing both, the character looks where it goes
+            player_actions.aim = settings
+                .bindings
+                .aim_stick_angle(&sources)
+                .or_else(|| {
+                    sources.mouse?;
+                    let cursor = primary_window_query.get_single().ok()?.cursor_position()?;
+                    let (camera, camera_transform) = camera_query.get_single().ok()?;
+                    let cursor = camera
+                        .viewport_to_world(camera_transform, cursor)?
+                        .origin
+                        .truncate();
+                    let (_, character_transform) = character_query
+                        .iter()
+                        .find(|(player, _)| player.handle == handle.0)?;
+                    let direction = cursor - character_transform.translation.truncate();
+                    (direction != Vec2::ZERO).then(|| direction.y.atan2(direction.x))
+                })
+                .or_else(|| {
+                    let direction = player_actions.world_direction();
+                    (direction != Vec2::ZERO).then(|| direction.y.atan2(direction.x))
+                });
         }
     }
 
-    macro_rules! set_flag_if_pressed {
-        ($action:expr, [$($e:expr),*]) => {{
-            $(
-                let button = GamepadButton {
-                    gamepad,
-                    button_type: $e,
-                };
-                $action |= buttons.pressed(button);
-            )*
-        }
-    }}
-
-    set_flag_if_pressed!(
-        actions.fire,
-        [GamepadButtonType::RightTrigger2, GamepadButtonType::South]
-    );
-    set_flag_if_pressed!(
-        actions.reload,
-        [GamepadButtonType::RightTrigger, GamepadButtonType::West]
-    );
-    set_flag_if_pressed!(actions.interact_1, [GamepadButtonType::North]);
-    set_flag_if_pressed!(actions.interact_2, [GamepadButtonType::East]);
+    player_actions.into()
 }
 
 /// System to track gamepad connections and disconnections.
 pub fn handle_gamepad_connections(
-    mut commands: Commands,
-    connected_gamepad: Option<Res<GamepadWrapper>>,
+    mut gamepads: ResMut<ConnectedGamepads>,
     mut gamepad_events: EventReader<GamepadConnectionEvent>,
 ) {
     for ev in gamepad_events.iter() {
@@ -243,30 +201,26 @@ pub fn handle_gamepad_connections(
                     info.name
                 );
 
-                // if we don't have any gamepad yet, use this one. Fix it for local multiplayer.
-                if connected_gamepad.is_none() {
-                    commands.insert_resource(GamepadWrapper(id));
+                if !gamepads.0.contains(&id) {
+                    gamepads.0.push(id);
                 }
             }
             GamepadConnection::Disconnected => {
                 bevy::log::info!("Lost gamepad connection with ID: {:?}", id);
 
-                if let Some(GamepadWrapper(old_id)) = connected_gamepad.as_deref() {
-                    if *old_id == id {
-                        commands.remove_resource::<GamepadWrapper>();
-                    }
-                }
+                // the gamepads connected after this one are shifted over to the players before
+                gamepads.0.retain(|gamepad| *gamepad != id);
             }
         }
     }
 }
 
-/// System that listens for pause inputs either from a keyboard or a connected gamepad,
+/// System that listens for pause inputs either from a keyboard or any connected gamepad,
 /// sending a `GamePauseEvent::Toggle` event.
 pub fn handle_pause_input(
     keyboard: Res<Input<KeyCode>>,
     input_consumers: Res<ActiveInputConsumerLayers>,
-    connected_gamepad: Option<Res<GamepadWrapper>>,
+    gamepads: Res<ConnectedGamepads>,
     gamepad_buttons: Res<Input<GamepadButton>>,
     mut pause_events: EventWriter<GamePauseEvent>,
 ) {
@@ -275,14 +229,12 @@ pub fn handle_pause_input(
     }
 
     if keyboard.just_pressed(KeyCode::Escape)
-        || connected_gamepad
-            .filter(|gp| {
-                gamepad_buttons.pressed(GamepadButton {
-                    gamepad: gp.0,
-                    button_type: GamepadButtonType::Start,
-                })
+        || gamepads.0.iter().any(|gamepad| {
+            gamepad_buttons.pressed(GamepadButton {
+                gamepad: *gamepad,
+                button_type: GamepadButtonType::Start,
             })
-            .is_some()
+        })
     {
         pause_events.send(GamePauseEvent::Toggle);
     }
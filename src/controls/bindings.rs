@@ -0,0 +1,362 @@
+use crate::controls::devices::KeyboardRegion;
+use crate::controls::CharacterActionInput;
+use bevy::input::gamepad::{
+    Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType,
+};
+use bevy::input::{Axis, Input};
+use bevy::prelude::{KeyCode, MouseButton, Vec2};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Default distance a gamepad stick has to travel before its input is registered.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.25;
+
+/// Something a player can make their character do, which any number of inputs can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    Forward,
+    Backward,
+    TurnRight,
+    TurnLeft,
+    Fire,
+    Reload,
+    Interact1,
+    Interact2,
+    Ability,
+}
+
+impl InputAction {
+    /// Every action, in the order they should be listed.
+    pub const ALL: [InputAction; 9] = [
+        InputAction::Forward,
+        InputAction::Backward,
+        InputAction::TurnRight,
+        InputAction::TurnLeft,
+        InputAction::Fire,
+        InputAction::Reload,
+        InputAction::Interact1,
+        InputAction::Interact2,
+        InputAction::Ability,
+    ];
+
+    /// Human-readable name of the action.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            InputAction::Forward => "Forward",
+            InputAction::Backward => "Backward",
+            InputAction::TurnRight => "Turn right",
+            InputAction::TurnLeft => "Turn left",
+            InputAction::Fire => "Fire",
+            InputAction::Reload => "Reload",
+            InputAction::Interact1 => "Pick up",
+            InputAction::Interact2 => "Throw away",
+            InputAction::Ability => "Ability",
+        }
+    }
+}
+
+/// The way the characters are steered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlScheme {
+    /// Thrust forward and backward along the facing, turning left and right.
+    #[default]
+    Tank,
+    /// Move along the world axes, facing the mouse cursor or wherever the right stick points.
+    TwinStick,
+}
+
+impl ControlScheme {
+    /// Human-readable name of the scheme.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            ControlScheme::Tank => "Tank",
+            ControlScheme::TwinStick => "Twin-stick",
+        }
+    }
+
+    /// Get the scheme to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            ControlScheme::Tank => ControlScheme::TwinStick,
+            ControlScheme::TwinStick => ControlScheme::Tank,
+        }
+    }
+}
+
+/// Direction a gamepad axis has to be pushed in to trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A single physical input that can be bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType, AxisDirection),
+}
+
+impl Display for InputBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputBinding::Key(key) => write!(f, "{:?}", key),
+            InputBinding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            InputBinding::GamepadButton(button) => write!(f, "Pad {:?}", button),
+            InputBinding::GamepadAxis(axis, direction) => write!(
+                f,
+                "Pad {:?}{}",
+                axis,
+                match direction {
+                    AxisDirection::Positive => "+",
+                    AxisDirection::Negative => "-",
+                }
+            ),
+        }
+    }
+}
+
+/// Everything the bindings read the state of the inputs from. Devices not given are not read.
+pub struct InputSources<'a> {
+    pub keyboard: Option<&'a Input<KeyCode>>,
+    /// Part of the keyboard whose keys are read, so that the players sharing it don't press each other's buttons.
+    pub key_region: KeyboardRegion,
+    pub mouse: Option<&'a Input<MouseButton>>,
+    pub gamepad: Option<Gamepad>,
+    pub gamepad_axes: &'a Axis<GamepadAxis>,
+    pub gamepad_buttons: &'a Input<GamepadButton>,
+}
+
+/// Map of the actions to the inputs that trigger them, along with the gamepad dead zones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputBindings {
+    bindings: HashMap<InputAction, Vec<InputBinding>>,
+    /// Dead zone of the gamepad axes not listed in the overrides.
+    pub dead_zone: f32,
+    pub dead_zone_overrides: HashMap<GamepadAxisType, f32>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let bindings = HashMap::from_iter([
+            (
+                InputAction::Forward,
+                vec![
+                    InputBinding::Key(KeyCode::W),
+                    InputBinding::Key(KeyCode::Up),
+                    InputBinding::GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+                ],
+            ),
+            (
+                InputAction::Backward,
+                vec![
+                    InputBinding::Key(KeyCode::S),
+                    InputBinding::Key(KeyCode::Down),
+                    InputBinding::GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Negative),
+                ],
+            ),
+            (
+                InputAction::TurnRight,
+                vec![
+                    InputBinding::Key(KeyCode::D),
+                    InputBinding::Key(KeyCode::Right),
+                    InputBinding::GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Positive),
+                ],
+            ),
+            (
+                InputAction::TurnLeft,
+                vec![
+                    InputBinding::Key(KeyCode::A),
+                    InputBinding::Key(KeyCode::Left),
+                    InputBinding::GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Negative),
+                ],
+            ),
+            (
+                InputAction::Fire,
+                vec![
+                    InputBinding::Key(KeyCode::Space),
+                    InputBinding::Key(KeyCode::RControl),
+                    InputBinding::GamepadButton(GamepadButtonType::RightTrigger2),
+                    InputBinding::GamepadButton(GamepadButtonType::South),
+                ],
+            ),
+            (
+                InputAction::Reload,
+                vec![
+                    InputBinding::Key(KeyCode::R),
+                    InputBinding::Key(KeyCode::RShift),
+                    InputBinding::GamepadButton(GamepadButtonType::RightTrigger),
+                    InputBinding::GamepadButton(GamepadButtonType::West),
+                ],
+            ),
+            (
+                InputAction::Interact1,
+                vec![
+                    InputBinding::Key(KeyCode::F),
+                    InputBinding::Key(KeyCode::Period),
+                    InputBinding::GamepadButton(GamepadButtonType::North),
+                ],
+            ),
+            (
+                InputAction::Interact2,
+                vec![
+                    InputBinding::Key(KeyCode::C),
+                    InputBinding::Key(KeyCode::Comma),
+                    InputBinding::GamepadButton(GamepadButtonType::East),
+                ],
+            ),
+            (
+                InputAction::Ability,
+                vec![
+                    InputBinding::Key(KeyCode::Q),
+                    InputBinding::Key(KeyCode::Slash),
+                    InputBinding::GamepadButton(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+        ]);
+
+        Self {
+            bindings,
+            dead_zone: DEFAULT_DEAD_ZONE,
+            dead_zone_overrides: HashMap::default(),
+        }
+    }
+}
+
+impl InputBindings {
+    /// Get the inputs bound to an action.
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings
+            .get(&action)
+            .map_or(&[], |bindings| bindings.as_slice())
+    }
+
+    /// Find the action an input is bound to, if any.
+    pub fn action_bound_to(&self, binding: InputBinding) -> Option<InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .find(|action| self.bindings(*action).contains(&binding))
+    }
+
+    /// Bind an input to an action. An input can only trigger a single action,
+    /// so if it was bound to another one, it gets unbound, and that action is returned.
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) -> Option<InputAction> {
+        let conflicting_action = self
+            .action_bound_to(binding)
+            .filter(|bound_action| *bound_action != action);
+        if let Some(conflicting_action) = conflicting_action {
+            if let Some(bindings) = self.bindings.get_mut(&conflicting_action) {
+                bindings.retain(|bound| *bound != binding);
+            }
+        }
+
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        conflicting_action
+    }
+
+    /// Bind the default inputs of an action, save for those already bound to something else.
+    pub fn bind_defaults(&mut self, action: InputAction) {
+        for binding in InputBindings::default().bindings(action) {
+            if self.action_bound_to(*binding).is_none() {
+                self.bind(action, *binding);
+            }
+        }
+    }
+
+    /// Remove all inputs bound to an action.
+    pub fn clear(&mut self, action: InputAction) {
+        self.bindings.remove(&action);
+    }
+
+    /// Get the distance a gamepad axis has to travel before its input is registered.
+    pub fn dead_zone(&self, axis: GamepadAxisType) -> f32 {
+        self.dead_zone_overrides
+            .get(&axis)
+            .copied()
+            .unwrap_or(self.dead_zone)
+    }
+
+    /// Get how strongly a single input is triggered, from 0 to 1.
+    fn binding_strength(&self, binding: InputBinding, sources: &InputSources) -> f32 {
+        let pressed = match binding {
+            InputBinding::Key(key) => {
+                sources.key_region.contains(key)
+                    && sources
+                        .keyboard
+                        .is_some_and(|keyboard| keyboard.pressed(key))
+            }
+            InputBinding::Mouse(button) => sources.mouse.is_some_and(|mouse| mouse.pressed(button)),
+            InputBinding::GamepadButton(button_type) => sources.gamepad.is_some_and(|gamepad| {
+                sources.gamepad_buttons.pressed(GamepadButton {
+                    gamepad,
+                    button_type,
+                })
+            }),
+            InputBinding::GamepadAxis(axis_type, direction) => {
+                let value = sources
+                    .gamepad
+                    .and_then(|gamepad| {
+                        sources.gamepad_axes.get(GamepadAxis { gamepad, axis_type })
+                    })
+                    .unwrap_or(0.0);
+                let value = match direction {
+                    AxisDirection::Positive => value,
+                    AxisDirection::Negative => -value,
+                };
+                return if value > self.dead_zone(axis_type) {
+                    value.min(1.0)
+                } else {
+                    0.0
+                };
+            }
+        };
+        pressed as i32 as f32
+    }
+
+    /// Get how strongly an action is triggered by any of its inputs, from 0 to 1.
+    pub fn action_strength(&self, action: InputAction, sources: &InputSources) -> f32 {
+        self.bindings(action)
+            .iter()
+            .map(|binding| self.binding_strength(*binding, sources))
+            .fold(0.0, f32::max)
+    }
+
+    /// Get the angle the right stick points at, counter-clockwise from the right, if it's pushed past its dead zone.
+    pub fn aim_stick_angle(&self, sources: &InputSources) -> Option<f32> {
+        let gamepad = sources.gamepad?;
+        let axis = |axis_type| {
+            sources
+                .gamepad_axes
+                .get(GamepadAxis { gamepad, axis_type })
+                .unwrap_or(0.0)
+        };
+        let stick = Vec2::new(
+            axis(GamepadAxisType::RightStickX),
+            axis(GamepadAxisType::RightStickY),
+        );
+        let dead_zone = self
+            .dead_zone(GamepadAxisType::RightStickX)
+            .max(self.dead_zone(GamepadAxisType::RightStickY));
+        (stick.length() > dead_zone).then(|| stick.y.atan2(stick.x))
+    }
+
+    /// Map the state of the bound inputs to a player's character action input.
+    pub fn apply(&self, actions: &mut CharacterActionInput, sources: &InputSources) {
+        let strength = |action| self.action_strength(action, sources);
+
+        actions.up += strength(InputAction::Forward) - strength(InputAction::Backward);
+        actions.right += strength(InputAction::TurnRight) - strength(InputAction::TurnLeft);
+        actions.fire |= strength(InputAction::Fire) > 0.0;
+        actions.reload |= strength(InputAction::Reload) > 0.0;
+        actions.interact_1 |= strength(InputAction::Interact1) > 0.0;
+        actions.interact_2 |= strength(InputAction::Interact2) > 0.0;
+        actions.ability |= strength(InputAction::Ability) > 0.0;
+    }
+}
@@ -0,0 +1,233 @@
+use crate::controls::bindings::InputSources;
+use bevy::input::gamepad::{Gamepad, GamepadAxis, GamepadButton};
+use bevy::input::{Axis, Input};
+use bevy::prelude::{KeyCode, MouseButton, Resource};
+use std::fmt::{Display, Formatter};
+
+/// Maximum number of players sharing a single machine.
+pub const MAX_LOCAL_PLAYERS: usize = 4;
+
+/// Keys on the left side of the keyboard, the rest belonging to the right one.
+/// The space bar goes to the left player, for the right one to fire with the right control key.
+const LEFT_HALF_KEYS: [KeyCode; 28] = [
+    KeyCode::Escape,
+    KeyCode::Grave,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Tab,
+    KeyCode::Q,
+    KeyCode::W,
+    KeyCode::E,
+    KeyCode::R,
+    KeyCode::T,
+    KeyCode::Capital,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::LShift,
+    KeyCode::Z,
+    KeyCode::X,
+    KeyCode::C,
+    KeyCode::V,
+    KeyCode::B,
+    KeyCode::LControl,
+    KeyCode::LAlt,
+    KeyCode::Space,
+];
+
+/// Resource of the gamepads currently connected, in the order they were connected in.
+#[derive(Resource, Debug, Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+/// Part of the keyboard a player is allowed to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyboardRegion {
+    Whole,
+    LeftHalf,
+    RightHalf,
+}
+
+impl KeyboardRegion {
+    /// Whether the key lies in this region of the keyboard.
+    pub fn contains(&self, key: KeyCode) -> bool {
+        match self {
+            KeyboardRegion::Whole => true,
+            KeyboardRegion::LeftHalf => LEFT_HALF_KEYS.contains(&key),
+            KeyboardRegion::RightHalf => !LEFT_HALF_KEYS.contains(&key),
+        }
+    }
+}
+
+/// Input device a local player controls their character with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalInputDevice {
+    /// The keyboard, the mouse and the first gamepad. Used when there is nobody to share them with.
+    Everything,
+    /// The keyboard and the mouse.
+    Keyboard,
+    /// The left half of the keyboard and the mouse.
+    KeyboardLeft,
+    /// The right half of the keyboard.
+    KeyboardRight,
+    /// A gamepad, connected when it was handed out. Gives no input while it's disconnected.
+    Gamepad(Gamepad),
+}
+
+impl Display for LocalInputDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalInputDevice::Everything => write!(f, "Any device"),
+            LocalInputDevice::Keyboard => write!(f, "Keyboard"),
+            LocalInputDevice::KeyboardLeft => write!(f, "Keyboard left"),
+            LocalInputDevice::KeyboardRight => write!(f, "Keyboard right"),
+            LocalInputDevice::Gamepad(gamepad) => write!(f, "Gamepad {}", gamepad.id + 1),
+        }
+    }
+}
+
+impl LocalInputDevice {
+    /// Most local players the devices can serve: two on the halves of the keyboard, and one on every gamepad.
+    pub fn max_local_players(gamepads: &ConnectedGamepads) -> usize {
+        (2 + gamepads.0.len()).min(MAX_LOCAL_PLAYERS)
+    }
+
+    /// Hand out the connected devices to the local players, in their order.
+    /// Gamepads are given to the last players, and whoever is left shares the keyboard.
+    /// Only as many players as the devices can serve get one.
+    pub fn assign(
+        local_player_count: usize,
+        gamepads: &ConnectedGamepads,
+    ) -> Vec<LocalInputDevice> {
+        if local_player_count <= 1 {
+            return vec![LocalInputDevice::Everything];
+        }
+        let local_player_count = local_player_count.min(Self::max_local_players(gamepads));
+
+        let mut devices = match local_player_count.saturating_sub(gamepads.0.len()) {
+            0 => vec![],
+            1 => vec![LocalInputDevice::Keyboard],
+            _ => vec![
+                LocalInputDevice::KeyboardLeft,
+                LocalInputDevice::KeyboardRight,
+            ],
+        };
+        let keyboard_players = devices.len();
+        devices.extend(
+            gamepads.0[..local_player_count - keyboard_players]
+                .iter()
+                .copied()
+                .map(LocalInputDevice::Gamepad),
+        );
+        devices
+    }
+
+    /// Gather the state of the inputs this device is made of.
+    pub fn sources<'a>(
+        &self,
+        keyboard: &'a Input<KeyCode>,
+        mouse: &'a Input<MouseButton>,
+        gamepads: &ConnectedGamepads,
+        gamepad_axes: &'a Axis<GamepadAxis>,
+        gamepad_buttons: &'a Input<GamepadButton>,
+    ) -> InputSources<'a> {
+        let (keyboard, key_region, mouse, gamepad) = match self {
+            LocalInputDevice::Everything => (
+                Some(keyboard),
+                KeyboardRegion::Whole,
+                Some(mouse),
+                gamepads.0.first().copied(),
+            ),
+            LocalInputDevice::Keyboard => {
+                (Some(keyboard), KeyboardRegion::Whole, Some(mouse), None)
+            }
+            LocalInputDevice::KeyboardLeft => {
+                (Some(keyboard), KeyboardRegion::LeftHalf, Some(mouse), None)
+            }
+            LocalInputDevice::KeyboardRight => {
+                (Some(keyboard), KeyboardRegion::RightHalf, None, None)
+            }
+            LocalInputDevice::Gamepad(gamepad) => (
+                None,
+                KeyboardRegion::Whole,
+                None,
+                gamepads.0.contains(gamepad).then_some(*gamepad),
+            ),
+        };
+
+        InputSources {
+            keyboard,
+            key_region,
+            mouse,
+            gamepad,
+            gamepad_axes,
+            gamepad_buttons,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamepads(count: usize) -> ConnectedGamepads {
+        ConnectedGamepads((0..count).map(Gamepad::new).collect())
+    }
+
+    #[test]
+    fn gives_a_lone_player_everything() {
+        for gamepad_count in 0..3 {
+            assert_eq!(
+                LocalInputDevice::assign(1, &gamepads(gamepad_count)),
+                vec![LocalInputDevice::Everything]
+            );
+        }
+    }
+
+    #[test]
+    fn gives_gamepads_to_the_last_players() {
+        assert_eq!(
+            LocalInputDevice::assign(2, &gamepads(1)),
+            vec![
+                LocalInputDevice::Keyboard,
+                LocalInputDevice::Gamepad(Gamepad::new(0))
+            ]
+        );
+        assert_eq!(
+            LocalInputDevice::assign(3, &gamepads(1)),
+            vec![
+                LocalInputDevice::KeyboardLeft,
+                LocalInputDevice::KeyboardRight,
+                LocalInputDevice::Gamepad(Gamepad::new(0))
+            ]
+        );
+        assert_eq!(
+            LocalInputDevice::assign(2, &gamepads(3)),
+            vec![
+                LocalInputDevice::Gamepad(Gamepad::new(0)),
+                LocalInputDevice::Gamepad(Gamepad::new(1))
+            ]
+        );
+    }
+
+    #[test]
+    fn never_hands_out_missing_gamepads() {
+        for gamepad_count in 0..MAX_LOCAL_PLAYERS {
+            let gamepads = gamepads(gamepad_count);
+            let devices = LocalInputDevice::assign(MAX_LOCAL_PLAYERS, &gamepads);
+            assert_eq!(
+                devices.len(),
+                LocalInputDevice::max_local_players(&gamepads)
+            );
+            assert!(devices.iter().all(|device| match device {
+                LocalInputDevice::Gamepad(gamepad) => gamepads.0.contains(gamepad),
+                _ => true,
+            }));
+        }
+        assert_eq!(LocalInputDevice::assign(4, &gamepads(0)).len(), 2);
+    }
+}
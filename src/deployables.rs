@@ -0,0 +1,325 @@
+//! Gear set down into the world by a player, holding its ground and acting on its own: turrets and mines.
+
+use crate::audio::SoundCue;
+use crate::characters::{equip_gear, PlayerControlled, CHARACTER_MAX_HEALTH, CHARACTER_SIZE};
+use crate::controls::CharacterActionInput;
+use crate::guns::{GunBundle, GunPreset};
+use crate::health::{Dying, Health, HitPoints};
+use crate::modifiers::{ModifierStack, PowerUp};
+use crate::network::{PlayerHandle, MAINTAINED_FPS_F64};
+use crate::physics::{
+    popular_collider, CollisionLayer, KinematicsBundle, RigidBody, Sensor, Velocity,
+};
+use crate::projectiles::{DamageDealt, Projectile};
+use crate::teams::{Team, TeamNumber, TeamPalette};
+use crate::EntropyGenerator;
+use bevy::hierarchy::DespawnRecursiveExt;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{
+    Bundle, Commands, Component, Entity, EventWriter, FromReflect, Query, Reflect, Sprite,
+    SpriteBundle, Transform, With, Without,
+};
+use bevy::utils::default;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// Deployables lie on the ground among the items, under the characters' guns.
+const DEPLOYABLE_Z_LAYER: f32 = 1.0;
+
+/// Distance within which a turret notices its foes.
+const TURRET_RANGE: f32 = CHARACTER_SIZE * 7.0;
+/// Rotational speed of a turret per second in radians.
+const TURRET_RAD_SPEED: f32 = PI * 0.75;
+/// Angle in radians off the target a turret still opens fire within.
+const TURRET_FIRE_ANGLE: f32 = PI / 18.0;
+
+/// Distance from a mine at which a foe sets it off.
+const MINE_TRIGGER_RADIUS: f32 = CHARACTER_SIZE * 0.75;
+/// Distance from a mine the blast reaches. The damage falls off to half at the edge.
+const MINE_BLAST_RADIUS: f32 = CHARACTER_SIZE * 2.0;
+
+/// Kind of gear that can be deployed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum DeployableKind {
+    /// Turns towards the nearest foe in range and fires at it.
+    #[default]
+    Turret,
+    /// Lies in wait, and blows up once a foe steps close.
+    Mine,
+}
+
+impl DeployableKind {
+    /// Side of the deployable's sprite and collider.
+    pub const fn size(&self) -> f32 {
+        match self {
+            DeployableKind::Turret => CHARACTER_SIZE * 0.6,
+            DeployableKind::Mine => CHARACTER_SIZE * 0.35,
+        }
+    }
+
+    /// Hit points the deployable is set down with.
+    pub const fn max_health(&self) -> HitPoints {
+        match self {
+            DeployableKind::Turret => CHARACTER_MAX_HEALTH * 0.6,
+            DeployableKind::Mine => CHARACTER_MAX_HEALTH * 0.1,
+        }
+    }
+
+    /// Most of the kind a single player may have deployed at once. Deploying more takes down the oldest.
+    pub const fn limit(&self) -> usize {
+        match self {
+            DeployableKind::Turret => 1,
+            DeployableKind::Mine => 3,
+        }
+    }
+}
+
+/// Component of a piece of deployed gear, telling whose it is.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect, FromReflect)]
+pub struct Deployable {
+    pub kind: DeployableKind,
+    pub owner: Option<PlayerHandle>,
+    /// Time the deployable was set down at, to tell the oldest ones apart.
+    pub deployed_at: Duration,
+}
+
+/// Collection of components making up a deployable entity.
+#[derive(Bundle)]
+pub struct DeployableBundle {
+    pub deployable: Deployable,
+    pub team: Team,
+    pub health: Health,
+    #[bundle]
+    pub kinematics: KinematicsBundle,
+    #[bundle]
+    pub sprite_bundle: SpriteBundle,
+}
+
+impl DeployableBundle {
+    pub fn new(
+        kind: DeployableKind,
+        owner: Option<PlayerHandle>,
+        team: TeamNumber,
+        mut transform: Transform,
+        deployed_at: Duration,
+    ) -> Self {
+        transform.translation.z = DEPLOYABLE_Z_LAYER;
+        transform.scale = Vec3::ONE;
+        let kinematics = match kind {
+            // unmoved by the characters bumping into it, but free to turn
+            DeployableKind::Turret => KinematicsBundle::new(
+                popular_collider::square(kind.size()),
+                &[CollisionLayer::Deployable],
+                CollisionLayer::all(),
+            )
+            .with_rigidbody_type(RigidBody::KinematicVelocityBased),
+            DeployableKind::Mine => KinematicsBundle::new(
+                popular_collider::square(kind.size()),
+                &[CollisionLayer::Deployable],
+                &[CollisionLayer::Projectile],
+            )
+            .with_rigidbody_type(RigidBody::Fixed),
+        };
+        Self {
+            deployable: Deployable {
+                kind,
+                owner,
+                deployed_at,
+            },
+            team: Team(team),
+            health: Health::new(kind.max_health()),
+            kinematics,
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    // repainted in the colors picked once spawned
+                    color: TeamPalette::default().team_color(team),
+                    custom_size: Some(Vec2::new(kind.size(), kind.size())),
+                    ..default()
+                },
+                transform,
+                ..default()
+            },
+        }
+    }
+}
+
+/// Collection of components making up a turret. It acts on its input like a character would,
+/// so that its gun fires through the same pipeline as everyone else's.
+#[derive(Bundle)]
+pub struct TurretBundle {
+    #[bundle]
+    pub deployable_bundle: DeployableBundle,
+    pub action_input: CharacterActionInput,
+    pub modifiers: ModifierStack,
+}
+
+impl Deployable {
+    /// Set a new deployable down for its owner, and equip it with a gun if it's a turret.
+    /// If the owner already has as many of the kind as allowed, the oldest ones are taken down.
+    pub fn deploy(
+        commands: &mut Commands,
+        query_deployables: &Query<(&Deployable, Entity)>,
+        bundle: DeployableBundle,
+        random_state: &mut EntropyGenerator,
+    ) {
+        let kind = bundle.deployable.kind;
+        let owner = bundle.deployable.owner;
+        let mut deployed: Vec<_> = query_deployables
+            .iter()
+            .filter(|(deployable, _)| deployable.kind == kind && deployable.owner == owner)
+            .collect();
+        if deployed.len() >= kind.limit() {
+            deployed.sort_by_key(|(deployable, _)| deployable.deployed_at);
+            for (_, entity) in deployed.iter().take(deployed.len() + 1 - kind.limit()) {
+                commands.entity(*entity).despawn_recursive();
+            }
+        }
+
+        match kind {
+            DeployableKind::Turret => {
+                let turret_id = commands
+                    .spawn(TurretBundle {
+                        deployable_bundle: bundle,
+                        action_input: CharacterActionInput::default(),
+                        modifiers: ModifierStack::default(),
+                    })
+                    .id();
+                let gun_id = commands
+                    .spawn(GunBundle::new(
+                        GunPreset::TurretGun,
+                        None,
+                        random_state.fork(),
+                    ))
+                    .id();
+                equip_gear(commands, turret_id, gun_id, GunPreset::TurretGun, None);
+            }
+            DeployableKind::Mine => {
+                commands.spawn((bundle, Sensor));
+            }
+        }
+    }
+}
+
+/// System to turn the turrets towards the nearest visible foe in range, and to open fire once it's in their sights.
+pub fn handle_turret_targeting(
+    mut query_turrets: Query<(
+        &Deployable,
+        &Team,
+        &Transform,
+        &mut Velocity,
+        &mut CharacterActionInput,
+    )>,
+    query_targets: Query<
+        (&Team, &Transform, &Health, &ModifierStack),
+        (With<CharacterActionInput>, Without<Deployable>),
+    >,
+) {
+    for (deployable, team, transform, mut velocity, mut action_input) in query_turrets.iter_mut() {
+        if deployable.kind != DeployableKind::Turret {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let nearest_target = query_targets
+            .iter()
+            .filter(|(target_team, _, health, modifiers)| {
+                *target_team != team && !health.is_dead() && !modifiers.has(PowerUp::Invisibility)
+            })
+            .map(|(_, target_transform, _, _)| target_transform.translation.truncate() - position)
+            .filter(|offset| offset.length() <= TURRET_RANGE)
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+
+        let Some(offset) = nearest_target else {
+            velocity.angvel = 0.0;
+            action_input.fire = false;
+            continue;
+        };
+
+        // turn towards the target, but not so fast as to overshoot it within a frame
+        let angle_to_target = transform.up().truncate().angle_between(offset);
+        velocity.angvel = (angle_to_target * MAINTAINED_FPS_F64 as f32)
+            .clamp(-TURRET_RAD_SPEED, TURRET_RAD_SPEED);
+        action_input.fire = angle_to_target.abs() <= TURRET_FIRE_ANGLE;
+    }
+}
+
+/// System to set the mines off when a foe steps close, dealing damage to everyone in the blast but the owner's team.
+pub fn handle_mine_triggering(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageDealt>,
+    mut sound_cues: EventWriter<SoundCue>,
+    query_mines: Query<(&Deployable, &Team, &Transform, Entity)>,
+    mut query_bodies: Query<
+        (
+            Entity,
+            &mut Health,
+            &Team,
+            &Transform,
+            Option<&PlayerControlled>,
+            Option<&Deployable>,
+        ),
+        Without<Dying>,
+    >,
+) {
+    // mines gone off this frame, not to be damaged again by the blasts of the others
+    let mut detonated = Vec::<Entity>::new();
+    for (deployable, mine_team, mine_transform, mine_entity) in query_mines.iter() {
+        if deployable.kind != DeployableKind::Mine || detonated.contains(&mine_entity) {
+            continue;
+        }
+
+        let mine_position = mine_transform.translation.truncate();
+        let is_triggered =
+            query_bodies
+                .iter()
+                .any(|(_, health, team, transform, _, maybe_deployable)| {
+                    maybe_deployable.is_none()
+                        && team != mine_team
+                        && !health.is_dead()
+                        && transform.translation.truncate().distance(mine_position)
+                            <= MINE_TRIGGER_RADIUS
+                });
+        if !is_triggered {
+            continue;
+        }
+
+        detonated.push(mine_entity);
+        commands.entity(mine_entity).despawn_recursive();
+        sound_cues.send(SoundCue::Explosion);
+
+        let blast = Projectile {
+            gun_type: GunPreset::MinePlanter,
+            shooter_handle: deployable.owner,
+            damage_multiplier: 1.0,
+            ..default()
+        };
+        let blast_damage = GunPreset::MinePlanter.stats().projectile_damage;
+        for (body_entity, mut health, body_team, body_transform, maybe_player, _) in
+            query_bodies.iter_mut()
+        {
+            if detonated.contains(&body_entity) {
+                continue;
+            }
+            let offset = body_transform.translation.truncate() - mine_position;
+            let distance = offset.length();
+            if distance > MINE_BLAST_RADIUS {
+                continue;
+            }
+
+            let falloff = 1.0 - 0.5 * distance / MINE_BLAST_RADIUS;
+            Projectile::do_damage(
+                &mut commands,
+                &mut damage_events,
+                (
+                    mine_entity,
+                    &blast,
+                    mine_team,
+                    mine_transform,
+                    &Velocity::linear(offset.normalize_or_zero()),
+                ),
+                (body_entity, &mut health, Some(body_team), maybe_player),
+                Some(blast_damage * falloff),
+            );
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Cosmetic effects living outside of the rollback world. Nothing here may feed back into the simulation,
+//! so the randomness comes from the thread, and not the [`EntropyGenerator`](crate::EntropyGenerator).
+
+use crate::audio::Surface;
+use crate::characters::CHARACTER_MAX_HEALTH;
+use crate::guns::GunPreset;
+use crate::network::confirmed::Confirmed;
+use crate::network::session::LocalPlayers;
+use crate::projectiles::{DamageDealt, Projectile, ProjectileImpact};
+use crate::teams::TeamColors;
+use crate::ui::user_settings::UserSettings;
+use crate::GameState;
+use bevy::prelude::*;
+use rand::Rng;
+use std::f32::consts::PI;
+
+/// Effects are drawn just above the bodies, but below the floating combat feedback.
+const EFFECTS_Z_LAYER: f32 = 5.0;
+
+const MUZZLE_FLASH_SIZE: f32 = 12.0;
+const MUZZLE_FLASH_LIFETIME_SECS: f32 = 0.06;
+
+const TRAIL_SIZE: f32 = 3.0;
+const TRAIL_LIFETIME_SECS: f32 = 0.12;
+const RAILGUN_TRAIL_SIZE: f32 = 6.0;
+const RAILGUN_TRAIL_LIFETIME_SECS: f32 = 0.6;
+
+const SPARK_COUNT: usize = 6;
+const SPARK_SIZE: f32 = 3.0;
+const SPARK_SPEED: f32 = 160.0;
+const SPARK_LIFETIME_SECS: f32 = 0.25;
+const SPARK_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+
+const DEBRIS_COUNT: usize = 24;
+const DEBRIS_SIZE: f32 = 7.0;
+const DEBRIS_SPEED: f32 = 120.0;
+const DEBRIS_LIFETIME_SECS: f32 = 1.2;
+const DEBRIS_FALLBACK_COLOR: Color = Color::WHITE;
+
+/// Share of the particles left in a burst when the effects are reduced.
+const REDUCED_PARTICLE_SHARE: f32 = 0.25;
+
+/// Screen shake offset in in-game units at full trauma.
+const MAX_SHAKE_OFFSET: f32 = 12.0;
+/// Trauma lost per second.
+const SHAKE_DECAY: f32 = 1.5;
+/// Trauma gained from taking the character's whole health in damage.
+const SHAKE_TRAUMA_PER_FULL_HEALTH: f32 = 2.0;
+
+/// Short-lived sprite drifting away and fading out.
+#[derive(Component, Debug)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+    pub initial_alpha: f32,
+}
+
+impl Particle {
+    fn new(velocity: Vec2, lifetime_secs: f32, initial_alpha: f32) -> Self {
+        Self {
+            velocity,
+            lifetime: Timer::from_seconds(lifetime_secs, TimerMode::Once),
+            initial_alpha,
+        }
+    }
+}
+
+/// Amount of shaking the camera is currently subjected to, from 0 to 1.
+#[derive(Resource, Debug, Default)]
+pub struct ScreenShake {
+    pub trauma: f32,
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    position: Vec3,
+    color: Color,
+    size: f32,
+    particle: Particle,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: color.with_a(particle.initial_alpha),
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.truncate().extend(EFFECTS_Z_LAYER)),
+            ..default()
+        },
+        particle,
+    ));
+}
+
+/// Get the number of particles in a burst according to the settings.
+fn burst_size(settings: &UserSettings, full_count: usize) -> usize {
+    if settings.reduced_effects {
+        ((full_count as f32 * REDUCED_PARTICLE_SHARE) as usize).max(1)
+    } else {
+        full_count
+    }
+}
+
+/// System to flash the gun barrels at every new projectile.
+fn handle_muzzle_flashes(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    query_projectiles: Query<(&Transform, &Sprite), Added<Projectile>>,
+) {
+    if settings.reduced_effects {
+        return;
+    }
+    for (transform, sprite) in query_projectiles.iter() {
+        spawn_particle(
+            &mut commands,
+            transform.translation,
+            sprite.color,
+            MUZZLE_FLASH_SIZE,
+            Particle::new(Vec2::ZERO, MUZZLE_FLASH_LIFETIME_SECS, 0.9),
+        );
+    }
+}
+
+/// System to leave trails behind the flying projectiles. The railgun slug's trail lingers the longest.
+fn handle_projectile_trails(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    query_projectiles: Query<(&Projectile, &Transform, &Sprite)>,
+) {
+    for (projectile, transform, sprite) in query_projectiles.iter() {
+        let (size, lifetime) = match projectile.gun_type {
+            GunPreset::RailGun => (RAILGUN_TRAIL_SIZE, RAILGUN_TRAIL_LIFETIME_SECS),
+            _ if settings.reduced_effects => continue,
+            _ => (TRAIL_SIZE, TRAIL_LIFETIME_SECS),
+        };
+        spawn_particle(
+            &mut commands,
+            transform.translation,
+            sprite.color,
+            size,
+            Particle::new(Vec2::ZERO, lifetime, 0.5),
+        );
+    }
+}
+
+/// System to throw sparks off the obstacles hit by projectiles.
+fn handle_impact_sparks(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    mut impact_events: EventReader<Confirmed<ProjectileImpact>>,
+) {
+    let mut rng = rand::thread_rng();
+    for impact in impact_events.iter() {
+        if !matches!(impact.surface, Surface::Obstacle | Surface::Glass) {
+            continue;
+        }
+        // sparks bounce back where the projectile came from
+        let base_angle = (-impact.direction).y.atan2((-impact.direction).x);
+        for _ in 0..burst_size(&settings, SPARK_COUNT) {
+            let angle = base_angle + rng.gen_range(-PI / 3.0..PI / 3.0);
+            let velocity = Vec2::from_angle(angle) * SPARK_SPEED * rng.gen_range(0.5..1.0);
+            spawn_particle(
+                &mut commands,
+                impact.position,
+                SPARK_COLOR,
+                SPARK_SIZE,
+                Particle::new(velocity, SPARK_LIFETIME_SECS, 1.0),
+            );
+        }
+    }
+}
+
+/// System to blow the dead characters into debris of their team's colour, and to shake the screen of those taking hits.
+fn handle_damage_effects(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    local_players: Option<Res<LocalPlayers>>,
+    team_colors: Res<TeamColors>,
+    mut damage_events: EventReader<Confirmed<DamageDealt>>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in damage_events.iter() {
+        if !settings.reduced_effects
+            && event.victim_handle.is_some_and(|handle| {
+                local_players
+                    .as_ref()
+                    .is_some_and(|local_players| local_players.contains(handle))
+            })
+        {
+            screen_shake.trauma = (screen_shake.trauma
+                + event.damage / CHARACTER_MAX_HEALTH * SHAKE_TRAUMA_PER_FULL_HEALTH)
+                .min(1.0);
+        }
+
+        if !event.is_fatal {
+            continue;
+        }
+        let color = event
+            .victim_team
+            .map(|team| team_colors.character_color(event.victim_handle, team))
+            .unwrap_or(DEBRIS_FALLBACK_COLOR);
+        for _ in 0..burst_size(&settings, DEBRIS_COUNT) {
+            let velocity =
+                Vec2::from_angle(rng.gen_range(-PI..PI)) * DEBRIS_SPEED * rng.gen_range(0.2..1.0)
+                    + event.direction * DEBRIS_SPEED * 0.5;
+            spawn_particle(
+                &mut commands,
+                event.position,
+                color,
+                DEBRIS_SIZE * rng.gen_range(0.5..1.0),
+                Particle::new(
+                    velocity,
+                    DEBRIS_LIFETIME_SECS * rng.gen_range(0.5..1.0),
+                    1.0,
+                ),
+            );
+        }
+    }
+}
+
+/// System to move the particles along, fade them out and clean them up once they're gone.
+fn handle_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query_particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in query_particles.iter_mut() {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.0);
+        sprite
+            .color
+            .set_a(particle.initial_alpha * particle.lifetime.percent_left());
+    }
+}
+
+/// System to shake the camera as long as there is trauma, and calm it back down to its resting place.
+fn handle_screen_shake(
+    time: Res<Time>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut query_cameras: Query<&mut Transform, With<Camera>>,
+) {
+    if screen_shake.trauma <= 0.0 {
+        return;
+    }
+    screen_shake.trauma = (screen_shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.0);
+
+    // squared, so that light hits barely nudge the camera, while the heavy ones knock it about
+    let offset = if screen_shake.trauma > 0.0 {
+        let mut rng = rand::thread_rng();
+        Vec2::from_angle(rng.gen_range(-PI..PI)) * MAX_SHAKE_OFFSET * screen_shake.trauma.powi(2)
+    } else {
+        Vec2::ZERO
+    };
+    for mut transform in query_cameras.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
+
+fn reset_screen_shake(
+    mut screen_shake: ResMut<ScreenShake>,
+    mut query_cameras: Query<&mut Transform, With<Camera>>,
+) {
+    screen_shake.trauma = 0.0;
+    for mut transform in query_cameras.iter_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+    }
+}
+
+/// Plugin of the purely cosmetic visual effects: muzzle flashes, trails, sparks, debris and screen shake.
+pub struct VisualEffectsPlugin;
+impl Plugin for VisualEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .add_system(handle_muzzle_flashes.run_if(in_state(GameState::InGame)))
+            .add_system(handle_projectile_trails.run_if(in_state(GameState::InGame)))
+            .add_system(handle_impact_sparks.run_if(in_state(GameState::InGame)))
+            .add_system(handle_damage_effects.run_if(in_state(GameState::InGame)))
+            .add_system(handle_particles.run_if(in_state(GameState::InGame)))
+            .add_system(handle_screen_shake.run_if(in_state(GameState::InGame)))
+            .add_system(reset_screen_shake.in_schedule(OnExit(GameState::InGame)));
+    }
+}
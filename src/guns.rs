@@ -1,16 +1,20 @@
+use crate::audio::SoundCue;
 use crate::characters::CHARACTER_SPEED;
 use crate::controls::CharacterActionInput;
+use crate::deployables::{Deployable, DeployableBundle};
 use crate::guns::stats::ProjectileSpawnSpace;
+use crate::match_stats::StatEvent;
+use crate::modifiers::{ModifierStack, MULTI_SHOT_FAN_ANGLE};
 use crate::physics::{
     ColliderScale, KinematicsBundle, OngoingCollisions, RigidBody, Sensor, Velocity,
 };
 use crate::projectiles::ProjectileBundle;
-use crate::teams::{team_color, Team, TeamNumber};
+use crate::teams::{Team, TeamColors};
 use crate::EntropyGenerator;
 use bevy::ecs::system::EntityCommands;
 use bevy::math::{Quat, Vec2, Vec3};
 use bevy::prelude::{
-    Bundle, Changed, Children, Commands, Component, Entity, GlobalTransform, Query,
+    Bundle, Children, Color, Commands, Component, Entity, EventWriter, GlobalTransform, Query, Ref,
     ReflectComponent, Res, Sprite, SpriteBundle, Time, Timer, TimerMode, Transform, With, Without,
 };
 use bevy::reflect::{FromReflect, Reflect, ReflectFromReflect};
@@ -19,12 +23,12 @@ use std::f32::consts::PI;
 use std::time::Duration;
 
 pub mod additives;
+pub mod aim_preview;
 mod presets;
 mod stats;
 
 use crate::network::PlayerHandle;
 pub use presets::GunPreset;
-pub use stats::GunPersistentStats;
 
 /// The gun is slightly darker than the main color of the character body to be distinct.
 const GUN_COLOR_MULTIPLIER: f32 = 0.75;
@@ -97,16 +101,6 @@ impl GunBundle {
         }
         gun_bundle
     }
-
-    /// Change the bundled gun's color from neutral to that in line with the team.
-    pub fn with_paint_job(mut self, team_number: TeamNumber) -> Self {
-        Gun::team_paint(
-            self.gun.preset,
-            &mut self.sprite_bundle.sprite,
-            Some(team_number),
-        );
-        self
-    }
 }
 
 /// Holder of all non-constant properties of a weapon.
@@ -175,10 +169,11 @@ impl Gun {
         }
     }
 
-    /// Make a gun look in line with a team's color or neutral (usually when not equipped by anybody).
-    pub fn team_paint(preset: GunPreset, sprite: &mut Sprite, team_number: Option<TeamNumber>) {
-        if let Some(team_number) = team_number {
-            sprite.color = (team_color(team_number) * GUN_COLOR_MULTIPLIER).into();
+    /// Make a gun look in line with its owner's color or neutral (usually when not equipped by anybody).
+    /// The owner's color is looked up in [`TeamColors`].
+    pub fn team_paint(preset: GunPreset, sprite: &mut Sprite, owner_color: Option<Color>) {
+        if let Some(owner_color) = owner_color {
+            sprite.color = (owner_color * GUN_COLOR_MULTIPLIER).into();
         } else {
             sprite.color = preset.stats().gun_neutral_color.0;
         }
@@ -218,6 +213,14 @@ impl Gun {
         }
     }
 
+    /// Expend a single round, reloading if it was the last one, and let the gun cool down. For shots that aren't projectiles.
+    fn expend_round(&mut self) {
+        if self.eject_shot_and_check_if_empty() {
+            self.start_reloading();
+        }
+        self.reset_fire_cooldown();
+    }
+
     /// Set the cooldown timer to run anew. To be used usually when making a shot.
     fn reset_fire_cooldown(&mut self) {
         self.fire_cooldown.reset();
@@ -229,6 +232,19 @@ impl Gun {
         self.reload_progress.unpause();
     }
 
+    /// Fill the magazine back up, cancelling the reload if there was one. Returns `false` if it was already full.
+    pub fn refill_ammo(&mut self) -> bool {
+        let stats = self.preset.stats();
+        if self.shots_before_reload == stats.shots_before_reload && self.reload_progress.paused() {
+            return false;
+        }
+        self.shots_before_reload = stats.shots_before_reload;
+        self.reload_progress.reset();
+        self.reload_progress.pause();
+        self.fire_cooldown.unpause();
+        true
+    }
+
     /// Calculate a possibly random vector of flight direction of a projectile. The gun will change its state.
     pub fn choose_spread_direction(&mut self) -> Quat {
         if self.preset.stats().projectile_spread_angle == 0.0 {
@@ -241,18 +257,36 @@ impl Gun {
         }
     }
 
+    /// Get the rotation of a projectile beyond the gun's usual shot, so that the extra ones fan out to the sides in turn.
+    fn extra_projectile_fan(projectile_index: u32, usual_projectiles: u32) -> Quat {
+        if projectile_index < usual_projectiles {
+            return Quat::IDENTITY;
+        }
+        let extra_index = projectile_index - usual_projectiles;
+        // alternate between the sides, starting with the right
+        let side = (-1f32).powi(extra_index as i32);
+        Quat::from_axis_angle(
+            -Vec3::Z,
+            side * (extra_index / 2 + 1) as f32 * MULTI_SHOT_FAN_ANGLE,
+        )
+    }
+
     /// Get a round (and, optionally, several more ahead, if that should have happened in the past)
     /// of projectiles that come out of a gun when a trigger is pressed. These still have to be spawned.
     /// The gun will change its state. If the gun has recoil, the character will be affected by it.
+    /// The character's modifiers are applied to the projectiles.
     fn fire_and_produce_projectiles(
         &mut self,
         gun_transform: &GlobalTransform,
         maybe_shooter_handle: Option<PlayerHandle>,
         team: &Team,
+        modifiers: &ModifierStack,
         character_transform: &mut Transform,
         fast_forward_rounds: Option<(u128, u128)>,
     ) -> (Vec<ProjectileBundle>, u128) {
         let gun_stats = self.preset.stats();
+        let projectiles_per_shot = modifiers.projectiles_per_shot(gun_stats.projectiles_per_shot);
+        let damage_multiplier = modifiers.projectile_damage(1.0);
         let (gun_scale, _, gun_translation) = gun_transform.to_scale_rotation_translation();
         let bullet_spawn_distance = gun_stats.get_bullet_spawn_offset(gun_scale);
         let cooldown_duration = self.fire_cooldown.duration().as_nanos();
@@ -284,8 +318,10 @@ impl Gun {
                 (1, 0)
             };
         while rounds_fired < rounds_to_fire {
-            for _ in 0..gun_stats.projectiles_per_shot {
-                let facing_direction = self.choose_spread_direction() * gun_transform.up();
+            for projectile_index in 0..projectiles_per_shot {
+                let facing_direction = self.choose_spread_direction()
+                    * Self::extra_projectile_fan(projectile_index, gun_stats.projectiles_per_shot)
+                    * gun_transform.up();
 
                 // Adjust spawn points for "Perimeter" individually around the perimeter according to the established random direction.
                 let bullet_transform = match gun_stats.projectile_spawn_point {
@@ -301,7 +337,8 @@ impl Gun {
                     team.0,
                     bullet_transform,
                     facing_direction * gun_stats.projectile_speed,
-                );
+                )
+                .with_damage_multiplier(damage_multiplier);
 
                 let linear_velocity = bullet.kinematics.velocity.linvel.extend(0.);
                 bullet.sprite_bundle.transform.translation += (rounds_fired * cooldown_duration + time_in_nanos_elapsed_since_latest_cooldown) as f32
@@ -349,104 +386,188 @@ pub struct Equipped {
 pub struct LastUnequippedAt(pub Duration);
 
 pub mod systems {
+    use super::additives::steering::Steered;
     pub use super::additives::systems::*;
     use super::*;
     use crate::characters::PlayerControlled;
+    use bevy::ecs::change_detection::DetectChanges;
 
     /// System to spawn projectiles out of guns and keep track of their firing cooldowns, magazine sizes, and character recoil.
     pub fn handle_gunfire(
         mut commands: Commands,
         time: Res<Time>,
+        mut sound_cues: EventWriter<SoundCue>,
+        mut stat_events: EventWriter<StatEvent>,
         mut query_weapons: Query<(&mut Gun, &GlobalTransform, &Equipped)>,
         mut query_characters: Query<(
             &CharacterActionInput,
             &Team,
+            &ModifierStack,
             &mut Transform,
             Option<&PlayerControlled>,
+            Option<&Deployable>,
         )>,
+        query_deployables: Query<(&Deployable, Entity)>,
     ) {
         for (mut gun, gun_transform, equipped) in query_weapons.iter_mut() {
             if equipped.by.is_none() {
                 continue;
             }
 
-            let (wants_to_fire, wants_to_reload, team, mut transform, maybe_player_handle) =
+            let (
+                wants_to_fire,
+                wants_to_reload,
+                team,
+                modifiers,
+                mut transform,
+                maybe_player_handle,
+            ) =
                 query_characters
                     .get_mut(equipped.by.expect(
                         "Should've checked if it was none! The gun is not equipped by anyone.",
                     ))
-                    .map(|(input, team, transform, maybe_player)| {
-                        (
-                            input.fire,
-                            input.reload,
-                            team,
-                            transform,
-                            maybe_player.map(|player| player.handle),
-                        )
-                    })
+                    .map(
+                        |(input, team, modifiers, transform, maybe_player, maybe_deployable)| {
+                            (
+                                input.fire,
+                                input.reload,
+                                team,
+                                modifiers,
+                                transform,
+                                // a turret fires on its owner's behalf
+                                maybe_player
+                                    .map(|player| player.handle)
+                                    .or(maybe_deployable.and_then(|deployable| deployable.owner)),
+                            )
+                        },
+                    )
                     .unwrap();
 
+            let was_reloading = !gun.reload_progress.paused();
             if wants_to_reload {
                 gun.start_reloading();
             }
 
             let cooldown_time_previously_elapsed = gun.fire_cooldown.elapsed().as_nanos();
             if gun.tick_cooldowns(time.delta()) && wants_to_fire {
-                let cooldown_time_elapsed =
-                    cooldown_time_previously_elapsed + time.delta().as_nanos();
-                let cooldown_duration = gun.fire_cooldown.duration().as_nanos();
-                let cooldown_times_over = cooldown_time_elapsed / cooldown_duration;
-                let cooldown_latest_time_elapsed = cooldown_time_elapsed % cooldown_duration;
-
                 let gun_type = gun.preset;
 
-                // todo add a ray cast from the body to the gun barrel to check for collisions
-                // but currently it's kinda like shooting from cover / over shoulder, fun
-
-                let (bullets, _rounds_fired) = gun.fire_and_produce_projectiles(
-                    gun_transform,
-                    maybe_player_handle,
-                    team,
-                    &mut transform,
-                    Some((cooldown_times_over, cooldown_latest_time_elapsed)),
-                );
-
-                if gun_type.has_extra_projectile_components() {
-                    /* todo expand macro instead of function to return a bundle of components. would take the bullet bundle or other necessary information to form new components
-                    let bullets = bullets.into_iter().map(|default_bundle| (default_bundle, gun_type.get_extra_projectile_components())).collect();
-                    commands.spawn_batch(bullets);*/
-                    for bullet in bullets {
-                        let mut bullet_commands = commands.spawn(bullet);
-                        // Add any extra components that a bullet should have
-                        gun_type.add_projectile_components(&mut bullet_commands);
+                if let Some(kind) = gun_type.stats().deploys {
+                    // a single piece of gear per shot, however many shots were missed between the frames
+                    gun.expend_round();
+                    let (_, rotation, translation) = gun_transform.to_scale_rotation_translation();
+                    let deploy_point = translation
+                        + gun_type.stats().get_bullet_spawn_offset(Vec3::ONE) * gun_transform.up();
+                    Deployable::deploy(
+                        &mut commands,
+                        &query_deployables,
+                        DeployableBundle::new(
+                            kind,
+                            maybe_player_handle,
+                            team.0,
+                            Transform::from_translation(deploy_point).with_rotation(rotation),
+                            time.elapsed(),
+                        ),
+                        &mut gun.entropy,
+                    );
+
+                    sound_cues.send(SoundCue::Deploy);
+                    if let Some(shooter) = maybe_player_handle {
+                        stat_events.send(StatEvent::Fired {
+                            shooter,
+                            gun_type,
+                            projectiles: 1,
+                        });
                     }
                 } else {
-                    commands.spawn_batch(bullets);
+                    let cooldown_time_elapsed =
+                        cooldown_time_previously_elapsed + time.delta().as_nanos();
+                    let cooldown_duration = gun.fire_cooldown.duration().as_nanos();
+                    let cooldown_times_over = cooldown_time_elapsed / cooldown_duration;
+                    let cooldown_latest_time_elapsed = cooldown_time_elapsed % cooldown_duration;
+
+                    // todo add a ray cast from the body to the gun barrel to check for collisions
+                    // but currently it's kinda like shooting from cover / over shoulder, fun
+
+                    let (bullets, _rounds_fired) = gun.fire_and_produce_projectiles(
+                        gun_transform,
+                        maybe_player_handle,
+                        team,
+                        modifiers,
+                        &mut transform,
+                        Some((cooldown_times_over, cooldown_latest_time_elapsed)),
+                    );
+
+                    let projectiles_fired = bullets.len() as u32;
+
+                    let is_steered = gun_type.stats().projectile_steering.is_some();
+                    if gun_type.has_extra_projectile_components() || is_steered {
+                        /* todo expand macro instead of function to return a bundle of components. would take the bullet bundle or other necessary information to form new components
+                        let bullets = bullets.into_iter().map(|default_bundle| (default_bundle, gun_type.get_extra_projectile_components())).collect();
+                        commands.spawn_batch(bullets);*/
+                        for bullet in bullets {
+                            let mut bullet_commands = commands.spawn(bullet);
+                            // Add any extra components that a bullet should have
+                            gun_type.add_projectile_components(&mut bullet_commands);
+                            if is_steered {
+                                // the shooter is who a guided projectile follows and a boomerang returns to
+                                bullet_commands.insert(Steered::new(equipped.by));
+                            }
+                        }
+                    } else {
+                        commands.spawn_batch(bullets);
+                    }
+
+                    sound_cues.send(SoundCue::Fire(gun_type));
+                    if let Some(shooter) = maybe_player_handle {
+                        stat_events.send(StatEvent::Fired {
+                            shooter,
+                            gun_type,
+                            projectiles: projectiles_fired,
+                        });
+                    }
                 }
+            } else if wants_to_fire && was_reloading {
+                sound_cues.send(SoundCue::EmptyClick);
+            }
+
+            if !was_reloading && !gun.reload_progress.paused() {
+                sound_cues.send(SoundCue::Reload);
             }
         }
     }
 
     pub fn handle_gun_ownership_cosmetic_change(
         mut commands: Commands,
-        mut q_guns: Query<(&Gun, &mut Sprite, &Equipped, Entity), Changed<Equipped>>,
+        team_colors: Res<TeamColors>,
+        mut q_guns: Query<(&Gun, &mut Sprite, Ref<Equipped>, Entity)>,
         // maybe with character component if ever present
-        q_characters: Query<&Team, With<Children>>,
+        q_characters: Query<
+            (&Team, Option<&PlayerControlled>, Option<&Deployable>),
+            With<Children>,
+        >,
     ) {
         for (gun, mut sprite, equipped, entity) in q_guns.iter_mut() {
+            if !(equipped.is_changed() || team_colors.is_changed()) {
+                continue;
+            }
             if equipped.by.is_none() {
                 commands.entity(entity).remove::<Equipped>();
                 Gun::team_paint(gun.preset, &mut sprite, None);
                 continue;
             }
 
-            let owner = equipped
-                .by
-                .expect("Should've checked if it was none! The gun is not equipped by anyone.");
-            let team = q_characters
-                .get(owner)
-                .expect("Couldn't find the entity the gun is equipped by!");
-            Gun::team_paint(gun.preset, &mut sprite, Some(team.0));
+            let Some(Ok((team, maybe_player, maybe_deployable))) =
+                equipped.by.map(|owner| q_characters.get(owner))
+            else {
+                // the owner may be gone by now, and the gun is about to be dropped or despawned with them
+                continue;
+            };
+            let owner_handle = maybe_player
+                .map(|player| player.handle)
+                .or(maybe_deployable.and_then(|deployable| deployable.owner));
+            let owner_color = team_colors.character_color(owner_handle, team.0);
+            Gun::team_paint(gun.preset, &mut sprite, Some(owner_color));
         }
     }
 
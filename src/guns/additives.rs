@@ -1,7 +1,8 @@
+use crate::characters::PlayerControlled;
 use crate::characters::{CHARACTER_MAX_HEALTH, CHARACTER_SIZE};
 use crate::health::HitPoints;
-use crate::physics::OngoingCollisions;
-use crate::projectiles::Projectile;
+use crate::physics::{OngoingCollisions, Velocity};
+use crate::projectiles::{DamageDealt, Projectile};
 use crate::teams::Team;
 use crate::Health;
 use bevy::prelude::*;
@@ -26,28 +27,46 @@ pub mod railgun {
     /// System to continually deal damage to bodies that rail gun slugs travel through.
     pub fn handle_railgun_penetration_damage(
         mut commands: Commands,
+        mut damage_events: EventWriter<DamageDealt>,
         mut query_bullets: Query<(
             &OngoingCollisions,
             &Projectile,
             &Team,
             &Transform,
+            &Velocity,
             &mut RailGunThing,
+            Entity,
         )>,
-        mut query_bodies: Query<(&mut Health, Option<&Team>)>,
+        mut query_bodies: Query<(&mut Health, Option<&Team>, Option<&PlayerControlled>)>,
     ) {
-        for (collisions, bullet, bullet_team, bullet_transform, mut railgun_thing) in
-            query_bullets.iter_mut()
+        for (
+            collisions,
+            bullet,
+            bullet_team,
+            bullet_transform,
+            bullet_velocity,
+            mut railgun_thing,
+            bullet_entity,
+        ) in query_bullets.iter_mut()
         {
-            let gun_stats = bullet.gun_type.stats();
             for body_entity in collisions.iter() {
-                if let Ok((mut body_health, body_team)) = query_bodies.get_mut(*body_entity) {
+                if let Ok((mut body_health, body_team, body_player)) =
+                    query_bodies.get_mut(*body_entity)
+                {
                     let distance_travelled =
                         (bullet_transform.translation - railgun_thing.previous_position).length();
                     let damage = PENETRATION_DAMAGE_PER_DISTANCE * distance_travelled;
                     Projectile::do_damage(
                         &mut commands,
-                        (gun_stats, bullet.shooter_handle, bullet_team),
-                        (*body_entity, &mut body_health, body_team),
+                        &mut damage_events,
+                        (
+                            bullet_entity,
+                            bullet,
+                            bullet_team,
+                            bullet_transform,
+                            bullet_velocity,
+                        ),
+                        (*body_entity, &mut body_health, body_team, body_player),
                         Some(damage),
                     );
                 }
@@ -65,6 +84,118 @@ pub mod railgun {
     }*/
 }
 
+pub mod steering {
+    use super::*;
+    use crate::controls::CharacterActionInput;
+    use crate::guns::stats::SteeringBehaviour;
+    use crate::modifiers::{ModifierStack, PowerUp};
+    use crate::network::MAINTAINED_FPS_F64;
+
+    /// Distance from the shooter's center a returning boomerang is caught at.
+    const BOOMERANG_CATCH_DISTANCE: f32 = CHARACTER_SIZE;
+
+    /// Component of a projectile steering itself in flight, as its gun's stats say.
+    #[derive(Component, Debug, Default, PartialEq, Reflect, FromReflect)]
+    pub struct Steered {
+        /// Whoever fired the projectile, to be followed or returned to.
+        pub shooter: Option<Entity>,
+    }
+
+    impl Steered {
+        pub fn new(shooter: Option<Entity>) -> Self {
+            Self { shooter }
+        }
+    }
+
+    /// System to turn the steered projectiles towards where they want to go, no faster than their turn rate allows,
+    /// and to get rid of the boomerangs that are caught.
+    pub fn handle_projectile_steering(
+        mut commands: Commands,
+        mut query_projectiles: Query<(
+            &Projectile,
+            &Team,
+            &Transform,
+            &mut Velocity,
+            &Steered,
+            Entity,
+        )>,
+        query_characters: Query<
+            (
+                &CharacterActionInput,
+                &Team,
+                &Transform,
+                &Health,
+                &ModifierStack,
+            ),
+            Without<Projectile>,
+        >,
+    ) {
+        for (projectile, team, transform, mut velocity, steered, entity) in
+            query_projectiles.iter_mut()
+        {
+            let Some(steering) = &projectile.gun_type.stats().projectile_steering else {
+                continue;
+            };
+
+            let heading = velocity.linvel.normalize_or_zero();
+            if heading == Vec2::ZERO {
+                continue;
+            }
+            let position = transform.translation.truncate();
+
+            let desired_direction = match steering.behaviour {
+                SteeringBehaviour::Homing { cone_angle, range } => query_characters
+                    .iter()
+                    .filter(|(_, target_team, _, health, modifiers)| {
+                        *target_team != team
+                            && !health.is_dead()
+                            && !modifiers.has(PowerUp::Invisibility)
+                    })
+                    .map(|(_, _, target_transform, _, _)| {
+                        target_transform.translation.truncate() - position
+                    })
+                    .filter(|offset| {
+                        offset.length() <= range
+                            && heading.angle_between(*offset).abs() <= cone_angle / 2.
+                    })
+                    .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared())),
+                SteeringBehaviour::Guided => steered
+                    .shooter
+                    .and_then(|shooter| query_characters.get(shooter).ok())
+                    .filter(|(action_input, ..)| action_input.fire)
+                    .map(|(_, _, shooter_transform, _, _)| shooter_transform.up().truncate()),
+                SteeringBehaviour::Boomerang { return_after } => {
+                    if projectile.flight_time < return_after {
+                        None
+                    } else if let Some((_, _, shooter_transform, _, _)) = steered
+                        .shooter
+                        .and_then(|shooter| query_characters.get(shooter).ok())
+                    {
+                        let offset = shooter_transform.translation.truncate() - position;
+                        if offset.length() <= BOOMERANG_CATCH_DISTANCE {
+                            commands.entity(entity).despawn();
+                            continue;
+                        }
+                        Some(offset)
+                    } else {
+                        // nobody to return to, so it flies on until it's spent
+                        None
+                    }
+                }
+            };
+
+            if let Some(desired_direction) = desired_direction {
+                let max_turn = steering.turn_rate / MAINTAINED_FPS_F64 as f32;
+                let turn = heading
+                    .angle_between(desired_direction)
+                    .clamp(-max_turn, max_turn);
+                velocity.linvel = Vec2::from_angle(turn).rotate(velocity.linvel);
+            }
+        }
+    }
+}
+
 pub mod systems {
     pub use super::railgun::handle_railgun_penetration_damage;
+    pub use super::steering::handle_projectile_steering;
 }
@@ -0,0 +1,195 @@
+//! Preview of the paths the local players' shots would take. Lives outside of the rollback world,
+//! only ever reading the simulation and never feeding back into it.
+
+use crate::characters::{PlayerControlled, CHARACTER_SIZE};
+use crate::guns::{Equipped, Gun, GunPreset};
+use crate::network::session::LocalPlayers;
+use crate::physics::ObstacleMaterial;
+use crate::ui::user_settings::UserSettings;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{Collider, QueryFilter, RapierContext, TOIStatus};
+
+/// The preview is drawn just above the bodies, alongside the effects.
+const AIM_PREVIEW_Z_LAYER: f32 = 5.0;
+
+/// Most times the previewed path bounces off the obstacles before it ends.
+const AIM_PREVIEW_MAX_BOUNCES: usize = 3;
+/// Longest the previewed path gets, however far the projectiles would fly.
+const AIM_PREVIEW_MAX_LENGTH: f32 = CHARACTER_SIZE * 12.0;
+/// Length of the lines marking the edges of the spread cone.
+const AIM_PREVIEW_CONE_LENGTH: f32 = CHARACTER_SIZE * 3.0;
+
+const AIM_PREVIEW_DOT_SIZE: f32 = 3.0;
+const AIM_PREVIEW_DOT_SPACING: f32 = CHARACTER_SIZE * 0.4;
+const AIM_PREVIEW_PATH_ALPHA: f32 = 0.6;
+const AIM_PREVIEW_CONE_ALPHA: f32 = 0.25;
+
+/// Dot making up a line of the aim preview. All of them are redrawn every frame.
+#[derive(Component, Debug)]
+pub struct AimPreviewDot;
+
+/// Cast the shape of a projectile of the gun type from the origin along the direction,
+/// bouncing it off the obstacles that would reflect it.
+/// Returns the corners of the path, starting with the origin.
+fn predict_projectile_path(
+    rapier_context: &RapierContext,
+    query_obstacles: &Query<&ObstacleMaterial>,
+    gun_type: GunPreset,
+    origin: Vec2,
+    direction: Vec2,
+) -> Vec<Vec2> {
+    let gun_stats = gun_type.stats();
+    let shape = Collider::ball(gun_stats.projectile_size / 2.0);
+    // slugs pierce the walls that let them through
+    let is_piercing = gun_type == GunPreset::RailGun;
+    let predicate = |entity: Entity| {
+        query_obstacles
+            .get(entity)
+            .is_ok_and(|material| !is_piercing || material.stops_piercing())
+    };
+    let filter = QueryFilter::default().predicate(&predicate);
+
+    let mut remaining_length = gun_stats
+        .projectile_range
+        .min(gun_stats.projectile_speed * gun_stats.projectile_lifetime.as_secs_f32())
+        .min(AIM_PREVIEW_MAX_LENGTH);
+    let mut position = origin;
+    let mut direction = direction.normalize_or_zero();
+    let mut path = vec![origin];
+    for _ in 0..=AIM_PREVIEW_MAX_BOUNCES {
+        let Some((obstacle, toi)) =
+            rapier_context.cast_shape(position, 0.0, direction, &shape, remaining_length, filter)
+        else {
+            path.push(position + direction * remaining_length);
+            break;
+        };
+
+        position += direction * toi.toi;
+        remaining_length -= toi.toi;
+        path.push(position);
+
+        let is_reflected = toi.status != TOIStatus::Penetrating
+            && !is_piercing
+            && query_obstacles
+                .get(obstacle)
+                .is_ok_and(|material| match material {
+                    ObstacleMaterial::Bouncy => true,
+                    ObstacleMaterial::Absorbing => false,
+                    _ => gun_stats.projectile_elasticity > 0.0,
+                });
+        if !is_reflected {
+            break;
+        }
+        direction -= 2.0 * direction.dot(toi.normal1) * toi.normal1;
+    }
+    path
+}
+
+/// Lay evenly spaced dots along the path, carrying the spacing over its corners.
+fn spawn_dotted_line(commands: &mut Commands, path: &[Vec2], color: Color) {
+    let mut carried_over = 0.0;
+    for segment in path.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+        let direction = (end - start).normalize_or_zero();
+        let mut travelled = carried_over;
+        while travelled < length {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(AIM_PREVIEW_DOT_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        (start + direction * travelled).extend(AIM_PREVIEW_Z_LAYER),
+                    ),
+                    ..default()
+                },
+                AimPreviewDot,
+            ));
+            travelled += AIM_PREVIEW_DOT_SPACING;
+        }
+        carried_over = travelled - length;
+    }
+}
+
+/// System to rub out the aim preview of the previous frame.
+fn clear_aim_preview(mut commands: Commands, query_dots: Query<Entity, With<AimPreviewDot>>) {
+    for entity in query_dots.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// System to draw the predicted paths of the projectiles and the edges of the spread cones
+/// of the guns held by the local players, if they allow it.
+fn handle_aim_preview(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    local_players: Option<Res<LocalPlayers>>,
+    rapier_context: Res<RapierContext>,
+    query_guns: Query<(&Gun, &GlobalTransform, &Equipped)>,
+    query_players: Query<&PlayerControlled>,
+    query_obstacles: Query<&ObstacleMaterial>,
+) {
+    if !settings.aim_preview {
+        return;
+    }
+    let Some(local_players) = local_players else {
+        return;
+    };
+
+    for (gun, gun_transform, equipped) in query_guns.iter() {
+        let gun_stats = gun.preset.stats();
+        if !gun_stats.aim_preview {
+            continue;
+        }
+        let is_held_locally = equipped
+            .by
+            .and_then(|holder| query_players.get(holder).ok())
+            .is_some_and(|player| local_players.contains(player.handle));
+        if !is_held_locally {
+            continue;
+        }
+
+        let (gun_scale, _, gun_translation) = gun_transform.to_scale_rotation_translation();
+        let facing = gun_transform.up().truncate();
+        let gunpoint =
+            gun_translation.truncate() + gun_stats.get_bullet_spawn_offset(gun_scale) * facing;
+        let color = gun_stats.projectile_color.0;
+
+        let path = predict_projectile_path(
+            &rapier_context,
+            &query_obstacles,
+            gun.preset,
+            gunpoint,
+            facing,
+        );
+        spawn_dotted_line(&mut commands, &path, color.with_a(AIM_PREVIEW_PATH_ALPHA));
+
+        if gun_stats.projectile_spread_angle > 0.0 {
+            for side in [-0.5, 0.5] {
+                let edge =
+                    Vec2::from_angle(gun_stats.projectile_spread_angle * side).rotate(facing);
+                spawn_dotted_line(
+                    &mut commands,
+                    &[gunpoint, gunpoint + edge * AIM_PREVIEW_CONE_LENGTH],
+                    color.with_a(AIM_PREVIEW_CONE_ALPHA),
+                );
+            }
+        }
+    }
+}
+
+/// Plugin drawing the aim preview of the guns held by the local players.
+pub struct AimPreviewPlugin;
+impl Plugin for AimPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            (clear_aim_preview, handle_aim_preview)
+                .chain()
+                .distributive_run_if(in_state(GameState::InGame)),
+        );
+    }
+}
@@ -1,7 +1,11 @@
 // todo make it a separate crate -- take common consts and types outside, too
 use crate::characters::{CHARACTER_MAX_HEALTH, CHARACTER_SIZE, CHARACTER_SPEED};
+use crate::deployables::DeployableKind;
 use crate::guns::additives::*;
-use crate::guns::stats::{GunColour, GunPersistentStats, ProjectileSpawnSpace};
+use crate::guns::stats::{
+    DamageFalloff, GunColour, GunPersistentStats, ProjectileSpawnSpace, ProjectileSteering,
+    SteeringBehaviour,
+};
 use crate::health::HitPoints;
 use crate::physics::{ContinuousCollisionDetection, OngoingCollisions, Sensor};
 use crate::ui::colors;
@@ -35,9 +39,14 @@ pub enum GunPreset {
     Scattershot,
     Typhoon,
     LaserGun,
-    // EMPCannon, SmokeCannon, RocketLauncher, RemoteShrapnelLauncher, Termite, PortalGun, MechSword?, -MechScythe?
-    // NanoSwarmLauncher, AssemblyNanoSwarmLauncher, MinePlanter, TeslaCoilLauncher, ArtilleryBattery,
-    // Flammenwerfer, Vulkan, Boomerang, HookMineLauncher, TurretAssembler, ScorpionStinger, HackTaser,
+    MinePlanter,
+    TurretGun,
+    RocketLauncher,
+    NanoSwarmLauncher,
+    Boomerang,
+    // EMPCannon, SmokeCannon, RemoteShrapnelLauncher, Termite, PortalGun, MechSword?, -MechScythe?
+    // AssemblyNanoSwarmLauncher, TeslaCoilLauncher, ArtilleryBattery,
+    // Flammenwerfer, Vulkan, HookMineLauncher, TurretAssembler, ScorpionStinger, HackTaser,
     // IncendiaryBeam, WallRaiser, AcidTrailer, OneWayShield (better used for coop), BombardmentBeacon,
     // SonicBoomer (push enemies into traps!), TunnelDrillClaws (make tunnels in the second plane!), NitrogenSpewer (ice skates and flash freeze),
     // some melee attack always available, ram forward kinda like dodge?, parry to increase bullet speed?,
@@ -80,6 +89,11 @@ impl GunPreset {
             GunPreset::Typhoon => &TYPHOON,
             GunPreset::RailGun => &RAILGUN,
             GunPreset::LaserGun => &LASER_GUN,
+            GunPreset::MinePlanter => &MINE_PLANTER,
+            GunPreset::TurretGun => &TURRET_GUN,
+            GunPreset::RocketLauncher => &ROCKET_LAUNCHER,
+            GunPreset::NanoSwarmLauncher => &NANO_SWARM_LAUNCHER,
+            GunPreset::Boomerang => &BOOMERANG,
         }
     }
 
@@ -127,8 +141,16 @@ pub const REGULAR: GunPersistentStats = GunPersistentStats {
     projectile_size: BULLET_SIZE,
     projectile_color: GunColour::new(Color::rgb(0.8 * 4., 0.2 * 4., 0.2 * 4.)),
     projectile_spawn_point: ProjectileSpawnSpace::Gunpoint,
+    projectile_steering: None,
+    // Long enough to cross the arena a few times, short enough not to bounce around forever.
+    projectile_lifetime: Duration::from_secs(5),
+    projectile_range: f32::INFINITY,
+    projectile_drag: 0.0,
+    aim_preview: true,
     projectile_damage: BULLET_DAMAGE,
+    damage_falloff: None,
     friendly_fire: false,
+    deploys: None,
 };
 
 /// An experimental "upgrade" over a regular gun. Faster, inaccurate, doesn't hit as hard.
@@ -137,6 +159,11 @@ pub const IMPRECISE: GunPersistentStats = GunPersistentStats {
     gun_neutral_color: GunColour::new(colors::DARK_CHESTNUT),
     projectile_spread_angle: PI / 12.,
     projectile_damage: BULLET_DAMAGE * 1.3,
+    damage_falloff: Some(DamageFalloff {
+        start: CHARACTER_SIZE * 5.,
+        end: CHARACTER_SIZE * 12.,
+        min_multiplier: 0.6,
+    }),
     projectile_speed: BULLET_SPEED * 2.,
     reload_time: Duration::from_millis(500),
     shots_before_reload: 15,
@@ -150,6 +177,14 @@ pub const SCATTERSHOT: GunPersistentStats = GunPersistentStats {
     projectile_color: GunColour::new(Color::rgb(0.8 * 4., 0.5 * 4., 0.2 * 4.)),
     projectile_spread_angle: PI / 6.,
     projectile_damage: BULLET_DAMAGE * 0.85,
+    // Short-range by design: the pellets slow down and lose their punch quickly.
+    projectile_range: CHARACTER_SIZE * 7.,
+    projectile_drag: 1.5,
+    damage_falloff: Some(DamageFalloff {
+        start: CHARACTER_SIZE * 2.,
+        end: CHARACTER_SIZE * 6.,
+        min_multiplier: 0.3,
+    }),
     projectiles_per_shot: 12,
     fire_cooldown: Duration::from_millis(600),
     recoil: 6.0,
@@ -168,9 +203,11 @@ pub const TYPHOON: GunPersistentStats = GunPersistentStats {
     projectile_damage: BULLET_DAMAGE * 0.75,
     projectiles_per_shot: 64,
     projectile_elasticity: 1.0,
+    projectile_lifetime: Duration::from_secs(3),
     fire_cooldown: Duration::from_millis(200),
     reload_time: Duration::from_millis(1600),
     shots_before_reload: 2,
+    aim_preview: false,
     ..REGULAR
 };
 
@@ -208,3 +245,99 @@ pub const LASER_GUN: GunPersistentStats = GunPersistentStats {
     min_speed_to_live_multiplier: 0.3,
     ..REGULAR
 };
+
+/// Plants proximity mines instead of shooting. Each one lies in wait and blows up in the face of the first foe to step close.
+pub const MINE_PLANTER: GunPersistentStats = GunPersistentStats {
+    name: "Mine Planter",
+    gun_neutral_color: GunColour::new(colors::JUNGLE_GREEN),
+    // Damage at the heart of the blast, falling off towards its edge.
+    projectile_damage: CHARACTER_MAX_HEALTH * 0.6,
+    fire_cooldown: Duration::from_millis(800),
+    reload_time: Duration::from_millis(3000),
+    shots_before_reload: 3,
+    deploys: Some(DeployableKind::Mine),
+    aim_preview: false,
+    ..REGULAR
+};
+
+/// Mounted on turrets and not found lying around. Steady and modest, but it never tires.
+pub const TURRET_GUN: GunPersistentStats = GunPersistentStats {
+    name: "Turret",
+    gun_length: REGULAR_GUN_LENGTH * 0.6,
+    gun_neutral_color: GunColour::new(Color::GRAY),
+    gun_center_y: REGULAR_GUN_LENGTH * 0.3,
+    projectile_color: GunColour::new(Color::rgb(0.8 * 4., 0.8 * 4., 0.2 * 4.)),
+    projectile_spread_angle: PI / 24.,
+    projectile_damage: BULLET_DAMAGE * 0.8,
+    fire_cooldown: Duration::from_millis(250),
+    aim_preview: false,
+    ..REGULAR
+};
+
+/// Fires a slow, heavy rocket that follows the shooter's facing for as long as the trigger is held.
+pub const ROCKET_LAUNCHER: GunPersistentStats = GunPersistentStats {
+    name: "Rocket Launcher",
+    gun_width: REGULAR_GUN_WIDTH * 1.6,
+    gun_neutral_color: GunColour::new(colors::BEAVER),
+    projectile_color: GunColour::new(Color::rgb(0.9 * 4., 0.6 * 4., 0.1 * 4.)),
+    projectile_size: BULLET_SIZE * 2.,
+    projectile_speed: BULLET_SPEED * 1.2,
+    projectile_damage: BULLET_DAMAGE * 6.,
+    projectile_elasticity: 0.0,
+    projectile_steering: Some(ProjectileSteering {
+        behaviour: SteeringBehaviour::Guided,
+        turn_rate: PI * 1.5,
+    }),
+    projectile_lifetime: Duration::from_secs(4),
+    fire_cooldown: Duration::from_millis(900),
+    reload_time: Duration::from_millis(1500),
+    shots_before_reload: 1,
+    recoil: 8.0,
+    aim_preview: false,
+    ..REGULAR
+};
+
+/// Lets loose a handful of nanobots, each seeking out the nearest foe in front of it. Never a straight line.
+pub const NANO_SWARM_LAUNCHER: GunPersistentStats = GunPersistentStats {
+    name: "Nano Swarm",
+    gun_neutral_color: GunColour::new(colors::ORCHID),
+    projectile_color: GunColour::new(Color::rgb(0.5 * 4., 0.9 * 4., 0.9 * 4.)),
+    projectile_size: BULLET_SIZE * 0.8,
+    projectile_damage: BULLET_DAMAGE * 0.6,
+    projectiles_per_shot: 5,
+    projectile_spread_angle: PI / 3.,
+    projectile_steering: Some(ProjectileSteering {
+        behaviour: SteeringBehaviour::Homing {
+            cone_angle: PI / 2.,
+            range: CHARACTER_SIZE * 6.,
+        },
+        turn_rate: PI * 2.,
+    }),
+    projectile_lifetime: Duration::from_millis(2500),
+    fire_cooldown: Duration::from_millis(500),
+    reload_time: Duration::from_millis(1400),
+    shots_before_reload: 4,
+    aim_preview: false,
+    ..REGULAR
+};
+
+/// What goes around, comes around. Flies out, turns back, and is caught by the thrower on its return.
+pub const BOOMERANG: GunPersistentStats = GunPersistentStats {
+    name: "Boomerang",
+    gun_neutral_color: GunColour::new(colors::CANVAS),
+    projectile_color: GunColour::new(Color::rgb(0.9 * 4., 0.8 * 4., 0.5 * 4.)),
+    projectile_size: BULLET_SIZE * 2.4,
+    projectile_speed: BULLET_SPEED * 1.3,
+    projectile_damage: BULLET_DAMAGE * 3.,
+    projectile_elasticity: 1.0,
+    projectile_steering: Some(ProjectileSteering {
+        behaviour: SteeringBehaviour::Boomerang {
+            return_after: Duration::from_millis(600),
+        },
+        turn_rate: PI * 3.,
+    }),
+    projectile_lifetime: Duration::from_secs(4),
+    fire_cooldown: Duration::from_millis(1000),
+    aim_preview: false,
+    ..REGULAR
+};
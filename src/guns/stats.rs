@@ -1,3 +1,4 @@
+use crate::deployables::DeployableKind;
 use crate::guns::{GUN_VELOCITY_DAMPING_RATIO, GUN_Z_LAYER};
 use crate::physics::{popular_collider, CollisionLayer, KinematicsBundle, RigidBody};
 use bevy::math::Vec3;
@@ -74,8 +75,41 @@ pub enum ProjectileSpawnSpace {
     Perimeter,
 }
 
-// todo projectile trajectory dotted lines. So many projectile types, though...
-// references: Brigador, PC billiard. Experiment!
+/// Ways a projectile can steer itself in flight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SteeringBehaviour {
+    /// Seeks out the nearest foe within a cone ahead of it.
+    Homing {
+        /// The total angle in radians of the cone the projectile looks for foes in.
+        cone_angle: f32,
+        /// Distance the projectile notices foes from.
+        range: f32,
+    },
+    /// Follows the shooter's facing for as long as they hold the trigger.
+    Guided,
+    /// Flies out, then turns back to the shooter, and is caught once it arrives.
+    Boomerang {
+        /// Time in flight before the projectile turns back.
+        return_after: Duration,
+    },
+}
+
+/// How a projectile steers itself in flight.
+pub struct ProjectileSteering {
+    pub behaviour: SteeringBehaviour,
+    /// Fastest the projectile can turn, in radians per second.
+    pub turn_rate: f32,
+}
+
+/// How the damage of a projectile falls off with the distance it has travelled.
+pub struct DamageFalloff {
+    /// Distance travelled before the damage starts falling off.
+    pub start: f32,
+    /// Distance travelled at which the damage bottoms out.
+    pub end: f32,
+    /// Share of the damage still dealt past the end of the falloff.
+    pub min_multiplier: f32,
+}
 
 /// Fixed variables per gun preset that are typically accessed via a look-up tree.
 pub struct GunPersistentStats {
@@ -122,6 +156,17 @@ pub struct GunPersistentStats {
     pub projectile_density: f32,
     /// Where the projectile spawns: where the gun barrel ends, or around a character centered on its center
     pub projectile_spawn_point: ProjectileSpawnSpace,
+    /// How the projectile steers itself in flight, if it does at all.
+    pub projectile_steering: Option<ProjectileSteering>,
+    /// Time the projectile stays in flight before it's spent and disappears.
+    pub projectile_lifetime: Duration,
+    /// Distance the projectile travels before it's spent and disappears.
+    pub projectile_range: f32,
+    /// How fast the projectile loses its speed in flight, as the physics' linear damping.
+    pub projectile_drag: f32,
+    /// Whether the local players holding the gun are shown the path its projectiles would take.
+    /// Best left off for those that don't fly straight.
+    pub aim_preview: bool,
 
     /// Size of each projectile.
     pub projectile_size: f32,
@@ -130,8 +175,12 @@ pub struct GunPersistentStats {
 
     /// Damage each projectile deals to the body it hits.
     pub projectile_damage: f32,
+    /// How the damage falls off the farther the projectile flies, if it does at all.
+    pub damage_falloff: Option<DamageFalloff>,
     /// Does the gun deal damage to the bodies it hits that share the team with the shooter?
     pub friendly_fire: bool,
+    /// Gear the gun sets down at its barrel's exit with every shot, instead of firing projectiles.
+    pub deploys: Option<DeployableKind>,
 }
 
 impl GunPersistentStats {
@@ -168,4 +217,23 @@ impl GunPersistentStats {
     pub fn is_projectile_busted(&self, projectile_speed: f32) -> bool {
         projectile_speed <= self.projectile_speed * self.min_speed_to_live_multiplier
     }
+
+    /// Indicate whether the projectile has flown for too long or too far, and should be despawned.
+    pub fn is_projectile_spent(&self, flight_time: Duration, distance_travelled: f32) -> bool {
+        flight_time >= self.projectile_lifetime || distance_travelled >= self.projectile_range
+    }
+
+    /// Get the share of the damage a projectile still deals, having travelled some distance.
+    pub fn damage_falloff_multiplier(&self, distance_travelled: f32) -> f32 {
+        let Some(falloff) = &self.damage_falloff else {
+            return 1.0;
+        };
+        if distance_travelled <= falloff.start {
+            return 1.0;
+        }
+        let progress = ((distance_travelled - falloff.start)
+            / (falloff.end - falloff.start).max(f32::EPSILON))
+        .min(1.0);
+        1.0 - progress * (1.0 - falloff.min_multiplier)
+    }
 }
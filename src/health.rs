@@ -1,3 +1,4 @@
+use crate::audio::SoundCue;
 use crate::characters::PlayerControlled;
 use crate::network::{PlayerHandle, PlayerRegistry};
 use crate::ui::chat::ChatMessage;
@@ -14,12 +15,18 @@ pub type HitPoints = f32;
 #[derive(Component, Debug, Default, PartialEq, Reflect, FromReflect)]
 pub struct Health {
     hp: HitPoints,
-    // armor? max?
+    max_hp: HitPoints,
+    /// Points soaking up damage before hit points do. Cannot exceed the maximum hit points.
+    armor: HitPoints,
 }
 
 impl Health {
     pub fn new(max_health: HitPoints) -> Self {
-        Self { hp: max_health }
+        Self {
+            hp: max_health,
+            max_hp: max_health,
+            armor: 0.0,
+        }
     }
 
     /// Get current hit points.
@@ -27,12 +34,42 @@ impl Health {
         self.hp
     }
 
-    /// Take off (or add, if negative) some hit points.
+    /// Get the hit points the entity cannot be healed above.
+    pub const fn max_hp(&self) -> HitPoints {
+        self.max_hp
+    }
+
+    /// Get current armor points.
+    pub const fn armor(&self) -> HitPoints {
+        self.armor
+    }
+
+    /// Take off (or add, if negative) some hit points. Armor takes the damage first.
     pub fn damage(&mut self, damage: HitPoints) -> bool {
-        self.hp -= damage;
+        let absorbed = self.armor.min(damage.max(0.0));
+        self.armor -= absorbed;
+        self.hp -= damage - absorbed;
         self.is_dead()
     }
 
+    /// Restore some hit points, up to the maximum. Returns `false` if there was nothing to heal.
+    pub fn heal(&mut self, hp: HitPoints) -> bool {
+        if self.hp >= self.max_hp {
+            return false;
+        }
+        self.hp = (self.hp + hp).min(self.max_hp);
+        true
+    }
+
+    /// Add some armor points, up to the maximum hit points. Returns `false` if the armor was already full.
+    pub fn add_armor(&mut self, armor: HitPoints) -> bool {
+        if self.armor >= self.max_hp {
+            return false;
+        }
+        self.armor = (self.armor + armor).min(self.max_hp);
+        true
+    }
+
     /// Check if the character is dead.
     pub fn is_dead(&self) -> bool {
         self.hp <= 0.0
@@ -41,7 +78,7 @@ impl Health {
 
 impl From<f32> for Health {
     fn from(hp: HitPoints) -> Self {
-        Self { hp }
+        Self::new(hp)
     }
 }
 
@@ -57,10 +94,12 @@ pub fn handle_death(
     mut commands: Commands,
     mut query_lives: Query<(&Health, Entity, Option<&PlayerControlled>, &Dying)>,
     mut dead_teller: EventWriter<PlayerDied>,
+    mut sound_cues: EventWriter<SoundCue>,
 ) {
     for (life, entity, maybe_player, dying) in query_lives.iter_mut() {
         if life.is_dead() {
             commands.entity(entity).despawn_recursive();
+            sound_cues.send(SoundCue::Death);
             // todo handle respawning AI also somehow
             if let Some(player) = maybe_player {
                 dead_teller.send(PlayerDied {
@@ -100,15 +139,12 @@ pub fn handle_reporting_death(
                     if killer_data.team != player_team {
                         killer_data.kills += 1;
                     }
-                    ChatMessage {
-                        message: "{0} killed {1}!".to_string(),
-                        player_handles: vec![killer, event.player_handle],
-                    }
+                    ChatMessage::message("{0} killed {1}!")
+                        .with_handles(vec![killer, event.player_handle])
                 })
             })
-            .unwrap_or(ChatMessage {
-                message: "{0} died!".to_string(),
-                player_handles: vec![event.player_handle],
+            .unwrap_or_else(|| {
+                ChatMessage::message("{0} died!").with_handles(vec![event.player_handle])
             });
 
         postman.send(message);
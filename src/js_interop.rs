@@ -18,10 +18,6 @@ extern "C" {
     #[wasm_bindgen(js_name = getNewWindowSize)]
     pub fn get_new_window_size_from_js() -> Vec<f32>;
 
-    /// Get player input from JS joysticks.
-    #[wasm_bindgen(js_name = getSticksPosition)]
-    pub fn get_sticks_positions_from_js() -> Vec<f32>;
-
     /// Send JS to make a promise to fill a buffer with paste data from the clipboard for the entity to access later.
     #[wasm_bindgen(js_name = setPasteBuffer)]
     pub fn set_js_paste_buffer(entity_index: u32);
@@ -29,4 +25,24 @@ extern "C" {
     /// Check and get the paste from the clipboard filled into a buffer with a prior JS promise.
     #[wasm_bindgen(catch, js_name = getPasteBuffer)]
     pub fn get_js_paste_buffer(entity_index: u32) -> Result<Option<String>, JsValue>;
+
+    /// Send an HTTP request with JS, resolving into the response's text, or rejecting with the error's.
+    #[wasm_bindgen(catch, js_name = fetchText)]
+    pub async fn fetch_text_from_js(
+        method: &str,
+        url: &str,
+        body: Option<String>,
+    ) -> Result<JsValue, JsValue>;
+
+    /// Get a value stored in the browser's local storage under the key, if there is any.
+    #[wasm_bindgen(js_name = loadFromStorage)]
+    pub fn load_from_js_storage(key: &str) -> Option<String>;
+
+    /// Store a value in the browser's local storage under the key.
+    #[wasm_bindgen(js_name = saveToStorage)]
+    pub fn save_to_js_storage(key: &str, value: &str);
+
+    /// Get the current time in seconds since the Unix epoch from JS.
+    #[wasm_bindgen(js_name = getUnixTime)]
+    pub fn get_unix_time_from_js() -> f64;
 }
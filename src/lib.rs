@@ -1,44 +1,77 @@
 mod ai;
+mod audio;
 mod characters;
+mod classes;
 mod controls;
+mod deployables;
 mod easter;
+mod effects;
 mod guns;
 mod health;
 #[cfg(target_arch = "wasm32")]
 mod js_interop;
+mod match_stats;
+mod modifiers;
 mod network;
 mod physics;
 mod projectiles;
 mod scenes;
+mod spawners;
 mod teams;
 mod ui;
 
 pub use ai::{handle_ai_input, AIActionRoutine};
+pub use audio::{SoundCue, SpaceballsAudioPlugin};
 pub use characters::{
     calculate_character_velocity, handle_gun_picking, handle_inventory_layout_change,
     handle_letting_gear_go, PlayerCharacterBundle,
 };
+pub use classes::{handle_class_abilities, AbilityCooldown};
+pub use controls::devices::ConnectedGamepads;
 pub use controls::{
     handle_gamepad_connections, handle_online_player_input, handle_pause_input, process_input,
     CharacterActionInput, InputHandlingSet,
 };
+pub use deployables::{handle_mine_triggering, handle_turret_targeting, Deployable};
 pub use easter::EasterAnnouncementPlugin;
-pub use guns::{systems::*, Equipped, Gun, GunBundle, GunPreset, LastUnequippedAt};
+pub use effects::VisualEffectsPlugin;
+pub use guns::{
+    additives::steering::Steered, aim_preview::AimPreviewPlugin, systems::*, Equipped, Gun,
+    GunBundle, GunPreset, LastUnequippedAt,
+};
 pub use health::{handle_death, handle_reporting_death, Dying, Health};
-pub use network::players::{handle_match_time, send_new_players_joined};
+pub use match_stats::{queue_stat_events, MatchStatsPlugin};
+pub use modifiers::{
+    handle_invisibility_cosmetic_change, handle_modifier_expiration, ModifierStack,
+};
+pub use network::confirmed::{
+    count_frame, queue_confirmed_events, ConfirmedEventsPlugin, FrameCount,
+};
+pub use network::players::{
+    handle_disconnected_players, handle_match_time, send_new_players_joined,
+};
+pub use network::rooms::{room_slug, RoomInfo};
 pub use network::{
     GGRSConfig, GGRSPlugin, GGRSSchedule, MultiplayerPlugins, PlayerCount, PlayerDied, PlayerJoined,
 };
 pub use physics::{
-    handle_entities_out_of_bounds, ActiveEvents, RectangularObstacleBundle, Sleeping,
-    SpaceballsPhysicsPlugin, Velocity, CHUNKS_ON_SCREEN_SIDE, CHUNK_SIZE,
+    handle_entities_out_of_bounds, ActiveEvents, ObstacleIntegrity, ObstacleMaterial,
+    RectangularObstacleBundle, Sleeping, SpaceballsPhysicsPlugin, Velocity, CHUNKS_ON_SCREEN_SIDE,
+    CHUNK_SIZE,
+};
+pub use projectiles::{
+    handle_bullet_collision_events, handle_projectile_flight, DamageDealt, Projectile,
+    ProjectileImpact,
 };
-pub use projectiles::handle_bullet_collision_events;
 pub use scenes::{
     despawn_everything, handle_player_respawning, handle_respawn_point_occupation,
     reset_spawn_queue, summon_scene, SceneSelector, SpawnPoint, SpawnQueue,
 };
-pub use teams::{AI_DEFAULT_TEAM, PLAYER_DEFAULT_TEAM};
+pub use spawners::{
+    handle_item_spawning, handle_pickup_collection, handle_spawner_countdown_display,
+    setup_spawner_countdown_display, ItemSpawner, Pickup,
+};
+pub use teams::{TeamColorsPlugin, AI_DEFAULT_TEAM, PLAYER_DEFAULT_TEAM};
 pub use ui::{MenuState, UIPlugins};
 
 pub use bevy::prelude::*;
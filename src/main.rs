@@ -13,6 +13,7 @@ fn main() {
     GGRSPlugin::<GGRSConfig>::new()
         .with_input_system(process_input)
         .register_rollback_resource::<EntropyGenerator>()
+        .register_rollback_resource::<FrameCount>()
         // todo:mp figure out why rolling back `SpawnQueue` forces re-rolls of EntropyGenerator
         // not critical
         // .register_rollback_resource::<SpawnQueue>()
@@ -29,6 +30,14 @@ fn main() {
         .register_rollback_component::<LastUnequippedAt>()
         .register_rollback_component::<Health>()
         .register_rollback_component::<Dying>()
+        .register_rollback_component::<ItemSpawner>()
+        .register_rollback_component::<Pickup>()
+        .register_rollback_component::<ModifierStack>()
+        .register_rollback_component::<AbilityCooldown>()
+        .register_rollback_component::<Deployable>()
+        .register_rollback_component::<Projectile>()
+        .register_rollback_component::<Steered>()
+        .register_rollback_component::<ObstacleIntegrity>()
         // .register_rollback_component::<Children>()
         .build(&mut app);
 
@@ -44,9 +53,12 @@ fn main() {
         .init_resource::<EntropyGenerator>()
         // probably displace to plugin
         .init_resource::<SpawnQueue>()
+        .init_resource::<ConnectedGamepads>()
         .add_state::<GameState>()
         .add_state::<LimboState>()
         .add_event::<GamePauseEvent>()
+        .add_event::<DamageDealt>()
+        .add_event::<ProjectileImpact>()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(create_window(800., 800.)),
             ..default()
@@ -56,6 +68,13 @@ fn main() {
         .add_plugin(RapierPhysicsPlugin::<()>::default())
         .add_plugin(SpaceballsPhysicsPlugin)
         .add_plugin(EasterAnnouncementPlugin)
+        .add_plugin(SpaceballsAudioPlugin)
+        .add_plugin(MatchStatsPlugin)
+        .add_plugin(ConfirmedEventsPlugin::<DamageDealt>::default())
+        .add_plugin(ConfirmedEventsPlugin::<ProjectileImpact>::default())
+        .add_plugin(TeamColorsPlugin)
+        .add_plugin(VisualEffectsPlugin)
+        .add_plugin(AimPreviewPlugin)
         .configure_sets(
             // ggrs couldn't give two flying shits about this one
             (
@@ -81,9 +100,10 @@ fn main() {
                 .run_if(in_state(GameState::InGame))
                 .in_set(InputHandlingSet::InputReading),
         )
-        .add_system(
-            handle_online_player_input
-                .run_if(in_state(GameState::InGame))
+        .add_systems(
+            (handle_online_player_input, handle_disconnected_players)
+                .chain()
+                .distributive_run_if(in_state(GameState::InGame))
                 .in_set(InputHandlingSet::InputReading)
                 .in_schedule(GGRSSchedule),
         )
@@ -91,20 +111,43 @@ fn main() {
             // todo:mp de-chain, group independent systems into sets
             (
                 calculate_character_velocity,
+                handle_turret_targeting,
                 handle_gunfire,
+                handle_projectile_flight,
+                handle_projectile_steering,
                 handle_letting_gear_go,
                 handle_gun_picking,
+                handle_pickup_collection,
+                handle_class_abilities,
+                handle_modifier_expiration,
                 handle_gun_cleanup,
                 handle_inventory_layout_change,
                 handle_gun_arriving_at_rest,
+            )
+                .chain()
+                .in_set(InputHandlingSet::ResponseProcessing)
+                .after(InputHandlingSet::InputReading)
+                .in_schedule(GGRSSchedule),
+        )
+        .add_systems(
+            // too many systems for a single tuple, continue the chain
+            (
                 handle_bullet_collision_events,
                 handle_railgun_penetration_damage,
+                handle_mine_triggering,
                 handle_death,
                 send_new_players_joined,
                 handle_respawn_point_occupation,
                 handle_player_respawning,
+                handle_item_spawning,
+                count_frame,
+                queue_confirmed_events::<SoundCue>,
+                queue_stat_events,
+                queue_confirmed_events::<DamageDealt>,
+                queue_confirmed_events::<ProjectileImpact>,
             )
                 .chain()
+                .after(handle_gun_arriving_at_rest)
                 .in_set(InputHandlingSet::ResponseProcessing)
                 .after(InputHandlingSet::InputReading)
                 .in_schedule(GGRSSchedule),
@@ -126,6 +169,9 @@ fn main() {
         .add_systems((
             handle_gun_ownership_cosmetic_change,
             handle_gun_idle_bobbing,
+            setup_spawner_countdown_display,
+            handle_spawner_countdown_display,
+            handle_invisibility_cosmetic_change,
         ))
         .add_system(
             calculate_main_camera_projection_scale
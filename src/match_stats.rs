@@ -0,0 +1,221 @@
+//! Detailed statistics of every player in the match, from the shots they fire to the pickups they grab.
+//! Like the sounds, the stats are gathered from the rollback systems and only counted once their frames are confirmed.
+
+use crate::network::confirmed::{
+    confirmed_frame, reset_confirmed_event_queue, ConfirmedEventQueue, FrameCount,
+};
+use crate::network::{GGRSConfig, PlayerHandle, PlayerRegistry, MAINTAINED_FPS_F64};
+use crate::projectiles::DamageDealt;
+use crate::{GameState, GunPreset, PlayerDied};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_ggrs::Session;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Event of something worth counting happening in the simulation, not already told by another event.
+/// Sent from the rollback systems.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatEvent {
+    Fired {
+        shooter: PlayerHandle,
+        gun_type: GunPreset,
+        projectiles: u32,
+    },
+    Spawned {
+        player: PlayerHandle,
+    },
+    PickedUp {
+        player: PlayerHandle,
+    },
+}
+
+/// Everything the stats are counted from, as it happened on some frame.
+#[derive(Clone, Debug)]
+pub enum StatRecord {
+    Event(StatEvent),
+    Damage(DamageDealt),
+    Death(PlayerHandle),
+}
+
+/// How a player has fared with one kind of gun.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GunUsage {
+    /// Projectiles fired, every pellet of a shotgun blast counted.
+    pub projectiles: u32,
+    pub hits: u32,
+    pub kills: u32,
+    pub damage: f32,
+}
+
+impl GunUsage {
+    /// Share of the projectiles that hit someone, from 0 to 1.
+    pub fn accuracy(&self) -> f32 {
+        if self.projectiles == 0 {
+            return 0.0;
+        }
+        self.hits as f32 / self.projectiles as f32
+    }
+}
+
+/// Detailed statistics of a player's match.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerStats {
+    pub guns: HashMap<GunPreset, GunUsage>,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub kill_streak: u32,
+    pub longest_kill_streak: u32,
+    pub pickups: u32,
+    /// Frames spent alive in the lives that have ended.
+    alive_frames: i32,
+    /// Frame the current life has started on, if the player is alive.
+    alive_since_frame: Option<i32>,
+}
+
+impl PlayerStats {
+    /// Usage of all guns together.
+    pub fn total(&self) -> GunUsage {
+        self.guns
+            .values()
+            .fold(GunUsage::default(), |total, usage| GunUsage {
+                projectiles: total.projectiles + usage.projectiles,
+                hits: total.hits + usage.hits,
+                kills: total.kills + usage.kills,
+                damage: total.damage + usage.damage,
+            })
+    }
+
+    /// Usage of the gun, if it was used at all.
+    pub fn gun(&self, gun_type: GunPreset) -> GunUsage {
+        self.guns.get(&gun_type).cloned().unwrap_or_default()
+    }
+
+    /// Time spent alive up to the frame, counting in the current life.
+    pub fn time_alive(&self, frame: i32) -> Duration {
+        let current_life_frames = self
+            .alive_since_frame
+            .map_or(0, |since| (frame - since).max(0));
+        Duration::from_secs_f64(
+            (self.alive_frames + current_life_frames) as f64 / MAINTAINED_FPS_F64,
+        )
+    }
+
+    fn end_life(&mut self, frame: i32) {
+        if let Some(since) = self.alive_since_frame.take() {
+            self.alive_frames += (frame - since).max(0);
+        }
+        self.kill_streak = 0;
+    }
+}
+
+/// System to stash what happened on the frame until it's confirmed.
+/// Must run after [`count_frame`](crate::network::confirmed::count_frame).
+pub fn queue_stat_events(
+    frame: Res<FrameCount>,
+    mut stat_events: EventReader<StatEvent>,
+    mut damage_events: EventReader<DamageDealt>,
+    mut death_events: EventReader<PlayerDied>,
+    mut queue: ResMut<ConfirmedEventQueue<StatRecord>>,
+) {
+    let records = stat_events
+        .iter()
+        .map(|event| StatRecord::Event(*event))
+        .chain(damage_events.iter().cloned().map(StatRecord::Damage))
+        .chain(
+            death_events
+                .iter()
+                .map(|event| StatRecord::Death(event.player_handle)),
+        );
+    queue.push_frame(frame.0, records);
+}
+
+/// Resource of the projectiles that have been counted as hits, along with their victims.
+/// A projectile dealing damage over several frames, like a railgun slug, only hits each victim once.
+#[derive(Resource, Debug, Default)]
+pub struct CountedHits(HashSet<(Entity, Entity)>);
+
+fn reset_counted_hits(mut counted_hits: ResMut<CountedHits>) {
+    counted_hits.0.clear();
+}
+
+/// System to count the stats from the frames that can no longer be rolled back.
+fn apply_confirmed_stat_records(
+    session: Option<Res<Session<GGRSConfig>>>,
+    mut queue: ResMut<ConfirmedEventQueue<StatRecord>>,
+    mut counted_hits: ResMut<CountedHits>,
+    mut players: ResMut<PlayerRegistry>,
+) {
+    if queue.is_empty() {
+        return;
+    }
+
+    for (frame, record) in queue.take_confirmed(confirmed_frame(session.as_deref())) {
+        match record {
+            StatRecord::Event(StatEvent::Fired {
+                shooter,
+                gun_type,
+                projectiles,
+            }) => {
+                if let Some(player) = players.0.get_mut(shooter) {
+                    player.stats.guns.entry(gun_type).or_default().projectiles += projectiles;
+                }
+            }
+            StatRecord::Event(StatEvent::Spawned { player }) => {
+                if let Some(player) = players.0.get_mut(player) {
+                    player.stats.alive_since_frame = Some(frame);
+                }
+            }
+            StatRecord::Event(StatEvent::PickedUp { player }) => {
+                if let Some(player) = players.0.get_mut(player) {
+                    player.stats.pickups += 1;
+                }
+            }
+            StatRecord::Damage(damage) => {
+                let is_friendly = damage.victim_team == Some(damage.shooter_team);
+                if let Some(shooter) = damage.shooter_handle {
+                    if let Some(shooter) = players.0.get_mut(shooter) {
+                        let stats = &mut shooter.stats;
+                        stats.damage_dealt += damage.damage;
+                        let usage = stats.guns.entry(damage.gun_type).or_default();
+                        if counted_hits.0.insert((damage.projectile, damage.victim)) {
+                            usage.hits += 1;
+                        }
+                        usage.damage += damage.damage;
+                        if damage.is_fatal && !is_friendly {
+                            usage.kills += 1;
+                            stats.kill_streak += 1;
+                            stats.longest_kill_streak =
+                                stats.longest_kill_streak.max(stats.kill_streak);
+                        }
+                    }
+                }
+                if let Some(victim) = damage.victim_handle {
+                    if let Some(victim) = players.0.get_mut(victim) {
+                        victim.stats.damage_taken += damage.damage;
+                    }
+                }
+            }
+            StatRecord::Death(player) => {
+                if let Some(player) = players.0.get_mut(player) {
+                    player.stats.end_life(frame);
+                }
+            }
+        }
+    }
+}
+
+/// Plugin gathering the players' match stats. The rollback system [`queue_stat_events`] must be scheduled separately.
+pub struct MatchStatsPlugin;
+impl Plugin for MatchStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StatEvent>()
+            .init_resource::<ConfirmedEventQueue<StatRecord>>()
+            .init_resource::<CountedHits>()
+            .add_system(
+                reset_confirmed_event_queue::<StatRecord>.in_schedule(OnEnter(GameState::InGame)),
+            )
+            .add_system(reset_counted_hits.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(apply_confirmed_stat_records.run_if(in_state(GameState::InGame)));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::health::HitPoints;
+use crate::network::session::LocalPlayer;
+use crate::network::MAINTAINED_FPS_F64;
+use bevy::hierarchy::Children;
+use bevy::prelude::{Component, FromReflect, Query, Reflect, Sprite, Timer, TimerMode, Without};
+use std::time::Duration;
+
+/// Multiplier of projectile damage while the damage boost is active.
+const DAMAGE_BOOST_MULTIPLIER: f32 = 1.5;
+/// Multiplier of character speed while the speed boost is active.
+const SPEED_BOOST_MULTIPLIER: f32 = 1.5;
+/// Projectiles added to every shot while multi-shot is active.
+const MULTI_SHOT_EXTRA_PROJECTILES: u32 = 2;
+/// Angle in radians between the extra projectiles fanning out of a multi-shot.
+pub const MULTI_SHOT_FAN_ANGLE: f32 = std::f32::consts::PI / 24.;
+
+/// Alpha of an invisible character as seen by other players.
+const INVISIBLE_ALPHA: f32 = 0.08;
+/// Alpha of an invisible character as seen by its own player, who still needs to know where they are.
+const INVISIBLE_ALPHA_LOCAL: f32 = 0.4;
+
+/// Kind of a temporary boost a character can get from a pickup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+pub enum PowerUp {
+    /// Projectiles fired deal more damage.
+    #[default]
+    DamageBoost,
+    /// The character moves faster.
+    SpeedBoost,
+    /// The character is barely visible to the others.
+    Invisibility,
+    /// Guns fire extra projectiles with every shot.
+    MultiShot,
+}
+
+impl PowerUp {
+    /// Get the time the power-up stays active after being picked up.
+    pub const fn duration(&self) -> Duration {
+        match self {
+            PowerUp::DamageBoost => Duration::from_secs(10),
+            PowerUp::SpeedBoost => Duration::from_secs(10),
+            PowerUp::Invisibility => Duration::from_secs(8),
+            PowerUp::MultiShot => Duration::from_secs(10),
+        }
+    }
+}
+
+/// A power-up in effect and the time it has left.
+#[derive(Clone, Debug, Default, Reflect, FromReflect)]
+pub struct ActiveModifier {
+    pub power_up: PowerUp,
+    pub timeout: Timer,
+}
+
+/// Stack of modifiers active on a character.
+/// Any stat of a character or its guns that may be modified should be read through this.
+#[derive(Component, Clone, Debug, Default, Reflect, FromReflect)]
+pub struct ModifierStack {
+    modifiers: Vec<ActiveModifier>,
+}
+
+impl ModifierStack {
+    /// Put a power-up into effect. If it is already active, its timer is reset instead.
+    pub fn add(&mut self, power_up: PowerUp) {
+        self.add_for(power_up, power_up.duration());
+    }
+
+    /// Put a power-up into effect for some time other than its usual duration.
+    /// If it is already active, it is left with whichever time is longer.
+    pub fn add_for(&mut self, power_up: PowerUp, duration: Duration) {
+        if let Some(modifier) = self
+            .modifiers
+            .iter_mut()
+            .find(|modifier| modifier.power_up == power_up)
+        {
+            if modifier.timeout.remaining() < duration {
+                modifier.timeout = Timer::new(duration, TimerMode::Once);
+            }
+            return;
+        }
+        self.modifiers.push(ActiveModifier {
+            power_up,
+            timeout: Timer::new(duration, TimerMode::Once),
+        });
+    }
+
+    /// Check if a power-up is currently in effect.
+    pub fn has(&self, power_up: PowerUp) -> bool {
+        self.modifiers
+            .iter()
+            .any(|modifier| modifier.power_up == power_up)
+    }
+
+    /// Iterate over the active modifiers.
+    pub fn iter(&self) -> impl Iterator<Item = &ActiveModifier> {
+        self.modifiers.iter()
+    }
+
+    /// Advance the timers of all modifiers and drop those that have run out.
+    pub fn tick(&mut self, delta: Duration) {
+        self.modifiers
+            .retain_mut(|modifier| !modifier.timeout.tick(delta).finished());
+    }
+
+    /// Get the damage a projectile deals, given its base damage.
+    pub fn projectile_damage(&self, damage: HitPoints) -> HitPoints {
+        if self.has(PowerUp::DamageBoost) {
+            damage * DAMAGE_BOOST_MULTIPLIER
+        } else {
+            damage
+        }
+    }
+
+    /// Get the number of projectiles in a single shot, given the gun's base number.
+    pub fn projectiles_per_shot(&self, projectiles: u32) -> u32 {
+        if self.has(PowerUp::MultiShot) {
+            projectiles + MULTI_SHOT_EXTRA_PROJECTILES
+        } else {
+            projectiles
+        }
+    }
+
+    /// Get the linear speed of a character, given its base speed.
+    pub fn speed(&self, speed: f32) -> f32 {
+        if self.has(PowerUp::SpeedBoost) {
+            speed * SPEED_BOOST_MULTIPLIER
+        } else {
+            speed
+        }
+    }
+
+    /// Get the alpha a character's sprite should have, depending on whether it's seen by its own player.
+    pub fn sprite_alpha(&self, is_local: bool) -> f32 {
+        match (self.has(PowerUp::Invisibility), is_local) {
+            (false, _) => 1.0,
+            (true, false) => INVISIBLE_ALPHA,
+            (true, true) => INVISIBLE_ALPHA_LOCAL,
+        }
+    }
+}
+
+/// System to count down the active modifiers and remove those that have expired.
+pub fn handle_modifier_expiration(mut query_modifiers: Query<&mut ModifierStack>) {
+    for mut modifiers in query_modifiers.iter_mut() {
+        if modifiers.modifiers.is_empty() {
+            continue;
+        }
+        // GGRS fixed ticks
+        modifiers.tick(Duration::from_secs_f64(1. / MAINTAINED_FPS_F64));
+    }
+}
+
+/// System to fade out invisible characters along with their gear. Their own player still sees them, if faintly.
+pub fn handle_invisibility_cosmetic_change(
+    mut query_characters: Query<(
+        &ModifierStack,
+        &mut Sprite,
+        Option<&Children>,
+        Option<&LocalPlayer>,
+    )>,
+    mut query_gear: Query<&mut Sprite, Without<ModifierStack>>,
+) {
+    for (modifiers, mut sprite, children, maybe_local) in query_characters.iter_mut() {
+        let alpha = modifiers.sprite_alpha(maybe_local.is_some());
+        if sprite.color.a() != alpha {
+            sprite.color.set_a(alpha);
+        }
+
+        // guns get repainted when picked up, so they have to be checked separately
+        let mut gear = query_gear.iter_many_mut(children.into_iter().flatten());
+        while let Some(mut gear_sprite) = gear.fetch_next() {
+            if gear_sprite.color.a() != alpha {
+                gear_sprite.color.set_a(alpha);
+            }
+        }
+    }
+}
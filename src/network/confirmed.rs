@@ -0,0 +1,195 @@
+//! Holding back what happens in the rollback simulation until the frames it happened on are confirmed,
+//! so that whatever plays, shows or counts it outside of the simulation never does so for what gets rolled back.
+
+use crate::network::GGRSConfig;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy_ggrs::Session;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// Number of the simulation frame, kept in rollback to tell which frame something happened on.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+#[reflect(Resource)]
+pub struct FrameCount(pub i32);
+
+/// Get the furthest frame on [`FrameCount`] that can no longer be rolled back.
+pub fn confirmed_frame(session: Option<&Session<GGRSConfig>>) -> i32 {
+    // The frame counter is ahead of GGRS's by one, since it's incremented after the frame's inputs are in.
+    match session {
+        Some(Session::P2PSession(session)) => session.confirmed_frame().saturating_add(1),
+        // Nothing to roll back without a peer to wait on
+        _ => i32::MAX,
+    }
+}
+
+/// Event of something having happened in the simulation on a frame that can no longer be rolled back.
+#[derive(Clone, Debug)]
+pub struct Confirmed<T>(pub T);
+
+impl<T> Deref for Confirmed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Things that happened in the simulation, waiting for their frames to be confirmed.
+#[derive(Resource, Debug)]
+pub struct ConfirmedEventQueue<T: Send + Sync + 'static> {
+    pending: Vec<(i32, T)>,
+    /// The furthest frame simulated so far. Simulating it or any before it again means a rollback has happened.
+    last_simulated_frame: i32,
+}
+
+impl<T: Send + Sync + 'static> Default for ConfirmedEventQueue<T> {
+    fn default() -> Self {
+        Self {
+            pending: vec![],
+            last_simulated_frame: 0,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> ConfirmedEventQueue<T> {
+    /// Stash what happened on the frame until it's confirmed.
+    /// If the frame is being re-simulated, what was predicted for it and the frames after is thrown away.
+    pub fn push_frame(&mut self, frame: i32, events: impl IntoIterator<Item = T>) {
+        if frame <= self.last_simulated_frame {
+            self.pending.retain(|(event_frame, _)| *event_frame < frame);
+        }
+        self.last_simulated_frame = frame;
+        self.pending
+            .extend(events.into_iter().map(|event| (frame, event)));
+    }
+
+    /// Take out what happened on the frames up to the confirmed one, in order, along with the frames.
+    pub fn take_confirmed(&mut self, confirmed_frame: i32) -> Vec<(i32, T)> {
+        let (confirmed, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(frame, _)| *frame <= confirmed_frame);
+        self.pending = pending;
+        confirmed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// System to count the simulated frame. Must run before the events of the frame are queued.
+pub fn count_frame(mut frame: ResMut<FrameCount>) {
+    frame.0 += 1;
+}
+
+/// System to stash the events of the frame until it's confirmed. Must run after [`count_frame`].
+pub fn queue_confirmed_events<T: Event + Clone>(
+    frame: Res<FrameCount>,
+    mut events: EventReader<T>,
+    mut queue: ResMut<ConfirmedEventQueue<T>>,
+) {
+    queue.push_frame(frame.0, events.iter().cloned());
+}
+
+/// System to pass on the events of the frames that can no longer be rolled back.
+fn send_confirmed_events<T: Event>(
+    session: Option<Res<Session<GGRSConfig>>>,
+    mut queue: ResMut<ConfirmedEventQueue<T>>,
+    mut confirmed_events: EventWriter<Confirmed<T>>,
+) {
+    if queue.is_empty() {
+        return;
+    }
+
+    let confirmed_frame = confirmed_frame(session.as_deref());
+    for (_, event) in queue.take_confirmed(confirmed_frame) {
+        confirmed_events.send(Confirmed(event));
+    }
+}
+
+pub fn reset_confirmed_event_queue<T: Event>(mut queue: ResMut<ConfirmedEventQueue<T>>) {
+    *queue = ConfirmedEventQueue::default();
+}
+
+fn reset_frame_count(mut frame: ResMut<FrameCount>) {
+    frame.0 = 0;
+}
+
+/// Plugin passing the events of type `T` on as [`Confirmed`] events, once their frames are confirmed.
+/// The rollback system [`queue_confirmed_events`] must be scheduled separately.
+pub struct ConfirmedEventsPlugin<T>(PhantomData<T>);
+
+impl<T> Default for ConfirmedEventsPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Event> Plugin for ConfirmedEventsPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Confirmed<T>>()
+            .init_resource::<ConfirmedEventQueue<T>>()
+            .add_system(reset_confirmed_event_queue::<T>.in_schedule(OnEnter(GameState::InGame)))
+            .add_system(send_confirmed_events::<T>.run_if(in_state(GameState::InGame)));
+    }
+}
+
+/// Plugin counting the simulated frames. The rollback system [`count_frame`] must be scheduled separately.
+pub struct FrameCountPlugin;
+impl Plugin for FrameCountPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameCount>()
+            .add_system(reset_frame_count.in_schedule(OnEnter(GameState::InGame)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_events_back_until_their_frames_are_confirmed() {
+        let mut queue = ConfirmedEventQueue::default();
+        queue.push_frame(1, ["a"]);
+        queue.push_frame(2, ["b", "c"]);
+        queue.push_frame(3, []);
+        queue.push_frame(4, ["d"]);
+
+        assert_eq!(queue.take_confirmed(0), vec![]);
+        assert_eq!(queue.take_confirmed(2), vec![(1, "a"), (2, "b"), (2, "c")]);
+        assert_eq!(queue.take_confirmed(2), vec![]);
+        assert!(!queue.is_empty());
+        assert_eq!(queue.take_confirmed(i32::MAX), vec![(4, "d")]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn throws_away_predictions_of_rolled_back_frames() {
+        let mut queue = ConfirmedEventQueue::default();
+        queue.push_frame(1, ["a"]);
+        queue.push_frame(2, ["mispredicted"]);
+        queue.push_frame(3, ["mispredicted too"]);
+
+        // rolled back to frame 2, re-simulating it and the frames after
+        queue.push_frame(2, ["b"]);
+        queue.push_frame(3, []);
+        queue.push_frame(4, ["c"]);
+
+        assert_eq!(
+            queue.take_confirmed(i32::MAX),
+            vec![(1, "a"), (2, "b"), (4, "c")]
+        );
+    }
+
+    #[test]
+    fn keeps_confirmed_frames_through_later_rollbacks() {
+        let mut queue = ConfirmedEventQueue::default();
+        queue.push_frame(1, ["a"]);
+        queue.push_frame(2, ["b"]);
+        assert_eq!(queue.take_confirmed(1), vec![(1, "a")]);
+
+        queue.push_frame(2, ["b again"]);
+        assert_eq!(queue.take_confirmed(2), vec![(2, "b again")]);
+    }
+}
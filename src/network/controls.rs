@@ -1,6 +1,7 @@
 use crate::controls::CharacterActionInput;
 use bevy::core::{Pod, Zeroable};
 use bevy::reflect::{FromReflect, Reflect};
+use std::f32::consts::TAU;
 
 /// Players' input data structure, used and encoded by GGRS and exchanged over the internet.
 #[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable, Reflect, FromReflect)]
@@ -11,6 +12,8 @@ pub struct GGRSInput {
     // bytemuck::Pod does not accept "padding"/uninit bytes,
     // therefore fields must make up a multiple of the byte size of the biggest field
     pub bit_flags: u32,
+    /// Aim angle in fractions of a full turn, see [`GGRSInput::AIM_RESOLUTION`].
+    pub aim: u32,
 }
 
 impl GGRSInput {
@@ -18,6 +21,22 @@ impl GGRSInput {
     pub const RELOAD: u32 = 1 << 1;
     pub const INTERACT_1: u32 = 1 << 2;
     pub const INTERACT_2: u32 = 1 << 3;
+    pub const TWIN_STICK: u32 = 1 << 4;
+    pub const AIM: u32 = 1 << 5;
+    pub const ABILITY: u32 = 1 << 6;
+
+    /// Number of steps a full turn of the aim is divided into.
+    /// Aim is sent in whole steps, so that every peer decodes the very same angle.
+    pub const AIM_RESOLUTION: u32 = 1 << 16;
+
+    fn encode_aim(angle: f32) -> u32 {
+        (angle.rem_euclid(TAU) / TAU * Self::AIM_RESOLUTION as f32).round() as u32
+            % Self::AIM_RESOLUTION
+    }
+
+    fn decode_aim(aim: u32) -> f32 {
+        (aim % Self::AIM_RESOLUTION) as f32 / Self::AIM_RESOLUTION as f32 * TAU
+    }
 }
 
 impl Into<GGRSInput> for CharacterActionInput {
@@ -39,6 +58,16 @@ impl Into<GGRSInput> for CharacterActionInput {
         if self.interact_2 {
             input.bit_flags |= GGRSInput::INTERACT_2;
         }
+        if self.ability {
+            input.bit_flags |= GGRSInput::ABILITY;
+        }
+        if self.twin_stick {
+            input.bit_flags |= GGRSInput::TWIN_STICK;
+        }
+        if let Some(aim) = self.aim {
+            input.bit_flags |= GGRSInput::AIM;
+            input.aim = GGRSInput::encode_aim(aim);
+        }
 
         input
     }
@@ -53,6 +82,98 @@ impl From<GGRSInput> for CharacterActionInput {
             reload: value.bit_flags & GGRSInput::RELOAD != 0,
             interact_1: value.bit_flags & GGRSInput::INTERACT_1 != 0,
             interact_2: value.bit_flags & GGRSInput::INTERACT_2 != 0,
+            ability: value.bit_flags & GGRSInput::ABILITY != 0,
+            twin_stick: value.bit_flags & GGRSInput::TWIN_STICK != 0,
+            aim: (value.bit_flags & GGRSInput::AIM != 0).then(|| GGRSInput::decode_aim(value.aim)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Angle of a single step of the encoded aim.
+    const AIM_STEP: f32 = TAU / GGRSInput::AIM_RESOLUTION as f32;
+
+    /// Difference between two angles, going the shorter way around.
+    fn angle_between(a: f32, b: f32) -> f32 {
+        let difference = (a - b).rem_euclid(TAU);
+        difference.min(TAU - difference)
+    }
+
+    #[test]
+    fn decodes_aim_within_half_a_step() {
+        for angle in [
+            0.0,
+            0.1,
+            1.0,
+            PI / 2.0,
+            PI,
+            3.0,
+            TAU - 0.01,
+            -0.5,
+            -PI,
+            7.0,
+            -20.0,
+        ] {
+            let decoded = GGRSInput::decode_aim(GGRSInput::encode_aim(angle));
+            assert!(
+                angle_between(angle, decoded) <= AIM_STEP / 2.0 + f32::EPSILON,
+                "{} came back as {}",
+                angle,
+                decoded
+            );
+            assert!((0.0..TAU).contains(&decoded));
+        }
+    }
+
+    #[test]
+    fn encodes_decoded_aim_to_the_same_step() {
+        for aim in [
+            0,
+            1,
+            2,
+            12345,
+            GGRSInput::AIM_RESOLUTION / 2,
+            GGRSInput::AIM_RESOLUTION - 1,
+        ] {
+            assert_eq!(GGRSInput::encode_aim(GGRSInput::decode_aim(aim)), aim);
         }
     }
+
+    #[test]
+    fn wraps_aim_around_a_full_turn() {
+        assert_eq!(GGRSInput::encode_aim(TAU), 0);
+        assert_eq!(GGRSInput::encode_aim(TAU - AIM_STEP / 4.0), 0);
+        assert_eq!(GGRSInput::encode_aim(-AIM_STEP / 4.0), 0);
+        assert_eq!(
+            GGRSInput::encode_aim(-AIM_STEP),
+            GGRSInput::AIM_RESOLUTION - 1
+        );
+        assert_eq!(
+            GGRSInput::decode_aim(GGRSInput::AIM_RESOLUTION + 1),
+            GGRSInput::decode_aim(1)
+        );
+        assert_eq!(
+            GGRSInput::decode_aim(u32::MAX),
+            GGRSInput::decode_aim(u32::MAX % GGRSInput::AIM_RESOLUTION)
+        );
+    }
+
+    #[test]
+    fn keeps_aim_through_the_input() {
+        let action_input = CharacterActionInput {
+            twin_stick: true,
+            aim: Some(GGRSInput::decode_aim(4321)),
+            ..Default::default()
+        };
+        let input: GGRSInput = action_input.into();
+        assert_eq!(CharacterActionInput::from(input), action_input);
+
+        let input: GGRSInput = CharacterActionInput::default().into();
+        assert_eq!(input.bit_flags & GGRSInput::AIM, 0);
+        assert_eq!(CharacterActionInput::from(input).aim, None);
+    }
 }
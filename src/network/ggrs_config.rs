@@ -1,4 +1,5 @@
 use crate::network::controls::GGRSInput;
+use bevy::prelude::Resource;
 use bevy_ggrs::ggrs::{Config, DesyncDetection, SessionBuilder};
 
 pub use bevy_ggrs::ggrs::PlayerHandle;
@@ -18,19 +19,59 @@ impl Config for GGRSConfig {
 }
 pub const MAINTAINED_FPS: usize = 60;
 pub const MAINTAINED_FPS_F64: f64 = MAINTAINED_FPS as f64;
-pub const MAX_PREDICTION_FRAMES: usize = 5;
-pub const INPUT_DELAY: usize = 2;
+pub const DEFAULT_MAX_PREDICTION_FRAMES: usize = 5;
+pub const DEFAULT_INPUT_DELAY: usize = 2;
+/// Input delay beyond this feels worse than the rollbacks it would save.
+pub const MAX_INPUT_DELAY: usize = 6;
+
+/// Network parameters of a single session.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkSettings {
+    /// Frames the local inputs are held back for, so that they have the time to reach the peers.
+    pub input_delay: usize,
+    /// Frames the session may run ahead of the confirmed inputs, predicting the rest.
+    pub max_prediction_frames: usize,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            input_delay: DEFAULT_INPUT_DELAY,
+            max_prediction_frames: DEFAULT_MAX_PREDICTION_FRAMES,
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Pick the input delay to cover the trip of the inputs to the farthest peer, given the round trip to them.
+    pub fn from_round_trip(round_trip_ms: u32) -> Self {
+        let one_way_secs = round_trip_ms as f64 / 2000.;
+        let frames_in_flight = (one_way_secs * MAINTAINED_FPS_F64).ceil() as usize;
+        Self {
+            input_delay: frames_in_flight.clamp(1, MAX_INPUT_DELAY),
+            ..Self::default()
+        }
+    }
+
+    /// Use a fixed input delay, as set by the player.
+    pub fn with_input_delay(self, input_delay: usize) -> Self {
+        Self {
+            input_delay: input_delay.min(MAX_INPUT_DELAY),
+            ..self
+        }
+    }
+}
 
 impl GGRSConfig {
-    pub fn new_builder() -> SessionBuilder<Self> {
+    pub fn new_builder(network_settings: &NetworkSettings) -> SessionBuilder<Self> {
         SessionBuilder::<Self>::new()
             .with_fps(MAINTAINED_FPS)
             .expect("Invalid FPS")
-            .with_max_prediction_window(MAX_PREDICTION_FRAMES)
+            .with_max_prediction_window(network_settings.max_prediction_frames)
             // just in case *shrug*
             .with_desync_detection_mode(DesyncDetection::On {
                 interval: MAINTAINED_FPS as u32,
             })
-            .with_input_delay(INPUT_DELAY)
+            .with_input_delay(network_settings.input_delay)
     }
 }
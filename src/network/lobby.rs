@@ -0,0 +1,439 @@
+//! The pre-match lobby, where the peers pick their teams, the host picks the match settings,
+//! and everyone readies up before the host starts the match. Everything is synced over the reliable channel.
+
+use crate::classes::CharacterClass;
+use crate::network::peers::{KickedPeers, PeerConnectionEvent, PeerMessage};
+use crate::network::session::PlayerCount;
+use crate::network::socket::SpaceballSocket;
+use crate::network::PeerId;
+use crate::teams::TeamNumber;
+use crate::ui::user_settings::UserSettings;
+use crate::{GameState, SceneSelector, PLAYER_DEFAULT_TEAM};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_matchbox::prelude::PeerState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Number of teams to pick from when every player is on their own.
+const FREE_FOR_ALL_TEAM_COUNT: TeamNumber = 8;
+/// Number of teams to pick from when the players team up.
+const TEAMS_TEAM_COUNT: TeamNumber = 2;
+
+const DEFAULT_MATCH_DURATION_MINUTES: u32 = 1;
+const MAX_MATCH_DURATION_MINUTES: u32 = 15;
+
+/// How often the peers' latency is measured.
+const PING_INTERVAL_SECS: f32 = 1.0;
+
+/// The way the players are set against each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Everyone for themselves, each on a team of their own.
+    #[default]
+    FreeForAll,
+    /// Two teams, with any number of players on each.
+    Teams,
+}
+
+impl GameMode {
+    /// Human-readable name of the mode.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            GameMode::FreeForAll => "Free-for-all",
+            GameMode::Teams => "Teams",
+        }
+    }
+
+    /// Get the mode to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            GameMode::FreeForAll => GameMode::Teams,
+            GameMode::Teams => GameMode::FreeForAll,
+        }
+    }
+
+    /// Number of teams the players can pick from, starting at [`PLAYER_DEFAULT_TEAM`].
+    pub const fn team_count(&self) -> TeamNumber {
+        match self {
+            GameMode::FreeForAll => FREE_FOR_ALL_TEAM_COUNT,
+            GameMode::Teams => TEAMS_TEAM_COUNT,
+        }
+    }
+
+    /// Whether no two players may be on the same team.
+    pub const fn has_unique_teams(&self) -> bool {
+        matches!(self, GameMode::FreeForAll)
+    }
+
+    /// Get the team to switch to from this one, wrapping around the teams of this mode.
+    pub fn next_team(&self, team: TeamNumber) -> TeamNumber {
+        let index = team.saturating_sub(PLAYER_DEFAULT_TEAM);
+        PLAYER_DEFAULT_TEAM + (index + 1) % self.team_count()
+    }
+
+    /// Whether a player can be on the team in this mode.
+    pub fn is_team_selectable(&self, team: TeamNumber) -> bool {
+        (PLAYER_DEFAULT_TEAM..PLAYER_DEFAULT_TEAM + self.team_count()).contains(&team)
+    }
+}
+
+/// Settings of the match as picked by the host, for everyone to agree on before it starts.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSettings {
+    pub scene: SceneSelector,
+    pub mode: GameMode,
+    pub duration_minutes: u32,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            scene: SceneSelector::Main,
+            mode: GameMode::default(),
+            duration_minutes: DEFAULT_MATCH_DURATION_MINUTES,
+        }
+    }
+}
+
+impl MatchSettings {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_minutes as u64 * 60)
+    }
+
+    /// Sets the duration of the match, kept within reason.
+    pub fn set_duration_minutes(&mut self, minutes: u32) {
+        self.duration_minutes = minutes.clamp(1, MAX_MATCH_DURATION_MINUTES);
+    }
+}
+
+/// What a peer has told about themselves in the lobby.
+#[derive(Debug, Clone, Default)]
+pub struct LobbyPeer {
+    pub ready: bool,
+    /// Teams of the peer's players, in their order.
+    pub teams: Vec<TeamNumber>,
+    /// Classes of the peer's players, in their order.
+    pub classes: Vec<CharacterClass>,
+    /// Match settings the peer has last seen, so that nobody starts a match they haven't agreed to.
+    pub settings: Option<MatchSettings>,
+    /// Latency of the reliable channel to the peer, there and back, if measured yet.
+    pub ping_ms: Option<u32>,
+}
+
+/// Resource with the lobby states of the other peers.
+#[derive(Resource, Debug, Default)]
+pub struct LobbyPeers {
+    pub map: HashMap<PeerId, LobbyPeer>,
+}
+
+/// Resource with the lobby state of the players on this machine.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct LocalLobbyStatus {
+    pub ready: bool,
+    /// Teams of the local players, in their order.
+    pub teams: Vec<TeamNumber>,
+    /// Classes of the local players, in their order.
+    pub classes: Vec<CharacterClass>,
+}
+
+/// Resource with the settings the host has started the match with, once they have.
+/// The session is only built after, so that every peer starts the same match no matter what their lobby shows.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct MatchStart(pub Option<MatchSettings>);
+
+/// Lobby message received from a peer, passed on from the reliable channel.
+#[derive(Debug, Clone)]
+pub struct LobbyMessageEvent {
+    pub sender: PeerId,
+    pub message: PeerMessage,
+}
+
+/// Get the id of the peer whose word goes on the match settings -- the one with the least id, us included.
+pub fn host_id(socket: &SpaceballSocket) -> Option<PeerId> {
+    let socket = socket.inner();
+    let our_id = socket.id()?;
+    socket
+        .connected_peers()
+        .chain(std::iter::once(our_id))
+        .min()
+}
+
+/// Whether this peer is the one picking the match settings.
+pub fn is_host(socket: &SpaceballSocket) -> bool {
+    let our_id = socket.inner().id();
+    our_id.is_some() && host_id(socket) == our_id
+}
+
+/// Whether every peer, us included, is ready for the same match, so that it can be started.
+pub fn is_everyone_ready(
+    socket: &SpaceballSocket,
+    local_status: &LocalLobbyStatus,
+    lobby_peers: &LobbyPeers,
+    match_settings: &MatchSettings,
+) -> bool {
+    local_status.ready
+        && socket.inner().connected_peers().all(|peer| {
+            lobby_peers.map.get(&peer).is_some_and(|lobby_peer| {
+                lobby_peer.ready && lobby_peer.settings.as_ref() == Some(match_settings)
+            })
+        })
+}
+
+/// Get the first team no one else has claimed, if the mode wants the teams to be unique, or the least populated one otherwise.
+fn free_team(mode: GameMode, taken: &[TeamNumber]) -> TeamNumber {
+    (PLAYER_DEFAULT_TEAM..PLAYER_DEFAULT_TEAM + mode.team_count())
+        .min_by_key(|team| taken.iter().filter(|taken| *taken == team).count())
+        .unwrap_or(PLAYER_DEFAULT_TEAM)
+}
+
+/// System to open the lobby anew, with the scene picked before joining as the suggestion for the match.
+fn setup_lobby(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    scene: Option<Res<SceneSelector>>,
+) {
+    let match_settings = MatchSettings {
+        scene: scene.map_or(SceneSelector::Main, |scene| *scene),
+        ..default()
+    };
+    let mut teams = vec![];
+    for _ in 0..settings.local_player_count() {
+        teams.push(free_team(match_settings.mode, &teams));
+    }
+    let classes = vec![settings.character_class; teams.len()];
+
+    commands.insert_resource(match_settings);
+    commands.insert_resource(LocalLobbyStatus {
+        ready: false,
+        teams,
+        classes,
+    });
+    commands.insert_resource(LobbyPeers::default());
+    commands.insert_resource(MatchStart::default());
+}
+
+/// System to tell the peers about the local players whenever it changes, or someone new arrives.
+fn handle_lobby_status_broadcast(
+    mut socket: ResMut<SpaceballSocket>,
+    local_status: Res<LocalLobbyStatus>,
+    match_settings: Res<MatchSettings>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    let peer_connected = peer_events
+        .iter()
+        .any(|event| event.state == PeerState::Connected);
+    if !(peer_connected || local_status.is_changed() || match_settings.is_changed()) {
+        return;
+    }
+
+    socket.broadcast_tcp_message(PeerMessage::LobbyStatus {
+        ready: local_status.ready,
+        teams: local_status.teams.clone(),
+        classes: local_status.classes.clone(),
+        settings: *match_settings,
+    });
+}
+
+/// System to have the host tell everyone the match settings whenever they change, or someone new arrives.
+fn handle_match_settings_broadcast(
+    mut socket: ResMut<SpaceballSocket>,
+    match_settings: Res<MatchSettings>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    let peer_connected = peer_events
+        .iter()
+        .any(|event| event.state == PeerState::Connected);
+    if !(peer_connected || match_settings.is_changed()) || !is_host(&socket) {
+        return;
+    }
+
+    socket.broadcast_tcp_message(PeerMessage::MatchSettings {
+        settings: *match_settings,
+    });
+}
+
+/// System to have the host start the match once everyone is in and ready for it,
+/// telling everyone the settings to start it with.
+fn handle_match_start(
+    mut socket: ResMut<SpaceballSocket>,
+    player_count: Res<PlayerCount>,
+    local_status: Res<LocalLobbyStatus>,
+    lobby_peers: Res<LobbyPeers>,
+    match_settings: Res<MatchSettings>,
+    mut match_start: ResMut<MatchStart>,
+) {
+    if match_start.0.is_some()
+        || !is_host(&socket)
+        || socket.peer_count() != player_count.0
+        || !is_everyone_ready(&socket, &local_status, &lobby_peers, &match_settings)
+    {
+        return;
+    }
+
+    socket.broadcast_tcp_message(PeerMessage::StartMatch {
+        settings: *match_settings,
+    });
+    match_start.0 = Some(*match_settings);
+}
+
+/// System to measure the latency to every peer every so often.
+fn handle_lobby_pings(
+    mut socket: ResMut<SpaceballSocket>,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+) {
+    let timer =
+        timer.get_or_insert_with(|| Timer::from_seconds(PING_INTERVAL_SECS, TimerMode::Repeating));
+    if timer.tick(time.delta()).just_finished() {
+        socket.broadcast_tcp_message(PeerMessage::Ping {
+            sent_at_secs: time.raw_elapsed_seconds_f64(),
+        });
+    }
+}
+
+/// System to take in what the peers tell about themselves and the match.
+fn handle_receiving_lobby_messages(
+    mut socket: ResMut<SpaceballSocket>,
+    time: Res<Time>,
+    mut lobby_messages: EventReader<LobbyMessageEvent>,
+    mut lobby_peers: ResMut<LobbyPeers>,
+    mut local_status: ResMut<LocalLobbyStatus>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut match_start: ResMut<MatchStart>,
+) {
+    for LobbyMessageEvent { sender, message } in lobby_messages.iter() {
+        match message {
+            PeerMessage::LobbyStatus {
+                ready,
+                teams,
+                classes,
+                settings,
+            } => {
+                let lobby_peer = lobby_peers.map.entry(*sender).or_default();
+                lobby_peer.ready = *ready;
+                lobby_peer.teams = teams.clone();
+                lobby_peer.classes = classes.clone();
+                lobby_peer.settings = Some(*settings);
+            }
+            PeerMessage::MatchSettings { settings } => {
+                // only the host gets to pick, the rest is likely an echo from before someone else has joined
+                if host_id(&socket) != Some(*sender)
+                    || *settings == *match_settings
+                    || match_start.0.is_some()
+                {
+                    continue;
+                }
+                *match_settings = *settings;
+                // nobody should be ready for a match they haven't seen yet
+                local_status.ready = false;
+            }
+            PeerMessage::StartMatch { settings } => {
+                // only the host gets to start the match, and only once
+                if host_id(&socket) != Some(*sender) || match_start.0.is_some() {
+                    continue;
+                }
+                *match_settings = *settings;
+                match_start.0 = Some(*settings);
+            }
+            PeerMessage::Ping { sent_at_secs } => {
+                socket.send_tcp_message(
+                    *sender,
+                    PeerMessage::Pong {
+                        sent_at_secs: *sent_at_secs,
+                    },
+                );
+            }
+            PeerMessage::Pong { sent_at_secs } => {
+                let round_trip = time.raw_elapsed_seconds_f64() - sent_at_secs;
+                lobby_peers.map.entry(*sender).or_default().ping_ms =
+                    Some((round_trip.max(0.0) * 1000.0) as u32);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System to keep the local players on teams they can be on, giving way to the peers with lesser ids when the teams must be unique.
+fn handle_team_conflicts(
+    socket: Res<SpaceballSocket>,
+    lobby_peers: Res<LobbyPeers>,
+    match_settings: Res<MatchSettings>,
+    mut local_status: ResMut<LocalLobbyStatus>,
+) {
+    if !(lobby_peers.is_changed() || match_settings.is_changed() || local_status.is_changed()) {
+        return;
+    }
+    let Some(our_id) = socket.inner().id() else {
+        return;
+    };
+    let mode = match_settings.mode;
+
+    let mut taken: Vec<TeamNumber> = lobby_peers
+        .map
+        .iter()
+        .filter(|(id, _)| **id < our_id)
+        .flat_map(|(_, lobby_peer)| lobby_peer.teams.iter().copied())
+        .collect();
+    let mut teams = local_status.teams.clone();
+    for team in teams.iter_mut() {
+        let is_conflicting = mode.has_unique_teams() && taken.contains(team);
+        if is_conflicting || !mode.is_team_selectable(*team) {
+            *team = free_team(mode, &taken);
+        }
+        taken.push(*team);
+    }
+
+    if teams != local_status.teams {
+        local_status.teams = teams;
+    }
+}
+
+/// System to forget about the peers who have left.
+fn handle_lobby_peer_disconnecting(
+    mut lobby_peers: ResMut<LobbyPeers>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    for event in peer_events.iter() {
+        if event.state == PeerState::Disconnected {
+            lobby_peers.map.remove(&event.id);
+        }
+    }
+}
+
+/// System to forget what the kicked peers have told about themselves, so that no match is started with them.
+fn handle_lobby_peer_kicking(kicked_peers: Res<KickedPeers>, mut lobby_peers: ResMut<LobbyPeers>) {
+    if !kicked_peers.is_changed() {
+        return;
+    }
+    lobby_peers
+        .map
+        .retain(|id, _| !kicked_peers.set.contains(id));
+}
+
+/// Plugin of the pre-match lobby's networking.
+pub(crate) struct LobbyNetworkPlugin;
+impl Plugin for LobbyNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LobbyMessageEvent>()
+            .init_resource::<MatchSettings>()
+            .init_resource::<LocalLobbyStatus>()
+            .init_resource::<LobbyPeers>()
+            .init_resource::<MatchStart>()
+            .add_system(setup_lobby.in_schedule(OnEnter(GameState::Matchmaking)))
+            .add_systems(
+                (
+                    handle_receiving_lobby_messages,
+                    handle_lobby_peer_kicking,
+                    handle_team_conflicts,
+                    handle_lobby_status_broadcast,
+                    handle_match_settings_broadcast,
+                    handle_match_start,
+                    handle_lobby_pings,
+                    handle_lobby_peer_disconnecting,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Matchmaking)),
+            );
+    }
+}
@@ -1,9 +1,14 @@
+pub mod confirmed;
 pub mod controls;
 pub mod ggrs_config;
+pub mod lobby;
+pub mod moderation;
 pub mod peers;
 pub mod players;
+pub mod rooms;
 pub mod session;
 pub mod socket;
+pub mod stats;
 
 use bevy::app::PluginGroupBuilder;
 pub use bevy_ggrs::{GGRSPlugin, GGRSSchedule};
@@ -13,10 +18,15 @@ pub use players::{PlayerDied, PlayerJoined, PlayerRegistry};
 pub use session::PlayerCount;
 
 use bevy::prelude::PluginGroup;
+use confirmed::FrameCountPlugin;
+use lobby::LobbyNetworkPlugin;
+use moderation::ChatModerationPlugin;
 use peers::OnlinePeerPlugin;
 use players::OnlinePlayerPlugin;
+use rooms::RoomListingPlugin;
 use session::SessionPlugin;
 use socket::SocketPlugin;
+use stats::NetworkStatsPlugin;
 
 // Having a load screen of just one frame helps with desync issues, some report.
 
@@ -26,7 +36,12 @@ impl PluginGroup for MultiplayerPlugins {
         PluginGroupBuilder::start::<Self>()
             .add(SocketPlugin)
             .add(SessionPlugin)
+            .add(FrameCountPlugin)
+            .add(LobbyNetworkPlugin)
             .add(OnlinePeerPlugin)
+            .add(ChatModerationPlugin)
             .add(OnlinePlayerPlugin)
+            .add(RoomListingPlugin)
+            .add(NetworkStatsPlugin)
     }
 }
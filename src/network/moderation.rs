@@ -0,0 +1,241 @@
+//! Receiver-side checks on what the peers say, for nobody to flood, forge or foul up the chat of this machine.
+//! The peers' own input limits are not to be relied on, as nothing stops a peer from sending whatever they like.
+
+use crate::network::peers::PeerConnectionEvent;
+use crate::network::PeerId;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_matchbox::prelude::PeerState;
+
+/// Longest a chat message can be, in characters. Anything longer is cut short.
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 200;
+/// Longest a player's name can be, in characters.
+pub const MAX_PLAYER_NAME_LENGTH: usize = 24;
+
+/// Words the chat filter masks out of the box, one per line, on top of those listed in the settings.
+const BUNDLED_FILTERED_WORDS: &str = include_str!("../../assets/filtered_words.txt");
+
+/// Number of messages a peer can send in a quick succession before being held back.
+const CHAT_BURST_MESSAGES: f64 = 5.0;
+/// Number of messages per second a peer regains the allowance for.
+const CHAT_MESSAGES_PER_SEC: f64 = 1.0;
+
+/// How many more messages a peer may send right now, refilling over time.
+#[derive(Debug, Clone, Copy)]
+struct ChatAllowance {
+    messages: f64,
+    updated_at_secs: f64,
+}
+
+/// Resource keeping the peers from sending more messages than anyone could read.
+#[derive(Resource, Debug, Default)]
+pub struct ChatRateLimiter {
+    allowances: HashMap<PeerId, ChatAllowance>,
+}
+
+impl ChatRateLimiter {
+    /// Whether the peer's message should be let through at this time, spending their allowance if it is.
+    pub fn allow(&mut self, peer: PeerId, now_secs: f64) -> bool {
+        let allowance = self.allowances.entry(peer).or_insert(ChatAllowance {
+            messages: CHAT_BURST_MESSAGES,
+            updated_at_secs: now_secs,
+        });
+        let elapsed_secs = (now_secs - allowance.updated_at_secs).max(0.0);
+        allowance.messages =
+            (allowance.messages + elapsed_secs * CHAT_MESSAGES_PER_SEC).min(CHAT_BURST_MESSAGES);
+        allowance.updated_at_secs = now_secs;
+
+        if allowance.messages >= 1.0 {
+            allowance.messages -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Forget about the peer, as they've left.
+    pub fn forget(&mut self, peer: PeerId) {
+        self.allowances.remove(&peer);
+    }
+}
+
+/// Make a peer's text safe to show: without control characters, without the braces
+/// that [`ChatMessage::separate_handles`](crate::ui::chat::ChatMessage::separate_handles) would take
+/// for player placeholders (such as forging someone else's name with `{0}`), and cut to the length.
+/// Returns `None` if nothing worth showing is left.
+pub fn sanitize_text(text: &str, max_length: usize) -> Option<String> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control() && *c != '{' && *c != '}')
+        .take(max_length)
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Make a peer's color safe to paint with: opaque, and within the range that can be shown.
+pub fn sanitize_color(color: Color) -> Color {
+    let [red, green, blue, _] = color.as_rgba_f32();
+    let channel = |value: f32| {
+        if value.is_finite() {
+            value.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    Color::rgb(channel(red), channel(green), channel(blue))
+}
+
+/// Get the words the chat filter masks out of the box.
+pub fn bundled_filtered_words() -> impl Iterator<Item = &'static str> {
+    BUNDLED_FILTERED_WORDS
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+}
+
+/// Mask the words from the list found in the text, regardless of their case.
+pub fn filter_words(text: &str, filtered_words: &[String]) -> String {
+    if filtered_words.is_empty() {
+        return text.to_string();
+    }
+    text.split(' ')
+        .map(|word| {
+            let bare_word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            let lowercase_word = bare_word.to_lowercase();
+            let is_filtered = !bare_word.is_empty()
+                && filtered_words
+                    .iter()
+                    .any(|filtered| filtered.to_lowercase() == lowercase_word);
+            if is_filtered {
+                word.replace(bare_word, &"*".repeat(bare_word.chars().count()))
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// System to forget the allowances of the peers who have left.
+fn handle_rate_limited_peer_disconnecting(
+    mut rate_limiter: ResMut<ChatRateLimiter>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    for event in peer_events.iter() {
+        if event.state == PeerState::Disconnected {
+            rate_limiter.forget(event.id);
+        }
+    }
+}
+
+fn reset_chat_rate_limiter(mut rate_limiter: ResMut<ChatRateLimiter>) {
+    rate_limiter.allowances.clear();
+}
+
+/// Plugin of the checks on the peers' messages.
+pub(crate) struct ChatModerationPlugin;
+impl Plugin for ChatModerationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatRateLimiter>()
+            .add_system(
+                handle_rate_limited_peer_disconnecting.run_if(not(in_state(GameState::MainMenu))),
+            )
+            .add_system(reset_chat_rate_limiter.in_schedule(OnEnter(GameState::MainMenu)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::Uuid;
+
+    #[test]
+    fn strips_player_placeholders() {
+        assert_eq!(
+            sanitize_text("{0} has left the game", MAX_CHAT_MESSAGE_LENGTH),
+            Some("0 has left the game".into())
+        );
+        assert_eq!(
+            sanitize_text("}{{1}}{", MAX_CHAT_MESSAGE_LENGTH),
+            Some("1".into())
+        );
+    }
+
+    #[test]
+    fn strips_control_characters_and_empty_text() {
+        assert_eq!(
+            sanitize_text("line\nbreak\u{7}", MAX_CHAT_MESSAGE_LENGTH),
+            Some("linebreak".into())
+        );
+        assert_eq!(sanitize_text("  {}\t ", MAX_CHAT_MESSAGE_LENGTH), None);
+        assert_eq!(sanitize_text("", MAX_CHAT_MESSAGE_LENGTH), None);
+    }
+
+    #[test]
+    fn truncates_multi_byte_text_by_characters() {
+        assert_eq!(sanitize_text("héllo wörld", 4), Some("héll".into()));
+        assert_eq!(sanitize_text("🚀🚀🚀", 2), Some("🚀🚀".into()));
+        assert_eq!(
+            sanitize_text("Ünïcödé", MAX_PLAYER_NAME_LENGTH),
+            Some("Ünïcödé".into())
+        );
+    }
+
+    #[test]
+    fn filters_whole_words_regardless_of_case() {
+        let filtered_words = vec!["schwartz".to_string(), "bärf".to_string()];
+        assert_eq!(
+            filter_words("May the Schwartz be with you!", &filtered_words),
+            "May the ******** be with you!"
+        );
+        assert_eq!(
+            filter_words("schwartz, bärf.", &filtered_words),
+            "********, ****."
+        );
+        assert_eq!(filter_words("BÄRF!", &filtered_words), "****!");
+        assert_eq!(
+            filter_words("schwartzenegger", &filtered_words),
+            "schwartzenegger"
+        );
+        assert_eq!(filter_words("Schwartz", &[]), "Schwartz");
+    }
+
+    #[test]
+    fn limits_bursts_and_refills_over_time() {
+        let peer = PeerId(Uuid::from_u128(1));
+        let mut rate_limiter = ChatRateLimiter::default();
+
+        for _ in 0..CHAT_BURST_MESSAGES as usize {
+            assert!(rate_limiter.allow(peer, 10.0));
+        }
+        assert!(!rate_limiter.allow(peer, 10.0));
+        assert!(!rate_limiter.allow(peer, 10.5));
+
+        // the half a second spent being held back counts towards the next message
+        assert!(rate_limiter.allow(peer, 11.0));
+        assert!(!rate_limiter.allow(peer, 11.0));
+
+        // the allowance never refills past the burst
+        for _ in 0..CHAT_BURST_MESSAGES as usize {
+            assert!(rate_limiter.allow(peer, 100.0));
+        }
+        assert!(!rate_limiter.allow(peer, 100.0));
+    }
+
+    #[test]
+    fn limits_peers_separately() {
+        let (peer, other_peer) = (PeerId(Uuid::from_u128(1)), PeerId(Uuid::from_u128(2)));
+        let mut rate_limiter = ChatRateLimiter::default();
+
+        for _ in 0..CHAT_BURST_MESSAGES as usize {
+            assert!(rate_limiter.allow(peer, 0.0));
+        }
+        assert!(!rate_limiter.allow(peer, 0.0));
+        assert!(rate_limiter.allow(other_peer, 0.0));
+
+        rate_limiter.forget(peer);
+        assert!(rate_limiter.allow(peer, 0.0));
+    }
+}
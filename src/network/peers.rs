@@ -1,13 +1,23 @@
 //! Peers are simply clients when they are not handled as players.
 //! While players are assigned GGRS' `PlayerHandle`, peers are assigned `PeerId`.
 
+use crate::classes::CharacterClass;
+use crate::controls::devices::MAX_LOCAL_PLAYERS;
+use crate::network::lobby::{host_id, LobbyMessageEvent, MatchSettings};
+use crate::network::moderation::{
+    bundled_filtered_words, filter_words, sanitize_color, sanitize_text, ChatRateLimiter,
+    MAX_CHAT_MESSAGE_LENGTH, MAX_PLAYER_NAME_LENGTH,
+};
+use crate::network::session::{interrupt_session, SessionInterruption};
 use crate::network::socket::SpaceballSocket;
 use crate::network::{PeerId, PlayerHandle};
-use crate::ui::chat::ChatMessage;
+use crate::teams::TeamNumber;
+use crate::ui::chat::{ChatChannel, ChatMessage};
+use crate::ui::profile::Profile;
 use crate::ui::user_settings::UserSettings;
 use crate::GameState;
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy_matchbox::prelude::PeerState;
 use serde::{Deserialize, Serialize};
 
@@ -19,9 +29,85 @@ pub struct PeerConnectionEvent {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum PeerMessage {
-    PlayerName { name: String },
-    Chat { message: String },
-    // todo ChatHistory { ... },
+    PlayerName {
+        name: String,
+    },
+    LocalPlayerCount {
+        count: usize,
+    },
+    /// The color the sender's player would like to be shown in, if any.
+    PlayerColor {
+        color: Option<Color>,
+    },
+    Chat {
+        message: String,
+        channel: ChatChannel,
+        /// Seconds since the Unix epoch of when the message was sent, by the sender's clock, kept for the history.
+        timestamp: f64,
+    },
+    /// The recent messages of the public chat, for a newly joined peer to catch up on.
+    ChatHistory {
+        records: Vec<ChatRecord>,
+    },
+    /// The host's word for the receiver to leave the session.
+    Kick,
+    /// The sender is up for another match.
+    Rematch,
+    /// The sender's readiness and their players' teams, along with the match settings they've seen.
+    LobbyStatus {
+        ready: bool,
+        teams: Vec<TeamNumber>,
+        classes: Vec<CharacterClass>,
+        settings: MatchSettings,
+    },
+    /// The match settings, as picked by the host.
+    MatchSettings {
+        settings: MatchSettings,
+    },
+    /// The host's word for everyone to start the match, with the settings it's played with.
+    StartMatch {
+        settings: MatchSettings,
+    },
+    Ping {
+        sent_at_secs: f64,
+    },
+    Pong {
+        sent_at_secs: f64,
+    },
+}
+
+/// Peer message to be sent only to some of the peers, rather than broadcast to all.
+#[derive(Debug, Clone)]
+pub struct DirectPeerMessage {
+    pub recipients: Vec<PeerId>,
+    pub message: PeerMessage,
+}
+
+/// A message from the public chat, as it is kept in the history.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChatRecord {
+    pub author: String,
+    pub message: String,
+    pub timestamp: f64,
+}
+
+/// Resource with the latest messages of the public chat, to be sent over to whoever joins later.
+#[derive(Resource, Debug, Default)]
+pub struct ChatHistory {
+    pub records: Vec<ChatRecord>,
+}
+
+impl ChatHistory {
+    /// Number of messages kept in the history, the older ones being forgotten.
+    pub const MAX_RECORDS: usize = 32;
+
+    pub fn push(&mut self, record: ChatRecord) {
+        self.records.push(record);
+        if self.records.len() > Self::MAX_RECORDS {
+            let excess = self.records.len() - Self::MAX_RECORDS;
+            self.records.drain(..excess);
+        }
+    }
 }
 
 #[derive(Resource, Debug, Default)]
@@ -34,9 +120,47 @@ pub struct PeerNames {
     pub map: HashMap<PeerId, String>,
 }
 
+/// Colors the peers' players would like to be shown in, for those who have picked one.
+#[derive(Resource, Debug, Default)]
+pub struct PeerColors {
+    pub map: HashMap<PeerId, Color>,
+}
+
+/// Number of players each peer brings to the session from their own machine.
+#[derive(Resource, Debug, Default)]
+pub struct PeerLocalPlayers {
+    pub map: HashMap<PeerId, usize>,
+}
+
+/// Peers the host has kicked. Their players are dropped from the session and whatever they still send is ignored,
+/// should they not leave on their own.
+#[derive(Resource, Debug, Default)]
+pub struct KickedPeers {
+    pub set: HashSet<PeerId>,
+}
+
+/// Peers whose chat messages are not to be shown on this machine.
+#[derive(Resource, Debug, Default)]
+pub struct MutedPeers {
+    pub set: HashSet<PeerId>,
+}
+
+impl MutedPeers {
+    /// Mute the peer if they are not muted, unmute them otherwise. Returns whether they are muted now.
+    pub fn toggle(&mut self, id: PeerId) -> bool {
+        if self.set.remove(&id) {
+            false
+        } else {
+            self.set.insert(id);
+            true
+        }
+    }
+}
+
 pub fn handle_player_name_broadcast(
     mut socket: ResMut<SpaceballSocket>,
     settings: Res<UserSettings>,
+    profile: Res<Profile>,
     mut peer_events: EventReader<PeerConnectionEvent>,
 ) {
     if peer_events.iter().any(|event| {
@@ -52,6 +176,66 @@ pub fn handle_player_name_broadcast(
         socket.broadcast_tcp_message(PeerMessage::PlayerName {
             name: settings.player_name.clone(),
         });
+        // ...and how many of us are sitting at this machine, for everyone to agree on the players in the session
+        socket.broadcast_tcp_message(PeerMessage::LocalPlayerCount {
+            count: settings.local_player_count(),
+        });
+        socket.broadcast_tcp_message(PeerMessage::PlayerColor {
+            color: profile.color,
+        });
+    }
+}
+
+/// System to tell the peers about the color we'd like to be shown in whenever it changes.
+pub fn handle_player_color_broadcast(
+    mut socket: ResMut<SpaceballSocket>,
+    profile: Res<Profile>,
+    mut last_sent_color: Local<Option<Color>>,
+) {
+    if !profile.is_changed() || *last_sent_color == profile.color {
+        return;
+    }
+    *last_sent_color = profile.color;
+    socket.broadcast_tcp_message(PeerMessage::PlayerColor {
+        color: profile.color,
+    });
+}
+
+/// System to have one of the peers who were here before catch the newly joined ones up on the public chat.
+pub fn handle_chat_history_sync(
+    mut socket: ResMut<SpaceballSocket>,
+    chat_history: Res<ChatHistory>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    let new_peers: Vec<PeerId> = peer_events
+        .iter()
+        .filter(|event| event.state == PeerState::Connected)
+        .map(|event| event.id)
+        .collect();
+    if new_peers.is_empty() || chat_history.records.is_empty() {
+        return;
+    }
+
+    // only one of the peers who were here before should send the history, lest it be repeated
+    let Some(our_id) = socket.inner().id() else {
+        return;
+    };
+    let is_oldest_peer = socket
+        .inner()
+        .connected_peers()
+        .filter(|id| !new_peers.contains(id))
+        .all(|id| our_id < id);
+    if !is_oldest_peer {
+        return;
+    }
+
+    for peer in new_peers {
+        socket.send_tcp_message(
+            peer,
+            PeerMessage::ChatHistory {
+                records: chat_history.records.clone(),
+            },
+        );
     }
 }
 
@@ -64,36 +248,173 @@ pub fn handle_chat_message_broadcast(
     }
 }
 
+pub fn handle_direct_message_sending(
+    mut socket: ResMut<SpaceballSocket>,
+    mut messenger: EventReader<DirectPeerMessage>,
+) {
+    for DirectPeerMessage {
+        recipients,
+        message,
+    } in messenger.iter()
+    {
+        for peer in recipients {
+            socket.send_tcp_message(*peer, message.clone());
+        }
+    }
+}
+
 pub fn handle_receiving_peer_messages(
     mut socket: ResMut<SpaceballSocket>,
+    time: Res<Time>,
+    settings: Res<UserSettings>,
     mut peer_names: ResMut<PeerNames>,
+    mut peer_local_players: ResMut<PeerLocalPlayers>,
+    mut peer_colors: ResMut<PeerColors>,
     peer_handles: Res<PeerHandles>,
+    muted_peers: Res<MutedPeers>,
+    kicked_peers: Res<KickedPeers>,
+    mut rate_limiter: ResMut<ChatRateLimiter>,
+    mut chat_history: ResMut<ChatHistory>,
     mut messenger: EventWriter<ChatMessage>,
+    mut lobby_messages: EventWriter<LobbyMessageEvent>,
+    mut interruption: ResMut<SessionInterruption>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
     let messages = socket.receive_tcp_messages();
+    if messages.is_empty() {
+        return;
+    }
+    let filtered_words: Vec<String> = if settings.chat_filter {
+        bundled_filtered_words()
+            .map(String::from)
+            .chain(settings.filtered_words.iter().cloned())
+            .collect()
+    } else {
+        vec![]
+    };
     for (sender, message) in messages {
+        // the peer is out, whether or not they have left
+        if kicked_peers.set.contains(&sender) {
+            continue;
+        }
         match message {
             PeerMessage::PlayerName { name } => {
+                let Some(name) = sanitize_text(&name, MAX_PLAYER_NAME_LENGTH) else {
+                    continue;
+                };
                 if !peer_names.map.contains_key(&sender) {
-                    messenger.send(ChatMessage {
-                        player_handles: vec![],
-                        message: format!("{} joined!", name),
-                    });
+                    messenger.send(ChatMessage::message(format!("{} joined!", name)));
                 }
                 peer_names.map.insert(sender, name);
             }
-            PeerMessage::Chat { message } => {
+            PeerMessage::LocalPlayerCount { count } => {
+                peer_local_players
+                    .map
+                    .insert(sender, count.clamp(1, MAX_LOCAL_PLAYERS));
+            }
+            PeerMessage::PlayerColor { color } => match color {
+                Some(color) => {
+                    peer_colors.map.insert(sender, sanitize_color(color));
+                }
+                None => {
+                    peer_colors.map.remove(&sender);
+                }
+            },
+            PeerMessage::Chat {
+                message,
+                channel,
+                timestamp,
+            } => {
+                if muted_peers.set.contains(&sender)
+                    || !rate_limiter.allow(sender, time.raw_elapsed_seconds_f64())
+                {
+                    continue;
+                }
                 // ignore the message if it came from an unregistered source
-                match (peer_handles.map.get(&sender), peer_names.map.get(&sender)) {
-                    (Some(handle), _) => {
-                        messenger.send(ChatMessage::message(message).by_player(*handle));
-                    }
-                    (None, Some(name)) => {
-                        messenger.send(ChatMessage::message(message).by(name.clone()));
-                    }
-                    _ => {}
+                let Some(name) = peer_names.map.get(&sender) else {
+                    continue;
+                };
+                let Some(message) = sanitize_text(&message, MAX_CHAT_MESSAGE_LENGTH) else {
+                    continue;
+                };
+                if channel == ChatChannel::All {
+                    chat_history.push(ChatRecord {
+                        author: name.clone(),
+                        message: message.clone(),
+                        timestamp,
+                    });
+                }
+                // stamped as it comes in, the sender's clock may well be off from ours
+                let chat_message = ChatMessage::message(filter_words(&message, &filtered_words))
+                    .in_channel(channel);
+                match peer_handles.map.get(&sender) {
+                    Some(handle) => messenger.send(chat_message.by_player(*handle)),
+                    None => messenger.send(chat_message.by(name.clone())),
                 }
             }
+            PeerMessage::ChatHistory { records } => {
+                // the history is only of use to those who have just joined
+                if !chat_history.records.is_empty() {
+                    continue;
+                }
+                let skipped = records.len().saturating_sub(ChatHistory::MAX_RECORDS);
+                for record in records.into_iter().skip(skipped) {
+                    let (Some(author), Some(message)) = (
+                        sanitize_text(&record.author, MAX_PLAYER_NAME_LENGTH),
+                        sanitize_text(&record.message, MAX_CHAT_MESSAGE_LENGTH),
+                    ) else {
+                        continue;
+                    };
+                    messenger.send(
+                        ChatMessage::message(filter_words(&message, &filtered_words))
+                            .by(author.clone())
+                            .at(record.timestamp),
+                    );
+                    chat_history.push(ChatRecord {
+                        author,
+                        message,
+                        timestamp: record.timestamp,
+                    });
+                }
+            }
+            PeerMessage::Kick => {
+                // anyone else has no say in who stays
+                if host_id(&socket) != Some(sender) {
+                    continue;
+                }
+                interrupt_session(
+                    &mut interruption,
+                    &mut next_state,
+                    "You have been kicked by the host.".to_string(),
+                );
+            }
+            PeerMessage::Rematch => {
+                if muted_peers.set.contains(&sender)
+                    || !rate_limiter.allow(sender, time.raw_elapsed_seconds_f64())
+                {
+                    continue;
+                }
+                let chat_message =
+                    match (peer_handles.map.get(&sender), peer_names.map.get(&sender)) {
+                        (Some(handle), _) => ChatMessage::message(
+                            "{0} is up for a rematch! Type /rematch to join in.",
+                        )
+                        .with_handles(vec![*handle]),
+                        (None, Some(name)) => ChatMessage::message(format!(
+                            "{} is up for a rematch! Type /rematch to join in.",
+                            name
+                        )),
+                        _ => continue,
+                    };
+                messenger.send(chat_message);
+            }
+            message @ (PeerMessage::LobbyStatus { .. }
+            | PeerMessage::MatchSettings { .. }
+            | PeerMessage::StartMatch { .. }
+            | PeerMessage::Ping { .. }
+            | PeerMessage::Pong { .. }) => {
+                lobby_messages.send(LobbyMessageEvent { sender, message });
+            }
         }
     }
 }
@@ -101,6 +422,8 @@ pub fn handle_receiving_peer_messages(
 // todo actually go through how all this works and should works, high risk of going wtf
 pub fn handle_reporting_peer_disconnecting(
     mut peer_names: ResMut<PeerNames>,
+    mut peer_local_players: ResMut<PeerLocalPlayers>,
+    mut peer_colors: ResMut<PeerColors>,
     mut peer_events: EventReader<PeerConnectionEvent>,
     mut messenger: EventWriter<ChatMessage>,
 ) {
@@ -110,11 +433,10 @@ pub fn handle_reporting_peer_disconnecting(
                 state: PeerState::Disconnected,
                 id,
             } => {
+                peer_local_players.map.remove(id);
+                peer_colors.map.remove(id);
                 if let Some(name) = peer_names.map.remove(id) {
-                    messenger.send(ChatMessage {
-                        player_handles: vec![],
-                        message: format!("{} left!", name),
-                    });
+                    messenger.send(ChatMessage::message(format!("{} left!", name)));
                 }
             }
             _ => {}
@@ -122,9 +444,24 @@ pub fn handle_reporting_peer_disconnecting(
     }
 }
 
-pub fn reset_peer_names(mut peer_names: ResMut<PeerNames>, mut peer_handles: ResMut<PeerHandles>) {
+pub fn reset_peer_names(
+    mut peer_names: ResMut<PeerNames>,
+    mut peer_local_players: ResMut<PeerLocalPlayers>,
+    mut peer_handles: ResMut<PeerHandles>,
+    mut peer_colors: ResMut<PeerColors>,
+    mut muted_peers: ResMut<MutedPeers>,
+    mut kicked_peers: ResMut<KickedPeers>,
+) {
     peer_names.map.clear();
+    peer_local_players.map.clear();
+    peer_colors.map.clear();
     peer_handles.map.clear();
+    muted_peers.set.clear();
+    kicked_peers.set.clear();
+}
+
+pub fn reset_chat_history(mut chat_history: ResMut<ChatHistory>) {
+    chat_history.records.clear();
 }
 
 pub(crate) struct OnlinePeerPlugin;
@@ -132,16 +469,26 @@ impl Plugin for OnlinePeerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<PeerConnectionEvent>()
             .add_event::<PeerMessage>()
+            .add_event::<DirectPeerMessage>()
             .init_resource::<PeerNames>()
+            .init_resource::<PeerLocalPlayers>()
             .init_resource::<PeerHandles>()
+            .init_resource::<PeerColors>()
+            .init_resource::<MutedPeers>()
+            .init_resource::<KickedPeers>()
+            .init_resource::<ChatHistory>()
             // ideally, there should be `or` between `Matchmaking` and `InGame`, but no, ok
             .add_system(handle_player_name_broadcast.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(handle_player_color_broadcast.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(handle_chat_history_sync.run_if(not(in_state(GameState::MainMenu))))
             .add_system(handle_chat_message_broadcast.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(handle_direct_message_sending.run_if(not(in_state(GameState::MainMenu))))
             .add_system(handle_receiving_peer_messages.run_if(not(in_state(GameState::MainMenu))))
             .add_system(
                 handle_reporting_peer_disconnecting.run_if(not(in_state(GameState::MainMenu))),
             )
             .add_system(reset_peer_names.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(reset_chat_history.in_schedule(OnEnter(GameState::MainMenu)))
             .add_system(reset_peer_names.in_schedule(OnExit(GameState::InGame)));
     }
 }
@@ -1,8 +1,17 @@
-use crate::network::peers::{PeerHandles, PeerNames};
+use crate::characters::PlayerControlled;
+use crate::classes::CharacterClass;
+use crate::match_stats::PlayerStats;
+use crate::network::ggrs_config::GGRSConfig;
+use crate::network::lobby::MatchSettings;
+use crate::network::peers::{PeerColors, PeerHandles, PeerNames};
+use crate::network::session::LocalPlayers;
 use crate::network::{PlayerHandle, MAINTAINED_FPS_F64};
 use crate::teams::{Team, TeamNumber, PLAYER_DEFAULT_TEAM};
+use crate::ui::profile::Profile;
 use crate::{GameState, MenuState, PlayerCount};
 use bevy::prelude::*;
+use bevy_ggrs::ggrs::InputStatus;
+use bevy_ggrs::PlayerInputs;
 use std::slice::Iter;
 use std::time::Duration;
 
@@ -10,8 +19,16 @@ use std::time::Duration;
 pub struct PlayerData {
     pub name: String,
     pub team: Team,
+    /// Class the player's character spawns as.
+    pub class: CharacterClass,
     pub kills: usize,
     pub deaths: usize,
+    /// Whether the player has left mid-match, and is not to come back into it.
+    pub disconnected: bool,
+    /// Color the player has asked to be shown in, which they get unless it clashes with someone else's.
+    pub color: Option<Color>,
+    /// Detailed statistics of the match, counted from the confirmed frames.
+    pub stats: PlayerStats,
 }
 
 impl PlayerData {
@@ -105,6 +122,59 @@ pub fn update_player_names(
     }
 }
 
+/// System to fill in the colors the players have asked for, the peers' as they've told and ours from the profile.
+pub fn update_player_colors(
+    peer_colors: Res<PeerColors>,
+    peer_handles: Res<PeerHandles>,
+    local_players: Option<Res<LocalPlayers>>,
+    profile: Res<Profile>,
+    mut players: ResMut<PlayerRegistry>,
+) {
+    if !(peer_colors.is_changed() || profile.is_changed() || players.is_added()) {
+        return;
+    }
+
+    for (id, handle) in &peer_handles.map {
+        if let Some(data) = players.0.get_mut(*handle) {
+            data.color = peer_colors.map.get(id).copied();
+        }
+    }
+    // the guests sharing the machine go with their teams' colors
+    let first_local_handle = local_players.map_or(0, |local_players| {
+        local_players.handles.first().copied().unwrap_or_default()
+    });
+    if let Some(data) = players.0.get_mut(first_local_handle) {
+        data.color = profile.color;
+    }
+}
+
+/// System to take the players who have disconnected out of the match, so that the rest can carry on without them.
+pub fn handle_disconnected_players(
+    mut commands: Commands,
+    inputs: Res<PlayerInputs<GGRSConfig>>,
+    mut players: ResMut<PlayerRegistry>,
+    character_query: Query<(Entity, &PlayerControlled)>,
+) {
+    for (handle, (_, status)) in inputs.iter().enumerate() {
+        if *status != InputStatus::Disconnected {
+            continue;
+        }
+        let Some(player_data) = players.0.get_mut(handle) else {
+            continue;
+        };
+        if player_data.disconnected {
+            continue;
+        }
+        player_data.disconnected = true;
+
+        for (entity, player) in character_query.iter() {
+            if player.handle == handle {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
 pub fn send_new_players_joined(
     players: Res<PlayerRegistry>,
     mut player_teller: EventWriter<PlayerJoined>,
@@ -126,9 +196,16 @@ pub fn send_new_players_joined(
     }
 }
 
-fn reset_match_time_in_multiplayer(mut commands: Commands, player_count: Res<PlayerCount>) {
+fn reset_match_time_in_multiplayer(
+    mut commands: Commands,
+    player_count: Res<PlayerCount>,
+    match_settings: Res<MatchSettings>,
+) {
     if player_count.0 > 1 {
-        commands.init_resource::<MatchTime>();
+        commands.insert_resource(MatchTime(Timer::new(
+            match_settings.duration(),
+            TimerMode::Once,
+        )));
     }
 }
 
@@ -160,6 +237,7 @@ impl Plugin for OnlinePlayerPlugin {
             .add_event::<PlayerJoined>()
             .add_event::<PlayerDied>()
             .add_system(update_player_names.run_if(in_state(GameState::InGame)))
+            .add_system(update_player_colors.run_if(in_state(GameState::InGame)))
             .add_system(reset_match_time_in_multiplayer.in_schedule(OnEnter(GameState::InGame)));
     }
 }
@@ -0,0 +1,436 @@
+//! Public rooms are listed by a room-listing service running alongside the matchbox server,
+//! or by the local stand-in from `examples/room_listing_server.rs`.
+//!
+//! The service answers `GET` at its URL with the open rooms as a JSON array of [`RoomInfo`],
+//! takes `POST` of a [`RoomInfo`] to list or refresh a room, and `DELETE` at `<url>/<room slug>` to unlist one.
+//! Rooms that have not been refreshed for a while are expected to be dropped by the service.
+//! With no listing URL set, rooms can only be joined by their names.
+
+use crate::network::lobby::{GameMode, MatchSettings};
+use crate::network::session::PlayerCount;
+use crate::network::socket::SpaceballSocket;
+use crate::ui::user_settings::UserSettings;
+use crate::{GameState, MenuState, SceneSelector};
+use bevy::log::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// How often a hosted public room reminds the listing that it's still open.
+const HEARTBEAT_INTERVAL_SECS: f32 = 10.0;
+
+/// Public room as seen in the listing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RoomInfo {
+    pub name: String,
+    /// Number of peers already in the room.
+    pub players: usize,
+    /// Number of peers the room waits for before the match starts.
+    pub max_players: usize,
+    pub scene: SceneSelector,
+    pub mode: GameMode,
+}
+
+impl RoomInfo {
+    /// Whether someone else can still join the room.
+    pub fn is_open(&self) -> bool {
+        self.players < self.max_players
+    }
+}
+
+/// Turn a room name into something fit to be a part of a URL. Rooms with the same slug are the same room.
+///
+/// Letters and digits are kept as they are, case aside. Names with anything else in them get a hash
+/// of the whole name appended, so that names differing only in what was replaced stay apart.
+pub fn room_slug(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    let readable: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if readable == name {
+        readable
+    } else {
+        format!("{}-{:016x}", readable, stable_hash(&name))
+    }
+}
+
+/// FNV-1a hash, the same on every peer and build, unlike the standard library's hashers.
+fn stable_hash(text: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    text.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Pick the room a quick-match should join: the open one closest to being full, so that matches start sooner.
+pub fn best_room(rooms: &[RoomInfo]) -> Option<&RoomInfo> {
+    rooms.iter().filter(|room| room.is_open()).min_by(|a, b| {
+        (a.max_players - a.players)
+            .cmp(&(b.max_players - b.players))
+            .then_with(|| a.name.cmp(&b.name))
+    })
+}
+
+/// Response to a request to the room listing, filled in once it arrives.
+#[derive(Debug, Clone, Default)]
+struct PendingResponse(Arc<Mutex<Option<Result<String, String>>>>);
+
+impl PendingResponse {
+    /// Take the response out, if it has arrived.
+    fn take(&self) -> Option<Result<String, String>> {
+        // a poisoned lock means the request has panicked, and there is nothing to take
+        self.0.lock().ok()?.take()
+    }
+}
+
+/// Send a request to the room listing in the background.
+fn send_request(method: &'static str, url: String, body: Option<String>) -> PendingResponse {
+    let response = PendingResponse::default();
+    let slot = response.0.clone();
+    http::spawn_request(method, url, body, move |result| {
+        if let Ok(mut slot) = slot.lock() {
+            *slot = Some(result);
+        }
+    });
+    response
+}
+
+/// State of the latest fetch of the room listing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum RoomListingStatus {
+    #[default]
+    Idle,
+    Fetching,
+    Fetched,
+    Failed(String),
+}
+
+/// Resource with the rooms last fetched from the room listing.
+#[derive(Resource, Debug, Default)]
+pub struct RoomListing {
+    pub rooms: Vec<RoomInfo>,
+    pub status: RoomListingStatus,
+    /// Whether to join the best room, or to host one, as soon as the listing arrives.
+    pub quick_match: bool,
+    pending: Option<PendingResponse>,
+}
+
+impl RoomListing {
+    /// Start fetching the rooms anew. The rooms fetched before are kept until the new ones arrive.
+    pub fn refresh(&mut self, url: &str) {
+        if url.trim().is_empty() {
+            self.status = RoomListingStatus::Failed("no room listing URL is set".to_string());
+            self.quick_match = false;
+            self.pending = None;
+            return;
+        }
+        self.status = RoomListingStatus::Fetching;
+        self.pending = Some(send_request("GET", url.to_string(), None));
+    }
+
+    /// Fetch the rooms, and join or host one once they arrive.
+    pub fn quick_match(&mut self, url: &str) {
+        self.quick_match = true;
+        self.refresh(url);
+    }
+
+    /// Human-readable state of the listing.
+    pub fn status_text(&self) -> String {
+        match &self.status {
+            RoomListingStatus::Idle => "".to_string(),
+            RoomListingStatus::Fetching if self.quick_match => "Looking for a room...".to_string(),
+            RoomListingStatus::Fetching => "Fetching rooms...".to_string(),
+            RoomListingStatus::Fetched if self.rooms.is_empty() => {
+                "No open rooms, why not host one?".to_string()
+            }
+            RoomListingStatus::Fetched => format!("{} open rooms", self.rooms.len()),
+            RoomListingStatus::Failed(error) => format!("Could not fetch the rooms: {}", error),
+        }
+    }
+}
+
+/// Public room this peer has opened, kept listed for as long as it's waiting for players.
+#[derive(Resource, Debug)]
+pub struct HostedRoom {
+    pub info: RoomInfo,
+    heartbeat: Timer,
+}
+
+impl HostedRoom {
+    pub fn new(info: RoomInfo) -> Self {
+        let mut heartbeat = Timer::from_seconds(HEARTBEAT_INTERVAL_SECS, TimerMode::Repeating);
+        // announce the room right away
+        heartbeat.set_elapsed(heartbeat.duration());
+        Self { info, heartbeat }
+    }
+}
+
+/// Name of the room picked from the listing or opened as public, to be headed into
+/// instead of the one in the settings. Lasts until matchmaking is over.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct JoinedRoom(pub String);
+
+/// Head into the room to wait for the rest of the players there.
+pub fn join_room(
+    commands: &mut Commands,
+    room: &RoomInfo,
+    game_state: &mut NextState<GameState>,
+    menu_state: &mut NextState<MenuState>,
+) {
+    commands.insert_resource(JoinedRoom(room.name.clone()));
+    commands.insert_resource(room.scene);
+    commands.insert_resource(PlayerCount(room.max_players));
+    game_state.set(GameState::Matchmaking);
+    menu_state.set(MenuState::MatchmakingLobby);
+}
+
+/// Open a public room under the settings' room name, or a made-up one if there's none, and list it.
+pub fn host_public_room(
+    commands: &mut Commands,
+    settings: &UserSettings,
+    scene: SceneSelector,
+    max_players: usize,
+) -> RoomInfo {
+    let name = if settings.room_name.trim().is_empty() {
+        format!("{}'s room", settings.player_name)
+    } else {
+        settings.room_name.clone()
+    };
+    let room = RoomInfo {
+        name,
+        players: 1,
+        max_players,
+        scene,
+        mode: GameMode::default(),
+    };
+    commands.insert_resource(HostedRoom::new(room.clone()));
+    commands.insert_resource(JoinedRoom(room.name.clone()));
+    room
+}
+
+/// System to take in the fetched rooms, and to see the quick-match through if one is waiting for them.
+fn receive_room_listing(
+    mut commands: Commands,
+    mut room_listing: ResMut<RoomListing>,
+    settings: Res<UserSettings>,
+    player_count: Option<Res<PlayerCount>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    let Some(response) = room_listing
+        .pending
+        .as_ref()
+        .and_then(PendingResponse::take)
+    else {
+        return;
+    };
+    room_listing.pending = None;
+
+    match response.and_then(|body| {
+        serde_json::from_str::<Vec<RoomInfo>>(&body).map_err(|error| error.to_string())
+    }) {
+        Ok(mut rooms) => {
+            rooms.retain(RoomInfo::is_open);
+            rooms.sort_by(|a, b| a.name.cmp(&b.name));
+            room_listing.rooms = rooms;
+            room_listing.status = RoomListingStatus::Fetched;
+        }
+        Err(error) => {
+            warn!("Failed to fetch the room listing: {}", error);
+            room_listing.status = RoomListingStatus::Failed(error);
+            room_listing.quick_match = false;
+            return;
+        }
+    }
+
+    if !room_listing.quick_match {
+        return;
+    }
+    room_listing.quick_match = false;
+
+    let room = match best_room(&room_listing.rooms) {
+        Some(room) => room.clone(),
+        None => host_public_room(
+            &mut commands,
+            &settings,
+            SceneSelector::Main,
+            player_count.map_or(2, |count| count.0),
+        ),
+    };
+    join_room(&mut commands, &room, &mut game_state, &mut menu_state);
+}
+
+/// System to keep the hosted room listed, along with the number of peers in it.
+fn handle_hosted_room_heartbeat(
+    time: Res<Time>,
+    settings: Res<UserSettings>,
+    socket: Option<Res<SpaceballSocket>>,
+    match_settings: Res<MatchSettings>,
+    mut hosted_room: ResMut<HostedRoom>,
+) {
+    if !hosted_room.heartbeat.tick(time.delta()).just_finished() {
+        return;
+    }
+    hosted_room.info.scene = match_settings.scene;
+    hosted_room.info.mode = match_settings.mode;
+    if let Some(socket) = socket {
+        hosted_room.info.players = socket.peer_count();
+    }
+    match serde_json::to_string(&hosted_room.info) {
+        // nobody waits on the answer, the listing will drop the room if it never hears from it
+        Ok(body) => {
+            send_request("POST", settings.room_listing_url.clone(), Some(body));
+        }
+        Err(error) => error!("Failed to serialize the hosted room: {}", error),
+    }
+}
+
+/// System to take the hosted room off the listing once the match starts or the host leaves.
+fn unlist_hosted_room(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    hosted_room: Option<Res<HostedRoom>>,
+) {
+    let Some(hosted_room) = hosted_room else {
+        return;
+    };
+    let url = format!(
+        "{}/{}",
+        settings.room_listing_url.trim_end_matches('/'),
+        room_slug(&hosted_room.info.name)
+    );
+    send_request("DELETE", url, None);
+    commands.remove_resource::<HostedRoom>();
+}
+
+fn forget_joined_room(mut commands: Commands) {
+    commands.remove_resource::<JoinedRoom>();
+}
+
+/// Requests are sent with a blocking HTTP client, secured with TLS for `https://` listings.
+/// Every request gets a thread of its own, keeping it off the task pools the sockets run on.
+#[cfg(not(target_arch = "wasm32"))]
+mod http {
+    use std::thread;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn spawn_request(
+        method: &'static str,
+        url: String,
+        body: Option<String>,
+        on_response: impl FnOnce(Result<String, String>) + Send + 'static,
+    ) {
+        thread::spawn(move || on_response(request(method, url, body)));
+    }
+
+    fn request(method: &'static str, url: String, body: Option<String>) -> Result<String, String> {
+        let request = ureq::request(method, &url).timeout(TIMEOUT);
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body),
+            None => request.call(),
+        }
+        .map_err(|error| match error {
+            ureq::Error::Status(status, _) => {
+                format!("the room listing answered with {}", status)
+            }
+            ureq::Error::Transport(transport) => transport.to_string(),
+        })?;
+        response.into_string().map_err(|error| error.to_string())
+    }
+}
+
+/// Requests are sent with the browser's `fetch`, which never blocks the task pool.
+#[cfg(target_arch = "wasm32")]
+mod http {
+    use crate::js_interop;
+    use bevy::tasks::IoTaskPool;
+
+    pub fn spawn_request(
+        method: &'static str,
+        url: String,
+        body: Option<String>,
+        on_response: impl FnOnce(Result<String, String>) + Send + 'static,
+    ) {
+        IoTaskPool::get()
+            .spawn(async move { on_response(request(method, url, body).await) })
+            .detach();
+    }
+
+    async fn request(
+        method: &'static str,
+        url: String,
+        body: Option<String>,
+    ) -> Result<String, String> {
+        js_interop::fetch_text_from_js(method, &url, body)
+            .await
+            .map(|response| response.as_string().unwrap_or_default())
+            .map_err(|error| {
+                error
+                    .as_string()
+                    .unwrap_or_else(|| "the request failed".to_string())
+            })
+    }
+}
+
+/// Plugin keeping track of the public rooms, and of the one this peer hosts.
+pub(crate) struct RoomListingPlugin;
+impl Plugin for RoomListingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomListing>()
+            .add_system(receive_room_listing.run_if(in_state(GameState::MainMenu)))
+            .add_system(
+                handle_hosted_room_heartbeat.run_if(
+                    in_state(GameState::Matchmaking).and_then(resource_exists::<HostedRoom>()),
+                ),
+            )
+            .add_system(unlist_hosted_room.in_schedule(OnExit(GameState::Matchmaking)))
+            .add_system(forget_joined_room.in_schedule(OnExit(GameState::Matchmaking)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_plain_names_readable() {
+        assert_eq!(room_slug("  Spaceballs42 "), "spaceballs42");
+        assert_eq!(room_slug("Spaceballs42"), room_slug("spaceballs42"));
+        assert_eq!(room_slug(""), "");
+    }
+
+    #[test]
+    fn tells_apart_names_with_replaced_characters() {
+        let slugs = [
+            room_slug("комната"),
+            room_slug("пещера1"),
+            room_slug("部屋の名前"),
+            room_slug("宇宙の部屋"),
+            room_slug("A B"),
+            room_slug("a-b"),
+            room_slug("a_b"),
+            room_slug("ab"),
+        ];
+        for (i, a) in slugs.iter().enumerate() {
+            for b in &slugs[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn makes_url_safe_slugs() {
+        for name in ["комната", "a/b?c#d", "🚀 launch"] {
+            assert!(room_slug(name)
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-'));
+        }
+        assert_eq!(room_slug("Ünïcödé"), room_slug("ünïcödé"));
+    }
+}
@@ -1,21 +1,25 @@
-use crate::network::ggrs_config::GGRSConfig;
-use crate::network::peers::{PeerConnectionEvent, PeerHandles};
+use crate::controls::devices::{ConnectedGamepads, LocalInputDevice};
+use crate::network::ggrs_config::{GGRSConfig, NetworkSettings};
+use crate::network::lobby::{LobbyPeers, LocalLobbyStatus, MatchStart};
+use crate::network::peers::{KickedPeers, PeerConnectionEvent, PeerHandles, PeerLocalPlayers};
 use crate::network::players::{PlayerData, PlayerRegistry};
 use crate::network::socket::SpaceballSocket;
-use crate::network::PlayerHandle;
+use crate::network::{PeerId, PlayerHandle};
+use crate::teams::Team;
+use crate::ui::chat::ChatMessage;
 use crate::ui::user_settings::UserSettings;
-use crate::GameState;
+use crate::{GameState, MenuState};
 use bevy::log::prelude::*;
 #[cfg(feature = "diagnostic")]
 use bevy::prelude::Local;
 use bevy::prelude::{
-    in_state, not, App, Commands, Component, EventWriter, IntoSystemAppConfig, IntoSystemConfig,
-    NextState, OnExit, Plugin, Res, ResMut, Resource,
+    in_state, not, App, Commands, Component, DetectChanges, EventReader, EventWriter,
+    IntoSystemAppConfig, IntoSystemConfig, NextState, OnExit, Plugin, Res, ResMut, Resource,
 };
-use bevy_ggrs::ggrs::PlayerType;
-#[cfg(feature = "diagnostic")]
-use bevy_ggrs::GGRSSchedule;
+use bevy::utils::HashMap;
+use bevy_ggrs::ggrs::{GGRSEvent, P2PSession, PlayerType};
 use bevy_ggrs::Session;
+use bevy_matchbox::prelude::PeerState;
 
 // Bevy-Extremists host this match making service for us to use FOR FREE.
 // So, use Johan's compatible matchbox.
@@ -25,7 +29,8 @@ use bevy_ggrs::Session;
 
 // pub const ROOM_NAME: &str = "spaceballs";
 
-/// Expected - and maximum - player count for the game session.
+/// Expected - and maximum - count of peers for the game session.
+/// Every peer brings at least one player, and perhaps a few more local ones sharing their machine.
 #[derive(Resource)]
 pub struct PlayerCount(pub usize);
 
@@ -37,9 +42,48 @@ impl std::ops::Deref for PlayerCount {
     }
 }
 
-/// Resource containing a player handle from the GGRS session as having [`PlayerType::Local`].
-#[derive(Resource)]
-pub struct LocalPlayerHandle(pub PlayerHandle);
+/// Resource containing the player handles from the GGRS session as having [`PlayerType::Local`], in their order,
+/// along with the input devices handed out to them as the session started.
+#[derive(Resource, Debug, Default)]
+pub struct LocalPlayers {
+    pub handles: Vec<PlayerHandle>,
+    /// Devices of the local players, in the same order. Kept for the whole session, whatever gets (dis)connected.
+    pub devices: Vec<LocalInputDevice>,
+}
+
+impl LocalPlayers {
+    /// Whether the player is playing on this machine.
+    pub fn contains(&self, handle: PlayerHandle) -> bool {
+        self.handles.contains(&handle)
+    }
+
+    /// Get the order of the player among the local ones, if they are local.
+    pub fn index_of(&self, handle: PlayerHandle) -> Option<usize> {
+        self.handles
+            .iter()
+            .position(|local_handle| *local_handle == handle)
+    }
+
+    /// Get the input device of the player, if they are local and there was a device left for them.
+    pub fn device_of(&self, handle: PlayerHandle) -> Option<LocalInputDevice> {
+        self.devices.get(self.index_of(handle)?).copied()
+    }
+}
+
+/// Resource with the reason the last online session was cut short, if it was, for the main menu to tell the player.
+#[derive(Resource, Debug, Default)]
+pub struct SessionInterruption(pub Option<String>);
+
+/// Give up on the online session, going back to the main menu with the reason to show there.
+pub(crate) fn interrupt_session(
+    interruption: &mut SessionInterruption,
+    next_state: &mut NextState<GameState>,
+    reason: String,
+) {
+    error!("{} Exiting to main menu.", reason);
+    interruption.0 = Some(reason);
+    next_state.set(GameState::MainMenu);
+}
 
 /// Marker component indicating that this entity should be used for some peer-side-only systems, like HUD.
 #[derive(Component)]
@@ -74,113 +118,313 @@ pub fn update_peers(
 
 /// Initialize the multiplayer session.
 /// Having input systems in GGRS schedule will not execute them until a session is initialized.
-/// Will wait until all players have joined.
+/// Will wait until all players have joined, and, if playing online, until the host has started the match.
 pub fn build_session(
     mut commands: Commands,
     mut socket: ResMut<SpaceballSocket>,
     player_count: Res<PlayerCount>,
+    peer_local_players: Res<PeerLocalPlayers>,
+    lobby_peers: Res<LobbyPeers>,
+    local_lobby_status: Res<LocalLobbyStatus>,
+    match_start: Res<MatchStart>,
     settings: Res<UserSettings>,
+    gamepads: Res<ConnectedGamepads>,
+    mut interruption: ResMut<SessionInterruption>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    // Check for new players
-    let players = socket.players();
+    // Check for new peers
+    let peer_count = socket.peer_count();
 
-    // if there is not enough players, wait
-    if players.len() < player_count.0 {
-        // wait for more players
+    // if there is not enough peers, wait
+    if peer_count < player_count.0 {
+        // wait for more peers
         return;
     }
 
-    if players.len() > player_count.0 {
-        error!("You are trying to join an already full game! Exiting to main menu.");
+    // every peer must tell how many players they bring, or the sessions won't match
+    if socket
+        .inner()
+        .connected_peers()
+        .any(|peer| !peer_local_players.map.contains_key(&peer))
+    {
+        return;
+    }
+
+    if peer_count > player_count.0 {
         // test without when `update_peers` is called externally. Maybe that would let a spectator in.
-        next_state.set(GameState::MainMenu);
+        interrupt_session(
+            &mut interruption,
+            &mut next_state,
+            "The game you are trying to join is already full!".to_string(),
+        );
         return;
-    } else {
-        info!("All peers have joined, going in-game");
     }
 
+    let is_online = player_count.0 > 1;
+    if is_online && match_start.0.is_none() {
+        return;
+    }
+    info!("All peers have joined, going in-game");
+
+    let players = socket.players(settings.local_player_count(), &peer_local_players.map);
+
+    // the inputs should take about as long to be delayed as to reach the farthest peer
+    let round_trip_ms = lobby_peers
+        .map
+        .values()
+        .filter_map(|lobby_peer| lobby_peer.ping_ms)
+        .max()
+        .unwrap_or(0);
+    let network_settings = match settings.input_delay {
+        Some(input_delay) => NetworkSettings::default().with_input_delay(input_delay),
+        None => NetworkSettings::from_round_trip(round_trip_ms),
+    };
+    info!(
+        "Using an input delay of {} frames, the longest round trip being {} ms",
+        network_settings.input_delay, round_trip_ms
+    );
+
     // create a GGRS P2P session
-    let mut session_builder = GGRSConfig::new_builder().with_num_players(player_count.0);
+    let mut session_builder =
+        GGRSConfig::new_builder(&network_settings).with_num_players(players.len());
 
     let mut peer_handles = PeerHandles::default();
     let mut player_registry = PlayerRegistry::default();
+    let mut local_players = LocalPlayers::default();
+
+    // the order of the players among those of their peer, to find their teams picked in the lobby by
+    let mut players_per_peer = HashMap::<PeerId, usize>::default();
 
     for (i, player) in players.into_iter().enumerate() {
-        session_builder = session_builder
-            .add_player(player, i)
-            .expect("failed to add player");
+        session_builder = match session_builder.add_player(player, i) {
+            Ok(session_builder) => session_builder,
+            Err(error) => {
+                interrupt_session(
+                    &mut interruption,
+                    &mut next_state,
+                    format!("Could not add player {} to the session: {}", i, error),
+                );
+                return;
+            }
+        };
 
-        // todo ensure consistency of team ordering, i.e the order of players joining? Nah.
-        // maybe implement ability to choose own color or join a specific team later, for now tis will do.
         match player {
             PlayerType::Remote(peer_id) => {
-                player_registry.0.push(PlayerData::from_player_handle(i));
-                peer_handles.map.insert(peer_id, i);
+                let order = players_per_peer.entry(peer_id).or_default();
+                let mut player_data = PlayerData::from_player_handle(i);
+                if let Some(team) = lobby_peers
+                    .map
+                    .get(&peer_id)
+                    .and_then(|lobby_peer| lobby_peer.teams.get(*order))
+                    .filter(|_| is_online)
+                {
+                    player_data.team = Team(*team);
+                }
+                if let Some(class) = lobby_peers
+                    .map
+                    .get(&peer_id)
+                    .and_then(|lobby_peer| lobby_peer.classes.get(*order))
+                    .filter(|_| is_online)
+                {
+                    player_data.class = *class;
+                }
+                *order += 1;
+                player_registry.0.push(player_data);
+                // the peer's messages are attributed to the first of their players
+                peer_handles.map.entry(peer_id).or_insert(i);
             }
             PlayerType::Local => {
-                player_registry.0.push(
-                    PlayerData::from_player_handle(i).with_name(settings.player_name.clone()),
-                );
-                commands.insert_resource(LocalPlayerHandle(i));
+                // the guests sharing the machine are told apart by their number
+                let name = match local_players.handles.len() {
+                    0 => settings.player_name.clone(),
+                    n => format!("{} {}", settings.player_name, n + 1),
+                };
+                let mut player_data = PlayerData::from_player_handle(i).with_name(name);
+                if let Some(team) = local_lobby_status
+                    .teams
+                    .get(local_players.handles.len())
+                    .filter(|_| is_online)
+                {
+                    player_data.team = Team(*team);
+                }
+                player_data.class = local_lobby_status
+                    .classes
+                    .get(local_players.handles.len())
+                    .copied()
+                    .unwrap_or(settings.character_class);
+                player_registry.0.push(player_data);
+                local_players.handles.push(i);
             }
             PlayerType::Spectator(_) => {}
         };
     }
 
+    if let Some(match_settings) = match_start.0.filter(|_| is_online) {
+        commands.insert_resource(match_settings.scene);
+    }
+
+    // the devices are settled on once, so that nobody's controls change hands mid-match
+    local_players.devices = LocalInputDevice::assign(local_players.handles.len(), &gamepads);
+    if local_players.devices.len() < local_players.handles.len() {
+        warn!(
+            "Only {} of the {} local players have an input device to play with",
+            local_players.devices.len(),
+            local_players.handles.len()
+        );
+    }
+
+    commands.insert_resource(network_settings);
     commands.insert_resource(peer_handles);
     commands.insert_resource(player_registry);
+    commands.insert_resource(local_players);
 
     // move the channel out of the socket (required because GGRS takes ownership of it)
-    let channel = socket
+    let channel = match socket
         .inner_mut()
         .take_channel(SpaceballSocket::GGRS_CHANNEL)
-        .unwrap();
+    {
+        Ok(channel) => channel,
+        Err(error) => {
+            interrupt_session(
+                &mut interruption,
+                &mut next_state,
+                format!("Could not open the game channel to the peers: {:?}", error),
+            );
+            return;
+        }
+    };
 
     // start the GGRS session
-    let ggrs_session = session_builder
-        .start_p2p_session(channel)
-        .expect("failed to start session");
+    let ggrs_session = match session_builder.start_p2p_session(channel) {
+        Ok(ggrs_session) => ggrs_session,
+        Err(error) => {
+            interrupt_session(
+                &mut interruption,
+                &mut next_state,
+                format!("Could not start the session: {}", error),
+            );
+            return;
+        }
+    };
 
     commands.insert_resource(Session::P2PSession(ggrs_session));
     next_state.set(GameState::InGame);
 }
 
-pub fn sever_connection(mut commands: Commands) {
-    commands.remove_resource::<SpaceballSocket>();
-    commands.remove_resource::<Session<GGRSConfig>>();
-    // ... and maybe more
+/// Stop waiting on the inputs of the peer's players.
+fn disconnect_peer(session: &mut P2PSession<GGRSConfig>, peer: PeerId) {
+    for handle in session.handles_by_address(peer) {
+        // GGRS may well have given up on them already
+        if let Err(error) = session.disconnect_player(handle) {
+            debug!("Player {} is already disconnected: {}", handle, error);
+        }
+    }
 }
 
-#[allow(unused)]
-// delete probably, it does not detect desync in the rolled-back components (transform)
-pub fn detect_desync(session: ResMut<Session<GGRSConfig>>) {
-    if let Session::P2PSession(p2p_session) = session.into_inner() {
-        let events = p2p_session.events();
-        if events.len() != 0 {
-            println!("GGRS got {} events.", events.len());
-            for event in events {
-                /*if matches!(event, GGRSEvent::DesyncDetected { .. }) {
-                    println!("Desync detected: {:?}", event);
-                }*/
-                println!("Hi, I'm {:?}", event);
-            }
+/// System to stop waiting on the inputs of the peers who have left, rather than for the session to time them out.
+pub fn disconnect_departed_peers(
+    session: Option<ResMut<Session<GGRSConfig>>>,
+    mut peer_events: EventReader<PeerConnectionEvent>,
+) {
+    let Some(Session::P2PSession(session)) = session.map(|session| session.into_inner()) else {
+        return;
+    };
+    for event in peer_events.iter() {
+        if event.state == PeerState::Disconnected {
+            disconnect_peer(session, event.id);
         }
     }
 }
 
+/// System to drop the peers the host has kicked from the session, rather than count on them to leave.
+pub fn disconnect_kicked_peers(
+    session: Option<ResMut<Session<GGRSConfig>>>,
+    kicked_peers: Res<KickedPeers>,
+) {
+    if !kicked_peers.is_changed() {
+        return;
+    }
+    let Some(Session::P2PSession(session)) = session.map(|session| session.into_inner()) else {
+        return;
+    };
+    for peer in kicked_peers.set.iter() {
+        disconnect_peer(session, *peer);
+    }
+}
+
+/// System to tell the player about the troubles the session runs into, none of which stop it.
+pub fn handle_session_events(
+    session: Option<ResMut<Session<GGRSConfig>>>,
+    players: Res<PlayerRegistry>,
+    mut messenger: EventWriter<ChatMessage>,
+) {
+    let Some(Session::P2PSession(session)) = session.map(|session| session.into_inner()) else {
+        return;
+    };
+    let events: Vec<_> = session.events().collect();
+    // the peer's name may already be forgotten, but not their first player's
+    let peer_name = |addr: &PeerId| {
+        session
+            .handles_by_address(*addr)
+            .first()
+            .and_then(|handle| players.0.get(*handle))
+            .map_or_else(|| "A peer".to_string(), |player| player.name.clone())
+    };
+    for event in events {
+        let message = match event {
+            GGRSEvent::NetworkInterrupted {
+                addr,
+                disconnect_timeout,
+            } => format!(
+                "Lost connection to {}, dropping them in {:.1} s...",
+                peer_name(&addr),
+                disconnect_timeout as f32 / 1000.
+            ),
+            GGRSEvent::NetworkResumed { addr } => {
+                format!("Connection to {} is back!", peer_name(&addr))
+            }
+            GGRSEvent::Disconnected { addr } => {
+                format!("{} dropped out, the match goes on.", peer_name(&addr))
+            }
+            GGRSEvent::DesyncDetected {
+                frame,
+                local_checksum,
+                remote_checksum,
+                addr,
+            } => {
+                warn!(
+                    "Desync with {:?} at frame {}: {} vs {}",
+                    addr, frame, local_checksum, remote_checksum
+                );
+                continue;
+            }
+            _ => continue,
+        };
+        messenger.send(ChatMessage::message(message));
+    }
+}
+
+/// System to forget about the interruption once the player has moved on from the main menu.
+fn clear_session_interruption(mut interruption: ResMut<SessionInterruption>) {
+    interruption.0 = None;
+}
+
+pub fn sever_connection(mut commands: Commands) {
+    commands.remove_resource::<SpaceballSocket>();
+    commands.remove_resource::<Session<GGRSConfig>>();
+    // ... and maybe more
+}
+
 pub(crate) struct SessionPlugin;
 impl Plugin for SessionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_peers.run_if(not(in_state(GameState::MainMenu))))
+        app.init_resource::<SessionInterruption>()
+            .add_system(update_peers.run_if(not(in_state(GameState::MainMenu))))
             .add_system(build_session.run_if(in_state(GameState::Matchmaking)))
-            .add_system(sever_connection.in_schedule(OnExit(GameState::InGame)));
-
-        #[cfg(feature = "diagnostic")]
-        app.add_system(
-            detect_desync
-                .in_schedule(GGRSSchedule)
-                .run_if(in_state(GameState::InGame)),
-        );
+            .add_system(disconnect_departed_peers.run_if(in_state(GameState::InGame)))
+            .add_system(disconnect_kicked_peers.run_if(in_state(GameState::InGame)))
+            .add_system(handle_session_events.run_if(in_state(GameState::InGame)))
+            .add_system(sever_connection.in_schedule(OnExit(GameState::InGame)))
+            .add_system(clear_session_interruption.in_schedule(OnExit(MenuState::Main)));
     }
 }
@@ -1,10 +1,12 @@
 use crate::network::peers::PeerMessage;
+use crate::network::rooms::{room_slug, JoinedRoom};
 use crate::network::session::PlayerCount;
 use crate::ui::user_settings::UserSettings;
 use crate::{App, GameState};
 use bevy::log::prelude::*;
 use bevy::prelude::{Commands, IntoSystemAppConfig, OnEnter, Plugin, Res, Resource};
 use bevy::tasks::IoTaskPool;
+use bevy::utils::HashMap;
 use bevy_ggrs::ggrs;
 use bevy_ggrs::ggrs::PlayerType;
 use bevy_matchbox::matchbox_socket::{MessageLoopFuture, WebRtcSocket};
@@ -73,19 +75,34 @@ impl SpaceballSocket {
             .channel(Self::RELIABLE_CHANNEL)
             .receive()
             .into_iter()
-            .map(|(id, packet)| {
-                let msg = bincode::deserialize(&packet).unwrap();
-                (id, msg)
+            .filter_map(|(id, packet)| match bincode::deserialize(&packet) {
+                Ok(msg) => Some((id, msg)),
+                Err(error) => {
+                    // likely a peer on a different version of the game, nothing worth crashing over
+                    warn!("Dropping an unreadable message from {:?}: {}", id, error);
+                    None
+                }
             })
             .collect()
     }
 
+    /// Get the number of peers in the session, counting in ourselves.
+    pub fn peer_count(&self) -> usize {
+        self.inner().connected_peers().count() + 1
+    }
+
+    /// Get the players of every peer, each peer bringing as many players as they have told us they have.
+    /// Peers who haven't told yet are assumed to bring one.
     #[allow(unused)]
-    pub fn players(&self) -> Vec<PlayerType<PeerId>> {
+    pub fn players(
+        &self,
+        local_player_count: usize,
+        remote_player_counts: &HashMap<PeerId, usize>,
+    ) -> Vec<PlayerType<PeerId>> {
         let Some(our_id) = self.inner().id() else {
             // we're still waiting for the server to initialize our id
             // no peers should be added at this point anyway
-            return vec![PlayerType::Local];
+            return vec![PlayerType::Local; local_player_count];
         };
 
         // player order needs to be consistent order across all peers
@@ -97,11 +114,12 @@ impl SpaceballSocket {
         ids.sort();
 
         ids.into_iter()
-            .map(|id| {
+            .flat_map(|id| {
                 if id == our_id {
-                    PlayerType::Local
+                    vec![PlayerType::Local; local_player_count]
                 } else {
-                    PlayerType::Remote(id)
+                    let count = remote_player_counts.get(&id).copied().unwrap_or(1);
+                    vec![PlayerType::Remote(id); count]
                 }
             })
             .collect()
@@ -125,14 +143,19 @@ pub fn start_matchbox_socket(
     mut commands: Commands,
     player_count: Res<PlayerCount>,
     settings: Res<UserSettings>,
+    joined_room: Option<Res<JoinedRoom>>,
 ) {
     let (room_url, reconnect_attempts) = if player_count.0 > 1 {
+        let room_name = joined_room
+            .as_deref()
+            .map_or(settings.room_name.as_str(), |room| room.0.as_str());
+        // every room is a separate room on the matchbox server, the nameless one being the common one
+        let room = match room_slug(room_name) {
+            slug if slug.is_empty() => "spaceballs".to_string(),
+            slug => format!("spaceballs-{}", slug),
+        };
         (
-            format!(
-                "{}/spaceballs?next={}",
-                settings.server_url, settings.room_name,
-            )
-            .to_lowercase(),
+            format!("{}/{}?next={}", settings.server_url, room, player_count.0).to_lowercase(),
             Some(3),
         )
     } else {
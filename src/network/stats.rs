@@ -0,0 +1,106 @@
+//! Live connection quality of the session, as GGRS measures it.
+
+use crate::network::ggrs_config::{GGRSConfig, NetworkSettings};
+use crate::network::PlayerHandle;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ggrs::Session;
+
+/// How often the network stats are refreshed. GGRS' own estimates don't change much faster anyway.
+const STATS_REFRESH_SECS: f32 = 0.5;
+/// Round trip beyond which a peer is considered lagging.
+const LAGGING_PING_MS: u128 = 200;
+/// Frames a peer may drift away from us before they are considered lagging.
+const LAGGING_FRAMES_AHEAD: i32 = 3;
+
+/// Connection stats of a remote player.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerNetworkStats {
+    /// Round trip to the player's peer.
+    pub ping_ms: u128,
+    /// How many frames we are ahead of the player, or behind if negative.
+    pub frames_ahead: i32,
+    /// Bandwidth we use to talk to the player's peer.
+    pub kbps_sent: usize,
+}
+
+impl PlayerNetworkStats {
+    pub fn is_lagging(&self) -> bool {
+        self.ping_ms > LAGGING_PING_MS || self.frames_ahead.abs() > LAGGING_FRAMES_AHEAD
+    }
+}
+
+/// Resource with the latest network stats of the session, refreshed every so often.
+#[derive(Resource, Debug, Default)]
+pub struct ConnectionQuality {
+    /// Stats of the remote players, once GGRS has synchronized with them.
+    pub players: HashMap<PlayerHandle, PlayerNetworkStats>,
+    /// Frames between the last confirmed one and the current one, all of which may have to be rolled back.
+    pub rollback_frames: usize,
+    /// Frames the session may predict, past which it stalls.
+    pub max_prediction_frames: usize,
+}
+
+impl ConnectionQuality {
+    /// Whether the predictions are about to run out, having the game stall or roll back far.
+    pub fn is_rollback_deep(&self) -> bool {
+        self.max_prediction_frames > 0 && self.rollback_frames + 1 >= self.max_prediction_frames
+    }
+}
+
+/// System to take in the network stats of the session.
+fn update_connection_quality(
+    session: Option<Res<Session<GGRSConfig>>>,
+    network_settings: Option<Res<NetworkSettings>>,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    mut quality: ResMut<ConnectionQuality>,
+) {
+    let timer =
+        timer.get_or_insert_with(|| Timer::from_seconds(STATS_REFRESH_SECS, TimerMode::Repeating));
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some(Session::P2PSession(session)) = session.as_deref() else {
+        return;
+    };
+
+    quality.players = session
+        .remote_player_handles()
+        .into_iter()
+        .filter_map(|handle| {
+            // not available until the peers are synchronized
+            let stats = session.network_stats(handle).ok()?;
+            Some((
+                handle,
+                PlayerNetworkStats {
+                    ping_ms: stats.ping,
+                    frames_ahead: -stats.local_frames_behind,
+                    kbps_sent: stats.kbps_sent,
+                },
+            ))
+        })
+        .collect();
+    quality.rollback_frames = session
+        .current_frame()
+        .saturating_sub(session.confirmed_frame())
+        .max(0) as usize;
+    quality.max_prediction_frames = network_settings.map_or(session.max_prediction(), |settings| {
+        settings.max_prediction_frames
+    });
+}
+
+fn reset_connection_quality(mut quality: ResMut<ConnectionQuality>) {
+    *quality = ConnectionQuality::default();
+}
+
+/// Plugin keeping track of the connection quality in online matches.
+pub(crate) struct NetworkStatsPlugin;
+impl Plugin for NetworkStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConnectionQuality>()
+            .add_system(update_connection_quality.run_if(in_state(GameState::InGame)))
+            .add_system(reset_connection_quality.in_schedule(OnExit(GameState::InGame)));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::characters::CHARACTER_MAX_HEALTH;
+use crate::health::HitPoints;
 use crate::{error, SCREEN_SPAN};
 use bevy::ecs::query::{ReadOnlyWorldQuery, WorldQuery};
 use bevy::math::Vec3;
@@ -106,12 +108,103 @@ impl KinematicsBundle {
     }
 }
 
-/// Standard rectangular obstacle, stopping characters and bullets alike.
+/// What an obstacle is made of, telling how it treats the projectiles and characters that run into it.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum ObstacleMaterial {
+    /// Stops characters and bullets alike.
+    #[default]
+    Standard,
+    /// Reflects the bullets and bounces the characters off.
+    Bouncy,
+    /// Swallows any projectile that hits it.
+    Absorbing,
+    /// Lets railgun slugs through, at a cost of some of their damage.
+    Penetrable,
+    /// Stops everything, until it's shot to pieces.
+    Glass,
+    /// Too slippery to hold on to, the characters touching it sliding along with little control.
+    Ice,
+}
+
+impl ObstacleMaterial {
+    /// Color of an obstacle made of the material.
+    pub fn color(&self) -> Color {
+        match self {
+            ObstacleMaterial::Standard => DEFAULT_OBSTACLE_COLOR,
+            ObstacleMaterial::Bouncy => Color::ORANGE_RED * 3.,
+            ObstacleMaterial::Absorbing => Color::rgb(0.35, 0.2, 0.5),
+            ObstacleMaterial::Penetrable => Color::rgba(1.0, 1.0, 1.0, 0.5),
+            ObstacleMaterial::Glass => Color::rgba(0.6, 0.85, 1.0, 0.35),
+            ObstacleMaterial::Ice => Color::rgb(0.55, 0.9, 1.0),
+        }
+    }
+
+    /// Restitution of the material, overriding that of whatever bumps into it if it's bouncy.
+    pub fn restitution(&self) -> Restitution {
+        match self {
+            ObstacleMaterial::Bouncy => Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            _ => Restitution::default(),
+        }
+    }
+
+    /// Friction of the material, overriding that of whatever slides against it if it's icy.
+    pub fn friction(&self) -> Friction {
+        match self {
+            ObstacleMaterial::Ice => Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            _ => Friction::default(),
+        }
+    }
+
+    /// Whether the material stops the projectiles that pierce through obstacles, such as railgun slugs.
+    /// Only the penetrable walls let them through.
+    pub const fn stops_piercing(&self) -> bool {
+        !matches!(self, ObstacleMaterial::Penetrable)
+    }
+
+    /// Hit points an obstacle of the material can take before breaking.
+    pub const fn integrity(&self) -> HitPoints {
+        match self {
+            ObstacleMaterial::Glass => CHARACTER_MAX_HEALTH * 0.6,
+            _ => HitPoints::INFINITY,
+        }
+    }
+}
+
+/// Hit points an obstacle has left before it breaks. Those of unbreakable materials never run out.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect, FromReflect)]
+pub struct ObstacleIntegrity(pub HitPoints);
+
+impl Default for ObstacleIntegrity {
+    fn default() -> Self {
+        Self(HitPoints::INFINITY)
+    }
+}
+
+impl ObstacleIntegrity {
+    /// Chip away at the obstacle. Returns `true` if this is what breaks it.
+    pub fn damage(&mut self, damage: HitPoints) -> bool {
+        let was_intact = self.0 > 0.0;
+        self.0 -= damage;
+        was_intact && self.0 <= 0.0
+    }
+}
+
+/// Standard rectangular obstacle, stopping characters and bullets alike, unless made of another material.
 /// Uses [`CHUNK_SIZE`] to determine its dimensions in addition to the provided scale.
 #[derive(Bundle)]
 pub struct RectangularObstacleBundle {
     pub rigidbody: RigidBody,
     pub collider: Collider,
+    pub material: ObstacleMaterial,
+    pub integrity: ObstacleIntegrity,
+    pub restitution: Restitution,
+    pub friction: Friction,
     #[bundle]
     pub sprite_bundle: SpriteBundle,
 }
@@ -121,6 +214,10 @@ impl Default for RectangularObstacleBundle {
         Self {
             rigidbody: RigidBody::Fixed,
             collider: Collider::cuboid(1., 1.),
+            material: ObstacleMaterial::default(),
+            integrity: ObstacleIntegrity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
                     color: DEFAULT_OBSTACLE_COLOR,
@@ -184,6 +281,16 @@ impl RectangularObstacleBundle {
         self.sprite_bundle.sprite.color = color;
         self
     }
+
+    /// Make the obstacle out of another material, painting it in the material's color.
+    pub fn with_material(mut self, material: ObstacleMaterial) -> Self {
+        self.material = material;
+        self.integrity = ObstacleIntegrity(material.integrity());
+        self.restitution = material.restitution();
+        self.friction = material.friction();
+        self.sprite_bundle.sprite.color = material.color();
+        self
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -400,6 +507,7 @@ pub enum CollisionLayer {
     Gear = 1 << 1,
     Projectile = 1 << 2,
     Obstacle = 1 << 3,
+    Deployable = 1 << 4,
 }
 
 impl Into<u32> for CollisionLayer {
@@ -417,6 +525,7 @@ impl CollisionLayer {
             CollisionLayer::Gear,
             CollisionLayer::Projectile,
             CollisionLayer::Obstacle,
+            CollisionLayer::Deployable,
         ]
     }
 }
@@ -524,7 +633,6 @@ pub fn handle_entities_out_of_bounds(
     mut query_bodies: Query<(&Transform, Entity), With<Velocity>>,
 ) {
     const HALF_SCREEN_SPAN: f32 = SCREEN_SPAN * 0.5;
-    // todo projectile velocity dampening
     for (transform, entity) in query_bodies.iter_mut() {
         if transform.translation.x.abs() > HALF_SCREEN_SPAN
             || transform.translation.y.abs() > HALF_SCREEN_SPAN
@@ -542,6 +650,7 @@ pub struct SpaceballsPhysicsPlugin;
 impl Plugin for SpaceballsPhysicsPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<OngoingCollisions>()
+            .register_type::<ObstacleMaterial>()
             .add_system(update_ongoing_collisions)
             .add_system(cleanup_ongoing_collisions.in_base_set(CoreSet::PostUpdate));
     }
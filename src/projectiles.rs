@@ -1,17 +1,23 @@
-use crate::guns::{GunPersistentStats, GunPreset};
+use crate::audio::{SoundCue, Surface};
+use crate::characters::PlayerControlled;
+use crate::guns::{Gun, GunPreset};
 use crate::health::{Dying, Health, HitPoints};
-use crate::network::PlayerHandle;
+use crate::network::{PlayerHandle, MAINTAINED_FPS_F64};
 use crate::physics::{
     popular_collider, try_get_components_from_entities, ActiveEvents, CollisionEvent,
-    CollisionLayer, KinematicsBundle, Velocity,
+    CollisionLayer, KinematicsBundle, ObstacleIntegrity, ObstacleMaterial, Sensor, Velocity,
 };
 use crate::teams::{Team, TeamNumber};
-use bevy::math::Vec3;
+use bevy::math::{Vec2, Vec3};
 use bevy::prelude::{
-    Bundle, Commands, Component, Entity, EventReader, FromReflect, Query, Reflect, Sprite,
-    SpriteBundle, Transform,
+    Bundle, Commands, Component, Entity, EventReader, EventWriter, FromReflect, Query, Reflect,
+    Sprite, SpriteBundle, Transform,
 };
 use bevy::utils::default;
+use std::time::Duration;
+
+/// Share of the damage a slug keeps after passing through a penetrable obstacle.
+const PENETRATION_DAMAGE_RETAINED: f32 = 0.6;
 
 /// Collection of components making up a projectile entity.
 #[derive(Bundle)]
@@ -39,17 +45,24 @@ impl ProjectileBundle {
             projectile: Projectile {
                 gun_type,
                 shooter_handle,
+                damage_multiplier: 1.0,
+                ..default()
             },
             team: Team(team),
             kinematics: KinematicsBundle::new(
                 // radius + bit of an oomph to the collider, no need to be so accurate
                 popular_collider::disc(0.5 + 0.25),
                 &[CollisionLayer::Projectile],
-                &[CollisionLayer::Character, CollisionLayer::Obstacle],
+                &[
+                    CollisionLayer::Character,
+                    CollisionLayer::Obstacle,
+                    CollisionLayer::Deployable,
+                ],
             )
             .with_linear_velocity(velocity)
             .with_restitution(gun_stats.projectile_elasticity)
-            .with_density(gun_stats.projectile_density),
+            .with_density(gun_stats.projectile_density)
+            .with_linear_damping(gun_stats.projectile_drag),
             active_events: ActiveEvents::COLLISION_EVENTS,
             sprite_bundle: SpriteBundle {
                 sprite: Sprite {
@@ -61,6 +74,12 @@ impl ProjectileBundle {
             },
         }
     }
+
+    /// Scale the damage the projectile deals, as the shooter's modifiers dictate.
+    pub fn with_damage_multiplier(mut self, damage_multiplier: f32) -> Self {
+        self.projectile.damage_multiplier = damage_multiplier;
+        self
+    }
 }
 
 /// Marker component signifying that this is indeed a bullet / projectile.
@@ -68,45 +87,151 @@ impl ProjectileBundle {
 pub struct Projectile {
     pub gun_type: GunPreset,
     pub shooter_handle: Option<PlayerHandle>,
+    /// Multiplier of any damage dealt, fixed at the moment of firing.
+    pub damage_multiplier: f32,
+    /// Time the projectile has spent in flight.
+    pub flight_time: Duration,
+    /// Distance the projectile has travelled, bounces and all.
+    pub distance_travelled: f32,
+}
+
+/// Event of a projectile having dealt damage to a body. Everything that shows off the hits feeds on this.
+#[derive(Clone, Debug)]
+pub struct DamageDealt {
+    /// Projectile that dealt the damage, or the mine that blew up. One may deal damage over several frames.
+    pub projectile: Entity,
+    pub victim: Entity,
+    pub victim_handle: Option<PlayerHandle>,
+    pub victim_team: Option<TeamNumber>,
+    pub shooter_handle: Option<PlayerHandle>,
+    pub shooter_team: TeamNumber,
+    pub gun_type: GunPreset,
+    pub damage: HitPoints,
+    /// Point of impact in the world.
+    pub position: Vec3,
+    /// Direction the projectile was travelling in when it hit.
+    pub direction: Vec2,
+    /// Whether this hit took the last of the victim's hit points.
+    pub is_fatal: bool,
+}
+
+/// Event of a projectile having hit anything at all, be it a body or a wall.
+#[derive(Clone, Debug)]
+pub struct ProjectileImpact {
+    pub gun_type: GunPreset,
+    pub surface: Surface,
+    /// Point of impact in the world.
+    pub position: Vec3,
+    /// Direction the projectile was travelling in when it hit.
+    pub direction: Vec2,
 }
 
 impl Projectile {
+    /// Damage the projectile deals on hit, or a substitute amount of it, scaled by the multiplier and the falloff.
+    pub fn dealt_damage(&self, damage_substitute: Option<HitPoints>) -> HitPoints {
+        let gun_stats = self.gun_type.stats();
+        damage_substitute.unwrap_or(gun_stats.projectile_damage)
+            * self.damage_multiplier
+            * gun_stats.damage_falloff_multiplier(self.distance_travelled)
+    }
+
     /// Apply damage to a body affected by a projectile. If the remaining health happens to be below 0, marks it Dying.
+    /// Reports the damage dealt, if any.
     pub fn do_damage(
         commands: &mut Commands,
-        projectile: (&GunPersistentStats, Option<PlayerHandle>, &Team),
-        body: (Entity, &mut Health, Option<&Team>),
+        damage_events: &mut EventWriter<DamageDealt>,
+        projectile: (Entity, &Projectile, &Team, &Transform, &Velocity),
+        body: (
+            Entity,
+            &mut Health,
+            Option<&Team>,
+            Option<&PlayerControlled>,
+        ),
         damage_substitute: Option<HitPoints>,
     ) {
         if body.1.is_dead() {
             // uncouth, but since we still don't have healing, return to this later when panicking is solved
             return;
         }
+        let gun_stats = projectile.1.gun_type.stats();
         let mut should_be_damaged = true;
         if let Some(body_team) = body.2 {
-            should_be_damaged = projectile.0.friendly_fire || projectile.2 != body_team;
+            should_be_damaged = gun_stats.friendly_fire || projectile.2 != body_team;
         }
-        if should_be_damaged
-            && body
-                .1
-                .damage(damage_substitute.unwrap_or(projectile.0.projectile_damage))
-        {
+        if !should_be_damaged {
+            return;
+        }
+
+        let damage = projectile.1.dealt_damage(damage_substitute);
+        let is_fatal = body.1.damage(damage);
+        if is_fatal {
             // todo panics if an entity is already despawned. issues on bevy are still open.
             commands.entity(body.0).insert(Dying {
-                by_shooter: projectile.1,
+                by_shooter: projectile.1.shooter_handle,
             });
         }
+
+        damage_events.send(DamageDealt {
+            projectile: projectile.0,
+            victim: body.0,
+            victim_handle: body.3.map(|player| player.handle),
+            victim_team: body.2.map(|team| team.0),
+            shooter_handle: projectile.1.shooter_handle,
+            shooter_team: projectile.2 .0,
+            gun_type: projectile.1.gun_type,
+            damage,
+            position: projectile.3.translation,
+            direction: projectile.4.linvel.normalize_or_zero(),
+            is_fatal,
+        });
+    }
+}
+
+/// System to keep track of how long and how far the projectiles have flown,
+/// and to get rid of those spent or slowed down by their drag too much to do any harm.
+pub fn handle_projectile_flight(
+    mut commands: Commands,
+    mut query_bullets: Query<(&mut Projectile, &Velocity, Entity)>,
+) {
+    // GGRS fixed ticks
+    let delta = Duration::from_secs_f64(1. / MAINTAINED_FPS_F64);
+    for (mut bullet, velocity, entity) in query_bullets.iter_mut() {
+        let speed = velocity.linvel.length();
+        bullet.flight_time += delta;
+        bullet.distance_travelled += speed * delta.as_secs_f32();
+
+        let gun_stats = bullet.gun_type.stats();
+        if gun_stats.is_projectile_spent(bullet.flight_time, bullet.distance_travelled)
+            || gun_stats.is_projectile_busted(speed)
+        {
+            commands.entity(entity).despawn();
+        }
     }
 }
 
 /// System to read collision events from bullets and apply their effects to the respective bodies.
-/// In particular, damage.
+/// In particular, damage, and whatever the material of the obstacles hit makes of the bullets.
 pub fn handle_bullet_collision_events(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageDealt>,
+    mut impact_events: EventWriter<ProjectileImpact>,
+    mut sound_cues: EventWriter<SoundCue>,
     // todo change this function to only damage if projectile dampening is done, health would be a given
-    mut query_bodies: Query<(Option<&mut Health>, Option<&Team>)>,
-    query_bullets: Query<(&Projectile, &Team, &Velocity)>,
+    mut query_bodies: Query<(
+        Option<&mut Health>,
+        Option<&Team>,
+        Option<&PlayerControlled>,
+        Option<&Gun>,
+        Option<(&ObstacleMaterial, &mut ObstacleIntegrity)>,
+    )>,
+    mut query_bullets: Query<(
+        &mut Projectile,
+        &Team,
+        &Transform,
+        &Velocity,
+        Option<&Sensor>,
+    )>,
 ) {
     for event in collision_events.iter() {
         let (entity_a, entity_b) = match event {
@@ -116,30 +241,69 @@ pub fn handle_bullet_collision_events(
         if let Some((bullet_entity, body_entity)) =
             try_get_components_from_entities(&query_bullets, &query_bodies, entity_a, entity_b)
         {
-            let (body_health, body_team) = query_bodies.get_mut(body_entity).unwrap();
-            let (gun_stats, maybe_shooter, bullet_team, bullet_velocity) = query_bullets
-                .get(bullet_entity)
-                .map(|(bullet, team, velocity)| {
-                    (
-                        bullet.gun_type.stats(),
-                        bullet.shooter_handle,
-                        team,
-                        velocity,
-                    )
-                })
-                .unwrap();
+            let (body_health, body_team, body_player, body_gun, body_obstacle) =
+                query_bodies.get_mut(body_entity).unwrap();
+            let (mut bullet, bullet_team, bullet_transform, bullet_velocity, bullet_sensor) =
+                query_bullets.get_mut(bullet_entity).unwrap();
+            let gun_stats = bullet.gun_type.stats();
             // todo deal damage proportionate to the momentum transferred, armor changes restitution of the body - deal less damage if a bullet is deflected
             // There'd be double damage if we don't pick a type of events.
             // Most bullets do not register collision Stopping immediately due to perfect inelasticity.
             if matches!(event, CollisionEvent::Started(..)) {
+                let surface = if body_health.is_some() {
+                    Surface::Character
+                } else if body_gun.is_some() {
+                    Surface::Gear
+                } else if matches!(body_obstacle, Some((ObstacleMaterial::Glass, _))) {
+                    Surface::Glass
+                } else {
+                    Surface::Obstacle
+                };
+                sound_cues.send(SoundCue::Impact(surface));
+                impact_events.send(ProjectileImpact {
+                    gun_type: bullet.gun_type,
+                    surface,
+                    position: bullet_transform.translation,
+                    direction: bullet_velocity.linvel.normalize_or_zero(),
+                });
+
                 if let Some(mut life) = body_health {
                     Projectile::do_damage(
                         &mut commands,
-                        (&gun_stats, maybe_shooter, bullet_team),
-                        (body_entity, &mut life, body_team),
+                        &mut damage_events,
+                        (
+                            bullet_entity,
+                            &*bullet,
+                            bullet_team,
+                            bullet_transform,
+                            bullet_velocity,
+                        ),
+                        (body_entity, &mut life, body_team, body_player),
                         None,
                     );
                 }
+
+                if let Some((material, mut integrity)) = body_obstacle {
+                    let is_broken = integrity.damage(bullet.dealt_damage(None));
+                    if is_broken {
+                        commands.entity(body_entity).despawn();
+                        sound_cues.send(SoundCue::Shatter);
+                    }
+
+                    // slugs only pass through the penetrable walls, at a cost of some damage, and the glass they break
+                    let is_stopped = if bullet_sensor.is_some() {
+                        if !material.stops_piercing() {
+                            bullet.damage_multiplier *= PENETRATION_DAMAGE_RETAINED;
+                        }
+                        material.stops_piercing() && !is_broken
+                    } else {
+                        *material == ObstacleMaterial::Absorbing
+                    };
+                    if is_stopped {
+                        commands.entity(bullet_entity).despawn();
+                        continue;
+                    }
+                }
             }
             if gun_stats.is_projectile_busted(bullet_velocity.linvel.length()) {
                 commands.entity(bullet_entity).despawn();
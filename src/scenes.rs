@@ -1,28 +1,43 @@
+use crate::audio::SoundCue;
 use crate::characters::{AICharacterBundle, BuildCharacter, PlayerCharacterBundle};
-use crate::network::session::{LocalPlayer, LocalPlayerHandle};
+use crate::classes::CharacterClass;
+use crate::match_stats::StatEvent;
+use crate::network::session::{LocalPlayer, LocalPlayers};
 use crate::network::{PlayerHandle, PlayerRegistry, MAINTAINED_FPS_F64};
-use crate::physics::{Chunks, ChunksAnchor};
+use crate::physics::{Chunks, ChunksAnchor, ObstacleMaterial};
+use crate::spawners::{ItemSpawnerBundle, LootTable};
 use crate::{
-    Color, EntropyGenerator, GunBundle, GunPreset, PlayerCount, RectangularObstacleBundle,
-    ReflectResource, TimerMode, AI_DEFAULT_TEAM, PLAYER_DEFAULT_TEAM,
+    Color, EntropyGenerator, GunBundle, GunPreset, RectangularObstacleBundle, ReflectResource,
+    TimerMode, AI_DEFAULT_TEAM, PLAYER_DEFAULT_TEAM,
 };
 use bevy::math::{Quat, Vec3};
 use bevy::prelude::{
-    default, Bundle, Camera, Commands, Component, Entity, EventReader, FromReflect, Query, Reflect,
-    Res, ResMut, Resource, Timer, Transform, Window, Without,
+    default, Bundle, Camera, Commands, Component, Entity, EventReader, EventWriter, FromReflect,
+    Query, Reflect, Res, ResMut, Resource, Timer, Transform, Window, Without,
 };
 use bevy::reflect::ReflectFromReflect;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::f32::consts::PI;
 use std::time::Duration;
 
 /// Specifier of the scene which to load.
-#[derive(clap::ValueEnum, Resource, Clone, Copy, Debug)]
+#[derive(clap::ValueEnum, Resource, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SceneSelector {
     Main,
     Experimental,
 }
 
+impl SceneSelector {
+    /// Get the scene to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            SceneSelector::Main => SceneSelector::Experimental,
+            SceneSelector::Experimental => SceneSelector::Main,
+        }
+    }
+}
+
 impl TryFrom<String> for SceneSelector {
     type Error = &'static str;
 
@@ -125,13 +140,15 @@ pub fn summon_scene(
     commands: Commands,
     scene: Option<Res<SceneSelector>>,
     random_state: ResMut<EntropyGenerator>,
-    player_count: Res<PlayerCount>,
+    player_registry: Res<PlayerRegistry>,
 ) {
     match scene {
         None => setup_main(commands),
         Some(scene) => match scene.into_inner() {
             SceneSelector::Main => setup_main(commands),
-            SceneSelector::Experimental => setup_experimental(commands, random_state, player_count),
+            SceneSelector::Experimental => {
+                setup_experimental(commands, random_state, player_registry)
+            }
         },
     }
 }
@@ -152,7 +169,7 @@ pub fn despawn_everything(
 pub fn setup_experimental(
     mut commands: Commands,
     mut random_state: ResMut<EntropyGenerator>,
-    player_count: Res<PlayerCount>,
+    player_registry: Res<PlayerRegistry>,
 ) {
     setup_base_arena(&mut commands);
 
@@ -174,11 +191,12 @@ pub fn setup_experimental(
     ));
 
     // Non-existent player character 2, whose death will cause a crash *shrug*
-    if player_count.0 == 1 {
+    if player_registry.0.len() == 1 {
         PlayerCharacterBundle::new(
             Transform::from_translation(Vec3::new(-50.0, 150.0, 0.0)),
             PLAYER_DEFAULT_TEAM + 1,
             1,
+            CharacterClass::default(),
         )
         .spawn_with_equipment(
             &mut commands,
@@ -194,21 +212,38 @@ pub fn setup_experimental(
             .with_scale(Vec3::new(2.0, 3.0, 1.0)),
         AI_DEFAULT_TEAM,
         usize::MAX,
+        CharacterClass::default(),
     )
     .spawn_with_equipment(&mut commands, random_state.fork(), vec![GunPreset::RailGun]);
 
-    // Random wall in the middle
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        ChunksAnchor::Center,
-        ChunksAnchor::Center,
-        1.0,
-        2.0,
-    ));
+    // Random wall in the middle, eating up the stray bullets
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(ChunksAnchor::Center, ChunksAnchor::Center, 1.0, 2.0)
+            .with_material(ObstacleMaterial::Absorbing),
+    );
 
     // Some spawn points, for your pleasure <3
     commands.spawn(SpawnPointBundle::new_at(-50.0, 150.0));
 
     commands.spawn(SpawnPointBundle::new_at(-150.0, 0.0));
+
+    // Spawners to test out the loot
+    commands.spawn(
+        ItemSpawnerBundle::new_at(LootTable::Armory, -120.0, -150.0)
+            .with_respawn_time(Duration::from_secs(3)),
+    );
+    commands.spawn(
+        ItemSpawnerBundle::new_at(LootTable::Medical, -180.0, -150.0)
+            .with_respawn_time(Duration::from_secs(3)),
+    );
+    commands.spawn(
+        ItemSpawnerBundle::new_at(LootTable::Supplies, -240.0, -150.0)
+            .with_respawn_time(Duration::from_secs(3)),
+    );
+    commands.spawn(
+        ItemSpawnerBundle::new_at(LootTable::Arcane, -300.0, -150.0)
+            .with_respawn_time(Duration::from_secs(3)),
+    );
 }
 
 /// Set up a lighter, stable scene. Considered default.
@@ -223,22 +258,23 @@ pub fn setup_main(mut commands: Commands) {
         Chunks::Blocks(1.5),
     ));
 
-    // LITTLE BLOCKS SURROUNDING THE TOP BLOCK
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        Chunks::Screen(-0.55 / 2.),
-        1.0,
-        1.,
-        2.,
-    ));
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        Chunks::Screen(0.55 / 2.).right(),
-        1.0,
-        // todo:physics allow negative scale to work like something from inside the usual dimension
-        1., // -1.,
-        2.,
-    ));
+    // LITTLE GLASS BLOCKS SURROUNDING THE TOP BLOCK
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(Chunks::Screen(-0.55 / 2.), 1.0, 1., 2.)
+            .with_material(ObstacleMaterial::Glass),
+    );
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(
+            Chunks::Screen(0.55 / 2.).right(),
+            1.0,
+            // todo:physics allow negative scale to work like something from inside the usual dimension
+            1., // -1.,
+            2.,
+        )
+        .with_material(ObstacleMaterial::Glass),
+    );
 
-    // ROTATED WEDGES SURROUNDING THE TOP BLOCK
+    // ROTATED BOUNCY WEDGES SURROUNDING THE TOP BLOCK
     let wedge_len = (1.5f32.powi(2) + 1.).sqrt();
     commands.spawn(
         RectangularObstacleBundle::new_chunk(
@@ -248,7 +284,7 @@ pub fn setup_main(mut commands: Commands) {
             wedge_len,
         )
         .with_rotation(-(1. / wedge_len).asin())
-        .with_color(Color::ORANGE_RED * 3.),
+        .with_material(ObstacleMaterial::Bouncy),
     );
     commands.spawn(
         RectangularObstacleBundle::new_chunk(
@@ -258,32 +294,31 @@ pub fn setup_main(mut commands: Commands) {
             wedge_len,
         )
         .with_rotation(PI + (1. / wedge_len).asin())
-        .with_color(Color::ORANGE_RED * 3.),
+        .with_material(ObstacleMaterial::Bouncy),
     );
 
-    // BOTTOM BLOCK
+    // BOTTOM ICE BLOCK
     let bottom_block_y_start: Chunks = -Chunks::Screen(0.5) + 1.75;
     let bottom_block_len: Chunks = Chunks::Screen(0.5) - 3.;
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        ChunksAnchor::Center,
-        bottom_block_y_start,
-        2.5,
-        bottom_block_len,
-    ));
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(
+            ChunksAnchor::Center,
+            bottom_block_y_start,
+            2.5,
+            bottom_block_len,
+        )
+        .with_material(ObstacleMaterial::Ice),
+    );
 
-    // SIDE BLOCKS
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        Chunks::Screen(-0.5),
-        -2.,
-        1.,
-        3.,
-    ));
-    commands.spawn(RectangularObstacleBundle::new_chunk(
-        Chunks::Screen(0.5).right(),
-        -2.,
-        1.,
-        3.,
-    ));
+    // SIDE PENETRABLE BLOCKS
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(Chunks::Screen(-0.5), -2., 1., 3.)
+            .with_material(ObstacleMaterial::Penetrable),
+    );
+    commands.spawn(
+        RectangularObstacleBundle::new_chunk(Chunks::Screen(0.5).right(), -2., 1., 3.)
+            .with_material(ObstacleMaterial::Penetrable),
+    );
 
     // bottom-left spawn point
     commands.spawn(
@@ -320,6 +355,33 @@ pub fn setup_main(mut commands: Commands) {
         )
         .with_rotation(PI * 3. / 4.),
     );
+
+    // gun spawner in the pass between the top and the bottom blocks
+    commands.spawn(ItemSpawnerBundle::new_at(
+        LootTable::Armory,
+        0.,
+        Chunks::Blocks(1.).to_px(),
+    ));
+
+    // power-up spawner at the top, above the top block
+    commands.spawn(
+        ItemSpawnerBundle::new_at(LootTable::Arcane, 0., (Chunks::Screen(0.5) - 1.5).to_px())
+            .with_respawn_time(Duration::from_secs(30)),
+    );
+
+    // bottom-left health spawner
+    commands.spawn(ItemSpawnerBundle::new_at(
+        LootTable::Medical,
+        (Chunks::Screen(-0.5) + 1.).to_px(),
+        (Chunks::Screen(-0.5) + 1.).to_px(),
+    ));
+
+    // bottom-right supply spawner
+    commands.spawn(ItemSpawnerBundle::new_at(
+        LootTable::Supplies,
+        (Chunks::Screen(0.5) - 1.).to_px(),
+        (Chunks::Screen(-0.5) + 1.).to_px(),
+    ));
 }
 
 /// Set up common stuff attributable to all levels.
@@ -409,8 +471,10 @@ pub fn handle_player_respawning(
         /* temporary */ &mut Sprite,
     )>,
     player_registry: Res<PlayerRegistry>,
-    local_player: Res<LocalPlayerHandle>,
+    local_players: Res<LocalPlayers>,
     mut random_state: ResMut<EntropyGenerator>,
+    mut sound_cues: EventWriter<SoundCue>,
+    mut stat_events: EventWriter<StatEvent>,
 ) {
     for (mut spawn_point, transform, mut sprite) in spawn_point_query.iter_mut() {
         if spawn_point.is_free()
@@ -424,23 +488,30 @@ pub fn handle_player_respawning(
         let player_handle = spawn_point
             .occupant_handle
             .expect("Spawn beacon is occupied, but occupant handle is `None`? Preposterous!");
-        let player_entity = PlayerCharacterBundle::new(
-            *transform,
-            *player_registry.get(player_handle).expect("Spawn beacon is occupied, but occupant handle is not registered as a player? Preposterous!").team,
-            player_handle,
-        )
-        .spawn_with_equipment(
-            &mut commands,
-            random_state.fork(),
-            vec![GunPreset::random(&mut random_state.0)],
-        )[0];
-
-        if player_handle == local_player.0 {
-            // this is with assumption that if we're resurrecting the local player, no other must exist.
+        // the player has left mid-match, keep the beacon for someone else
+        if player_registry
+            .get(player_handle)
+            .is_some_and(|player| player.disconnected)
+        {
+            spawn_point.free();
+            continue;
+        }
+        let player = player_registry.get(player_handle).expect("Spawn beacon is occupied, but occupant handle is not registered as a player? Preposterous!");
+        let loadout = player.class.loadout(&mut random_state);
+        let player_entity =
+            PlayerCharacterBundle::new(*transform, *player.team, player_handle, player.class)
+                .spawn_with_equipment(&mut commands, random_state.fork(), loadout)[0];
+
+        if local_players.contains(player_handle) {
+            // this is with assumption that if we're resurrecting a local player, no other of theirs must exist.
             commands.entity(player_entity).insert(LocalPlayer);
         }
 
         spawn_point.free();
+        sound_cues.send(SoundCue::Respawn);
+        stat_events.send(StatEvent::Spawned {
+            player: player_handle,
+        });
         #[cfg(feature = "diagnostic")]
         {
             sprite.color = Color::TOMATO * 2.;
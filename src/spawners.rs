@@ -0,0 +1,469 @@
+use crate::characters::{PlayerControlled, CHARACTER_SIZE};
+use crate::controls::CharacterActionInput;
+use crate::deployables::Deployable;
+use crate::guns::{Equipped, Gun, GunBundle, GunPreset, LastUnequippedAt};
+use crate::health::{Dying, Health, HitPoints};
+use crate::match_stats::StatEvent;
+use crate::modifiers::{ModifierStack, PowerUp};
+use crate::network::MAINTAINED_FPS_F64;
+use crate::physics::{
+    popular_collider, CollisionLayer, KinematicsBundle, OngoingCollisions, RigidBody, Sensor,
+};
+use crate::ui::fonts;
+use crate::EntropyGenerator;
+use bevy::hierarchy::{BuildChildren, Children};
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{
+    default, Added, AssetServer, Bundle, Color, Commands, Component, Entity, EventWriter,
+    FromReflect, Query, Reflect, Res, ResMut, Sprite, SpriteBundle, Text, Text2dBundle,
+    TextAlignment, TextStyle, Timer, TimerMode, Transform, With, Without,
+};
+use rand::Rng;
+use std::time::Duration;
+
+/// Standard time it takes a spawner to produce a new item after the previous one was taken.
+pub const DEFAULT_ITEM_RESPAWN_TIME: Duration = Duration::from_secs(15);
+
+/// Side of the square pad marking a spawner on the arena.
+const SPAWNER_SIZE: f32 = CHARACTER_SIZE;
+/// Side of a pickup's sprite and collider.
+const PICKUP_SIZE: f32 = CHARACTER_SIZE * 0.4;
+/// Items lie on top of the spawner pad, but under the characters' guns.
+const ITEM_Z_LAYER: f32 = 1.0;
+/// Font size of the countdown shown over an empty spawner.
+const COUNTDOWN_FONT_SIZE: f32 = 20.0;
+
+/// Amount of hit points restored by a small health pack.
+pub const SMALL_HEALTH_PACK: HitPoints = 25.0;
+/// Amount of hit points restored by a large health pack.
+pub const LARGE_HEALTH_PACK: HitPoints = 50.0;
+/// Amount of armor points given by an armor plate.
+pub const ARMOR_PLATE: HitPoints = 35.0;
+
+/// Kind of a consumable item, taking effect on the character as soon as it is touched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, FromReflect)]
+pub enum PickupKind {
+    /// Restores some hit points, up to the character's maximum.
+    Health(HitPoints),
+    /// Gives some armor points, which soak up damage before health does.
+    Armor(HitPoints),
+    /// Refills the magazines of all guns the character holds.
+    #[default]
+    Ammo,
+    /// Puts a temporary power-up into effect.
+    PowerUp(PowerUp),
+}
+
+impl PickupKind {
+    /// Get the color the pickup is painted in, so that its kind can be recognized at a glance.
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Health(_) => Color::rgb(0.2 * 4., 0.8 * 4., 0.3 * 4.),
+            PickupKind::Armor(_) => Color::rgb(0.3 * 4., 0.5 * 4., 0.9 * 4.),
+            PickupKind::Ammo => Color::rgb(0.9 * 4., 0.7 * 4., 0.2 * 4.),
+            PickupKind::PowerUp(power_up) => match power_up {
+                PowerUp::DamageBoost => Color::rgb(1.0 * 4., 0.2 * 4., 0.2 * 4.),
+                PowerUp::SpeedBoost => Color::rgb(0.2 * 4., 0.9 * 4., 0.9 * 4.),
+                PowerUp::Invisibility => Color::rgba(0.8 * 4., 0.8 * 4., 0.8 * 4., 0.3),
+                PowerUp::MultiShot => Color::rgb(0.8 * 4., 0.3 * 4., 0.9 * 4.),
+            },
+        }
+    }
+
+    /// Apply the pickup's effect to a character. Returns `false` if it would have been wasted and should stay in place.
+    pub fn apply(
+        &self,
+        health: &mut Health,
+        modifiers: &mut ModifierStack,
+        children: Option<&Children>,
+        query_guns: &mut Query<&mut Gun, With<Equipped>>,
+    ) -> bool {
+        match *self {
+            PickupKind::Health(hp) => health.heal(hp),
+            PickupKind::Armor(armor) => health.add_armor(armor),
+            PickupKind::Ammo => {
+                let mut refilled = false;
+                let mut guns = query_guns.iter_many_mut(children.into_iter().flatten());
+                while let Some(mut gun) = guns.fetch_next() {
+                    refilled |= gun.refill_ammo();
+                }
+                refilled
+            }
+            PickupKind::PowerUp(power_up) => {
+                modifiers.add(power_up);
+                true
+            }
+        }
+    }
+}
+
+/// Anything a spawner may produce when its timer runs out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpawnerLoot {
+    Gun(GunPreset),
+    Pickup(PickupKind),
+}
+
+/// Selection of weighted tables spawners draw their loot from. The tables are found via a look-up by a value of this enum.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Reflect, FromReflect)]
+pub enum LootTable {
+    /// Guns, the more exotic the rarer.
+    #[default]
+    Armory,
+    /// Health packs and armor.
+    Medical,
+    /// Ammunition and the occasional armor plate.
+    Supplies,
+    /// Power-ups, each about as likely as the other.
+    Arcane,
+}
+
+const ARMORY: &[(SpawnerLoot, u32)] = &[
+    (SpawnerLoot::Gun(GunPreset::Imprecise), 4),
+    (SpawnerLoot::Gun(GunPreset::Scattershot), 3),
+    (SpawnerLoot::Gun(GunPreset::Typhoon), 2),
+    (SpawnerLoot::Gun(GunPreset::RailGun), 2),
+    (SpawnerLoot::Gun(GunPreset::LaserGun), 1),
+    (SpawnerLoot::Gun(GunPreset::MinePlanter), 1),
+    (SpawnerLoot::Gun(GunPreset::RocketLauncher), 1),
+    (SpawnerLoot::Gun(GunPreset::NanoSwarmLauncher), 2),
+    (SpawnerLoot::Gun(GunPreset::Boomerang), 2),
+];
+
+const MEDICAL: &[(SpawnerLoot, u32)] = &[
+    (
+        SpawnerLoot::Pickup(PickupKind::Health(SMALL_HEALTH_PACK)),
+        5,
+    ),
+    (
+        SpawnerLoot::Pickup(PickupKind::Health(LARGE_HEALTH_PACK)),
+        2,
+    ),
+    (SpawnerLoot::Pickup(PickupKind::Armor(ARMOR_PLATE)), 3),
+];
+
+const SUPPLIES: &[(SpawnerLoot, u32)] = &[
+    (SpawnerLoot::Pickup(PickupKind::Ammo), 5),
+    (SpawnerLoot::Pickup(PickupKind::Armor(ARMOR_PLATE)), 2),
+];
+
+const ARCANE: &[(SpawnerLoot, u32)] = &[
+    (
+        SpawnerLoot::Pickup(PickupKind::PowerUp(PowerUp::DamageBoost)),
+        1,
+    ),
+    (
+        SpawnerLoot::Pickup(PickupKind::PowerUp(PowerUp::SpeedBoost)),
+        1,
+    ),
+    (
+        SpawnerLoot::Pickup(PickupKind::PowerUp(PowerUp::Invisibility)),
+        1,
+    ),
+    (
+        SpawnerLoot::Pickup(PickupKind::PowerUp(PowerUp::MultiShot)),
+        1,
+    ),
+];
+
+impl LootTable {
+    /// Map of a loot table to its entries and their weights.
+    #[inline]
+    pub const fn entries(&self) -> &'static [(SpawnerLoot, u32)] {
+        match self {
+            LootTable::Armory => ARMORY,
+            LootTable::Medical => MEDICAL,
+            LootTable::Supplies => SUPPLIES,
+            LootTable::Arcane => ARCANE,
+        }
+    }
+
+    /// Pick a random entry from the table, respecting the weights.
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> SpawnerLoot {
+        let entries = self.entries();
+        let total_weight: u32 = entries.iter().map(|&(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0..total_weight);
+        for &(loot, weight) in entries {
+            if roll < weight {
+                return loot;
+            }
+            roll -= weight;
+        }
+        // the roll never gets past the total weight, but the compiler doesn't know that
+        entries[entries.len() - 1].0
+    }
+
+    /// Get the color of the spawner pad, hinting at what it produces.
+    pub fn color(&self) -> Color {
+        match self {
+            LootTable::Armory => Color::SILVER.with_a(0.2),
+            LootTable::Medical => PickupKind::Health(0.).color().with_a(0.2),
+            LootTable::Supplies => PickupKind::Ammo.color().with_a(0.2),
+            LootTable::Arcane => Color::PURPLE.with_a(0.2),
+        }
+    }
+}
+
+/// Level-placed entity that periodically produces an item from its loot table.
+/// A new item is only produced once the previous one has been taken and the respawn timer has run out.
+#[derive(Component, Debug, Reflect, FromReflect)]
+pub struct ItemSpawner {
+    pub loot_table: LootTable,
+    pub timeout: Timer,
+    /// The item lying on the spawner and waiting to be picked up, if any.
+    pub item: Option<Entity>,
+}
+
+impl Default for ItemSpawner {
+    fn default() -> Self {
+        Self::new(LootTable::default(), DEFAULT_ITEM_RESPAWN_TIME)
+    }
+}
+
+impl ItemSpawner {
+    pub fn new(loot_table: LootTable, respawn_time: Duration) -> Self {
+        let mut timeout = Timer::new(respawn_time, TimerMode::Once);
+        // The first item is there right as the match starts
+        timeout.tick(respawn_time);
+        Self {
+            loot_table,
+            timeout,
+            item: None,
+        }
+    }
+
+    /// Get the seconds left until the next item appears, or `None` if an item is already there.
+    pub fn remaining_secs(&self) -> Option<f32> {
+        if self.item.is_some() || self.timeout.finished() {
+            None
+        } else {
+            Some(self.timeout.remaining_secs())
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct ItemSpawnerBundle {
+    pub spawner: ItemSpawner,
+    #[bundle]
+    pub sprite_bundle: SpriteBundle,
+}
+
+impl ItemSpawnerBundle {
+    pub fn new(loot_table: LootTable, transform: Transform) -> Self {
+        Self {
+            spawner: ItemSpawner::new(loot_table, DEFAULT_ITEM_RESPAWN_TIME),
+            sprite_bundle: SpriteBundle {
+                transform,
+                sprite: Sprite {
+                    color: loot_table.color(),
+                    custom_size: Some(Vec2::new(SPAWNER_SIZE, SPAWNER_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        }
+    }
+
+    pub fn new_at(loot_table: LootTable, x: f32, y: f32) -> Self {
+        Self::new(
+            loot_table,
+            Transform::from_translation(Vec3::new(x, y, 0.0)),
+        )
+    }
+
+    pub fn with_respawn_time(mut self, respawn_time: Duration) -> Self {
+        self.spawner = ItemSpawner::new(self.spawner.loot_table, respawn_time);
+        self
+    }
+}
+
+/// Component of a consumable item lying on the ground.
+#[derive(Component, Debug, Default, Reflect, FromReflect)]
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+/// Collection of components making up a pickup entity.
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pub pickup: Pickup,
+    #[bundle]
+    pub kinematics: KinematicsBundle,
+    pub sensor: Sensor,
+    pub collisions: OngoingCollisions,
+    #[bundle]
+    pub sprite_bundle: SpriteBundle,
+}
+
+impl PickupBundle {
+    pub fn new(kind: PickupKind, transform: Transform) -> Self {
+        Self {
+            pickup: Pickup { kind },
+            kinematics: KinematicsBundle::new(
+                popular_collider::square(PICKUP_SIZE),
+                &[CollisionLayer::Gear],
+                &[CollisionLayer::Character],
+            )
+            .with_rigidbody_type(RigidBody::Fixed),
+            sensor: Sensor,
+            collisions: OngoingCollisions::default(),
+            sprite_bundle: SpriteBundle {
+                transform,
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::new(PICKUP_SIZE, PICKUP_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        }
+    }
+}
+
+/// System to produce new items out of spawners whose previous item has been taken and whose timers have run out.
+pub fn handle_item_spawning(
+    mut commands: Commands,
+    mut query_spawners: Query<(&mut ItemSpawner, &Transform)>,
+    // items still lying where they were spawned; picked up guns are equipped or were thrown, pickups get despawned
+    query_untouched_items: Query<(), (Without<Equipped>, Without<LastUnequippedAt>)>,
+    mut random_state: ResMut<EntropyGenerator>,
+) {
+    for (mut spawner, transform) in query_spawners.iter_mut() {
+        if let Some(item) = spawner.item {
+            if query_untouched_items.contains(item) {
+                continue;
+            }
+            // The item's been taken, start counting down to the next one
+            spawner.item = None;
+            spawner.timeout.reset();
+        }
+
+        // GGRS fixed ticks
+        if !spawner
+            .timeout
+            .tick(Duration::from_secs_f64(1. / MAINTAINED_FPS_F64))
+            .finished()
+        {
+            continue;
+        }
+
+        let item_transform =
+            Transform::from_translation(transform.translation.truncate().extend(ITEM_Z_LAYER));
+        let item = match spawner.loot_table.roll(&mut random_state.0) {
+            SpawnerLoot::Gun(preset) => commands
+                .spawn(GunBundle::new(
+                    preset,
+                    Some(item_transform),
+                    random_state.fork(),
+                ))
+                .id(),
+            SpawnerLoot::Pickup(kind) => {
+                commands.spawn(PickupBundle::new(kind, item_transform)).id()
+            }
+        };
+        spawner.item = Some(item);
+    }
+}
+
+/// System to apply the effects of pickups to the characters touching them, and to remove the pickups that were used.
+pub fn handle_pickup_collection(
+    mut commands: Commands,
+    query_pickups: Query<(&Pickup, &OngoingCollisions, Entity)>,
+    mut query_characters: Query<
+        (
+            &mut Health,
+            &mut ModifierStack,
+            Option<&Children>,
+            Option<&PlayerControlled>,
+            Entity,
+        ),
+        // turrets act like characters, but have no use for what's lying around
+        (
+            With<CharacterActionInput>,
+            Without<Deployable>,
+            Without<Dying>,
+        ),
+    >,
+    mut query_guns: Query<&mut Gun, With<Equipped>>,
+    mut stat_events: EventWriter<StatEvent>,
+) {
+    for (pickup, collisions, pickup_entity) in query_pickups.iter() {
+        if collisions.is_empty() {
+            continue;
+        }
+
+        for (mut health, mut modifiers, children, maybe_player, char_entity) in
+            query_characters.iter_mut()
+        {
+            if !collisions.contains(&char_entity) {
+                continue;
+            }
+
+            if pickup
+                .kind
+                .apply(&mut health, &mut modifiers, children, &mut query_guns)
+            {
+                commands.entity(pickup_entity).despawn();
+                if let Some(player) = maybe_player {
+                    stat_events.send(StatEvent::PickedUp {
+                        player: player.handle,
+                    });
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Marker of the text counting down the time until a spawner produces its next item.
+#[derive(Component, Debug)]
+pub struct SpawnerCountdownDisplay;
+
+/// System to attach a countdown display to every newly placed spawner.
+pub fn setup_spawner_countdown_display(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query_spawners: Query<Entity, Added<ItemSpawner>>,
+) {
+    for spawner_entity in query_spawners.iter() {
+        commands.entity(spawner_entity).with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: fonts::load(&asset_server, fonts::SPACERUNNER),
+                            font_size: COUNTDOWN_FONT_SIZE,
+                            color: Color::WHITE.with_a(0.6),
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_translation(Vec3::Z * ITEM_Z_LAYER),
+                    ..default()
+                },
+                SpawnerCountdownDisplay,
+            ));
+        });
+    }
+}
+
+/// System to show the seconds left until an empty spawner produces its next item.
+pub fn handle_spawner_countdown_display(
+    query_spawners: Query<(&ItemSpawner, &Children)>,
+    mut query_displays: Query<&mut Text, With<SpawnerCountdownDisplay>>,
+) {
+    for (spawner, children) in query_spawners.iter() {
+        let countdown = spawner
+            .remaining_secs()
+            .map(|secs| secs.ceil().to_string())
+            .unwrap_or_default();
+
+        let mut displays = query_displays.iter_many_mut(children);
+        while let Some(mut text) = displays.fetch_next() {
+            if text.sections[0].value != countdown {
+                text.sections[0].value = countdown.clone();
+            }
+        }
+    }
+}
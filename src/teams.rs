@@ -1,5 +1,12 @@
-use bevy::prelude::{Component, FromReflect, Reflect};
-use bevy::render::color::Color;
+use crate::characters::PlayerControlled;
+use crate::controls::CharacterActionInput;
+use crate::deployables::Deployable;
+use crate::network::players::PlayerRegistry;
+use crate::network::PlayerHandle;
+use crate::ui::user_settings::UserSettings;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
 
 /// Number of teams is limited by 256.
 pub type TeamNumber = u8;
@@ -9,22 +16,15 @@ pub const PLAYER_DEFAULT_TEAM: TeamNumber = 1;
 /// The default team number of the AI enemies.
 pub const AI_DEFAULT_TEAM: TeamNumber = 9;
 
+/// Turn between the hues of the teams past the palette's own colors, in degrees, for them to stay far apart.
+const GOLDEN_ANGLE_DEGREES: f32 = 137.508;
+/// Colors closer than this to each other are too hard to tell apart for two players to have.
+const MIN_COLOR_DISTANCE: f32 = 0.25;
+
 /// Marker holding the character's (or anything's) allegiance.
 #[derive(Component, Clone, Copy, Debug, Default, Eq, PartialEq, Reflect, FromReflect)]
 pub struct Team(pub TeamNumber);
 
-impl Team {
-    /// Get the color associated with the team.
-    pub fn color(&self) -> Color {
-        team_color(self.0)
-    }
-
-    /// Get the color associated with the team.
-    pub fn safe_color(&self) -> Option<Color> {
-        safe_team_color(self.0)
-    }
-}
-
 impl Into<Team> for TeamNumber {
     fn into(self) -> Team {
         Team(self)
@@ -39,22 +39,216 @@ impl std::ops::Deref for Team {
     }
 }
 
-/// Get the color associated with the team.
-pub fn safe_team_color(team: TeamNumber) -> Option<Color> {
-    match team {
-        1 => Some(Color::CYAN),
-        2 => Some(Color::CRIMSON),
-        3 => Some(Color::LIME_GREEN),
-        4 => Some(Color::GOLD),
-        5 => Some(Color::PURPLE),
-        6 => Some(Color::SEA_GREEN),
-        7 => Some(Color::ORANGE_RED),
-        8 => Some(Color::INDIGO),
-        9 => Some(Color::SILVER),
-        _ => None,
+/// Colors of the classic team palette.
+const CLASSIC_PALETTE: [Color; 9] = [
+    Color::CYAN,
+    Color::CRIMSON,
+    Color::LIME_GREEN,
+    Color::GOLD,
+    Color::PURPLE,
+    Color::SEA_GREEN,
+    Color::ORANGE_RED,
+    Color::INDIGO,
+    Color::SILVER,
+];
+
+/// Colors of the team palette told apart with the common kinds of color blindness.
+const COLOR_BLIND_PALETTE: [Color; 9] = [
+    Color::rgb(0.34, 0.71, 0.91),
+    Color::rgb(0.84, 0.37, 0.0),
+    Color::rgb(0.0, 0.62, 0.45),
+    Color::rgb(0.94, 0.89, 0.26),
+    Color::rgb(0.8, 0.47, 0.65),
+    Color::rgb(0.0, 0.45, 0.7),
+    Color::rgb(0.9, 0.62, 0.0),
+    Color::WHITE,
+    Color::rgb(0.6, 0.6, 0.6),
+];
+
+/// Colors of the high contrast team palette.
+const HIGH_CONTRAST_PALETTE: [Color; 9] = [
+    Color::WHITE,
+    Color::rgb(1.0, 0.6, 0.0),
+    Color::rgb(0.0, 0.6, 1.0),
+    Color::YELLOW,
+    Color::FUCHSIA,
+    Color::CYAN,
+    Color::rgb(1.0, 0.4, 0.4),
+    Color::rgb(0.4, 0.4, 1.0),
+    Color::GRAY,
+];
+
+/// Set of colors the teams are told apart by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TeamPalette {
+    #[default]
+    Classic,
+    /// Colors that stay distinct with any kind of color blindness, after Okabe and Ito.
+    ColorBlind,
+    /// Bright colors far apart in lightness as well as in hue.
+    HighContrast,
+}
+
+impl TeamPalette {
+    /// Human-readable name of the palette.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            TeamPalette::Classic => "Classic",
+            TeamPalette::ColorBlind => "Color-blind",
+            TeamPalette::HighContrast => "High contrast",
+        }
     }
+
+    /// Get the palette to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            TeamPalette::Classic => TeamPalette::ColorBlind,
+            TeamPalette::ColorBlind => TeamPalette::HighContrast,
+            TeamPalette::HighContrast => TeamPalette::Classic,
+        }
+    }
+
+    /// The palette's own colors, starting from [`PLAYER_DEFAULT_TEAM`].
+    const fn colors(&self) -> &'static [Color] {
+        match self {
+            TeamPalette::Classic => &CLASSIC_PALETTE,
+            TeamPalette::ColorBlind => &COLOR_BLIND_PALETTE,
+            TeamPalette::HighContrast => &HIGH_CONTRAST_PALETTE,
+        }
+    }
+
+    /// Get the color of the team. The teams past the palette's own colors get ones spread around the hue wheel.
+    pub fn team_color(&self, team: TeamNumber) -> Color {
+        let index = team.wrapping_sub(PLAYER_DEFAULT_TEAM) as usize;
+        self.colors()
+            .get(index)
+            .copied()
+            .unwrap_or_else(|| Color::hsl((index as f32 * GOLDEN_ANGLE_DEGREES) % 360.0, 0.75, 0.6))
+    }
+}
+
+/// How far apart two colors look, roughly.
+fn color_distance(a: Color, b: Color) -> f32 {
+    let ([a_r, a_g, a_b, _], [b_r, b_g, b_b, _]) = (a.as_rgba_f32(), b.as_rgba_f32());
+    ((a_r - b_r).powi(2) + (a_g - b_g).powi(2) + (a_b - b_b).powi(2)).sqrt()
+}
+
+/// Resource to look up the colors of the teams and of the players with. Everything painted by team goes through it.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct TeamColors {
+    pub palette: TeamPalette,
+    /// Colors of the players who have picked their own and have been let to keep them.
+    pub player_colors: HashMap<PlayerHandle, Color>,
 }
 
-pub fn team_color(team: TeamNumber) -> Color {
-    safe_team_color(team).expect("The team number is out of bounds!")
+impl TeamColors {
+    /// Get the color of the team.
+    pub fn team_color(&self, team: TeamNumber) -> Color {
+        self.palette.team_color(team)
+    }
+
+    /// Get the color of the player: their own if they have one, their team's otherwise.
+    pub fn player_color(&self, handle: PlayerHandle, team: TeamNumber) -> Color {
+        self.player_colors
+            .get(&handle)
+            .copied()
+            .unwrap_or_else(|| self.team_color(team))
+    }
+
+    /// Get the color of a character, be it a player's or not.
+    pub fn character_color(&self, handle: Option<PlayerHandle>, team: TeamNumber) -> Color {
+        match handle {
+            Some(handle) => self.player_color(handle, team),
+            None => self.team_color(team),
+        }
+    }
+
+    /// Let the players keep the colors they have picked, as long as it doesn't muddle who is who.
+    /// Those sharing a team keep the team's color. A color too close to one already taken,
+    /// by a player with a lower handle or by a team whose players have not picked their own, is turned down.
+    /// Every peer comes to the same result, having the same players.
+    pub fn resolve_player_colors(&mut self, players: &PlayerRegistry) {
+        self.player_colors.clear();
+
+        let mut team_sizes = HashMap::<TeamNumber, usize>::default();
+        for player in players.iter() {
+            *team_sizes.entry(player.team.0).or_default() += 1;
+        }
+        let mut taken_colors: Vec<Color> = players
+            .iter()
+            .filter(|player| player.color.is_none())
+            .map(|player| self.team_color(player.team.0))
+            .collect();
+
+        for (handle, player) in players.iter().enumerate() {
+            let Some(color) = player.color else {
+                continue;
+            };
+            if team_sizes[&player.team.0] > 1 {
+                continue;
+            }
+            let is_clashing = taken_colors
+                .iter()
+                .any(|taken| color_distance(color, *taken) < MIN_COLOR_DISTANCE);
+            if !is_clashing {
+                taken_colors.push(color);
+                self.player_colors.insert(handle, color);
+            }
+        }
+    }
+}
+
+/// System to keep the team colors in line with the palette picked and the colors the players have asked for.
+fn handle_team_colors_change(
+    settings: Res<UserSettings>,
+    players: Res<PlayerRegistry>,
+    mut team_colors: ResMut<TeamColors>,
+) {
+    if !(settings.is_changed() || players.is_changed()) {
+        return;
+    }
+    if team_colors.palette != settings.team_palette {
+        team_colors.palette = settings.team_palette;
+    }
+    let mut resolved = team_colors.clone();
+    resolved.resolve_player_colors(&players);
+    if resolved.player_colors != team_colors.player_colors {
+        team_colors.player_colors = resolved.player_colors;
+    }
+}
+
+/// System to paint the characters and their deployables in their colors, as they are spawned and as the colors change.
+fn handle_character_paint_change(
+    team_colors: Res<TeamColors>,
+    mut character_query: Query<
+        (
+            Ref<Team>,
+            Option<&PlayerControlled>,
+            Option<&Deployable>,
+            &mut Sprite,
+        ),
+        Or<(With<CharacterActionInput>, With<Deployable>)>,
+    >,
+) {
+    for (team, maybe_player, maybe_deployable, mut sprite) in character_query.iter_mut() {
+        if !(team_colors.is_changed() || team.is_changed()) {
+            continue;
+        }
+        let handle = maybe_player
+            .map(|player| player.handle)
+            .or(maybe_deployable.and_then(|deployable| deployable.owner));
+        let color = team_colors.character_color(handle, team.0);
+        // the alpha is left to the invisibility
+        sprite.color = color.with_a(sprite.color.a());
+    }
+}
+
+/// Plugin of the colors the teams and the players are painted in.
+pub struct TeamColorsPlugin;
+impl Plugin for TeamColorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeamColors>()
+            .add_system(handle_team_colors_change)
+            .add_system(handle_character_paint_change.after(handle_team_colors_change));
+    }
 }
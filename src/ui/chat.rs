@@ -1,6 +1,9 @@
-use crate::network::peers::PeerMessage;
+use crate::network::moderation::MAX_CHAT_MESSAGE_LENGTH;
+use crate::network::peers::{handle_chat_message_broadcast, handle_direct_message_sending};
 use crate::network::players::PlayerRegistry;
 use crate::network::PlayerHandle;
+use crate::teams::TeamColors;
+use crate::ui::chat::commands::{handle_chat_messaging, handle_chat_session_commands, ChatCommand};
 use crate::ui::focus::Focus;
 use crate::ui::input_consumption::{
     ActiveInputConsumerLayers, CHAT_INPUT_LAYER, CHAT_OPEN_INPUT_LAYER,
@@ -9,8 +12,11 @@ use crate::ui::text_input::TextInput;
 use crate::ui::{fonts, menu_builder};
 use crate::GameState;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+pub mod commands;
+
 const MAX_CHAT_MESSAGES: usize = 16;
 const CHAT_FONT_SIZE: f32 = 14.0;
 
@@ -21,11 +27,54 @@ const FADEOUT_THRESHOLD: f32 = 0.01;
 // this shit will stay here at least until bevy 0.11
 const CHAT_WIDTH: f32 = 0.35 * 800.;
 
+/// Messages older than this are shown with how long ago they were sent, such as those from the history.
+const OLD_MESSAGE_AGE_SECS: f64 = 60.0;
+
+/// Whom a chat message is meant for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatChannel {
+    /// Everyone in the session.
+    #[default]
+    All,
+    /// Only the peers whose players are on the sender's team.
+    Team,
+    /// Only the one peer it's addressed to.
+    Whisper,
+}
+
+impl ChatChannel {
+    /// Get the tag shown before the messages of the channel, if it has any.
+    pub const fn tag(&self) -> Option<&'static str> {
+        match self {
+            ChatChannel::All => None,
+            ChatChannel::Team => Some("[Team] "),
+            ChatChannel::Whisper => Some("[Whisper] "),
+        }
+    }
+}
+
+/// Get the current time in seconds since the Unix epoch, which, unlike the app's time, means the same on every machine.
+pub fn unix_timestamp() -> f64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |duration| duration.as_secs_f64())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::js_interop::get_unix_time_from_js()
+    }
+}
+
 #[derive(Component, Clone, Debug)]
 pub struct ChatMessage {
     pub message: String,
     pub player_handles: Vec<PlayerHandle>,
-    // timestamp?
+    pub channel: ChatChannel,
+    /// Seconds since the Unix epoch of when the message came in, by this machine's clock.
+    /// Only the messages replayed from the history carry the time they were sent at instead.
+    pub timestamp: f64,
 }
 
 impl ChatMessage {
@@ -33,9 +82,26 @@ impl ChatMessage {
         Self {
             message: message.into(),
             player_handles: vec![],
+            channel: ChatChannel::All,
+            timestamp: unix_timestamp(),
         }
     }
 
+    pub fn with_handles(mut self, handles: Vec<PlayerHandle>) -> Self {
+        self.player_handles = handles;
+        self
+    }
+
+    pub fn in_channel(mut self, channel: ChatChannel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn at(mut self, timestamp: f64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
     pub fn by(mut self, whom: String) -> Self {
         self.message = format!("{}: {}", whom, self.message);
         self
@@ -137,7 +203,8 @@ fn setup_chat_display(mut commands: Commands, asset_server: Res<AssetServer>) {
                     text: Text::from_section("Chat: ", input_style.clone()),
                     ..default()
                 },
-                TextInput::new("".into(), input_style, Some("Send them a message".into())),
+                TextInput::new("".into(), input_style, Some("Send them a message".into()))
+                    .with_max_symbols(MAX_CHAT_MESSAGE_LENGTH),
                 Focus::<TextInput>::None,
                 CHAT_INPUT_LAYER,
                 ChatTypingDisplay,
@@ -164,6 +231,7 @@ fn handle_new_chat_messages(
     asset_server: Res<AssetServer>,
     mut new_messages: EventReader<ChatMessage>,
     players: Res<PlayerRegistry>,
+    team_colors: Res<TeamColors>,
     chat_display_query: Query<(Entity, Option<&Children>), With<ChatMessagesDisplay>>,
 ) {
     if new_messages.is_empty() {
@@ -187,43 +255,58 @@ fn handle_new_chat_messages(
         color: menu_builder::DEFAULT_TEXT_COLOR.with_a(0.8),
     };
 
+    let now = unix_timestamp();
+
     for message in new_messages.iter() {
+        let mut texts = vec![];
+        let age_secs = now - message.timestamp;
+        if age_secs > OLD_MESSAGE_AGE_SECS {
+            texts.push(TextSection::new(
+                format!("[{} min ago] ", (age_secs / 60.0) as u32),
+                text_style.clone(),
+            ));
+        }
+        if let Some(tag) = message.channel.tag() {
+            texts.push(TextSection::new(tag, you_style.clone()));
+        }
+
         // Parse the chat message in case it contains any players handles, in which case we want to prettify them
-        let texts = message
-            .separate_handles()
-            .iter()
-            .enumerate()
-            .map(|(i, piece)| {
-                // `ChatMessage`'s `separate_handles` leaves player handles at odd indices
-                if i % 2 == 1 {
-                    match piece.as_str() {
-                        "You" => TextSection::new("You", you_style.clone()),
-                        _ => {
-                            let (player_name, name_color) = piece
-                                .parse::<usize>()
-                                .ok()
-                                .and_then(|id| players.get(id))
-                                .map(|data| {
-                                    (
-                                        data.name.clone(),
-                                        data.team.safe_color().unwrap_or(you_style.color),
-                                    )
-                                })
-                                .unwrap_or(("[unknown]".to_string(), you_style.color));
-                            TextSection::new(
-                                player_name,
-                                TextStyle {
-                                    color: name_color,
-                                    ..you_style.clone()
-                                },
-                            )
+        texts.extend(
+            message
+                .separate_handles()
+                .iter()
+                .enumerate()
+                .map(|(i, piece)| {
+                    // `ChatMessage`'s `separate_handles` leaves player handles at odd indices
+                    if i % 2 == 1 {
+                        match piece.as_str() {
+                            "You" => TextSection::new("You", you_style.clone()),
+                            _ => {
+                                let (player_name, name_color) = piece
+                                    .parse::<usize>()
+                                    .ok()
+                                    .and_then(|id| Some((id, players.get(id)?)))
+                                    .map(|(id, data)| {
+                                        (
+                                            data.name.clone(),
+                                            team_colors.player_color(id, data.team.0),
+                                        )
+                                    })
+                                    .unwrap_or(("[unknown]".to_string(), you_style.color));
+                                TextSection::new(
+                                    player_name,
+                                    TextStyle {
+                                        color: name_color,
+                                        ..you_style.clone()
+                                    },
+                                )
+                            }
                         }
+                    } else {
+                        TextSection::new(piece, text_style.clone())
                     }
-                } else {
-                    TextSection::new(piece, text_style.clone())
-                }
-            })
-            .collect::<Vec<_>>();
+                }),
+        );
         let text = Text::from_sections(texts);
 
         if chat_children
@@ -348,8 +431,7 @@ fn handle_chat_sending(
         Or<(With<ChatDisplayBackground>, With<ChatTypingDisplay>)>,
     >,
     mut chat_fade: ResMut<ChatIsFading>,
-    mut messenger: EventWriter<ChatMessage>,
-    mut broadcaster: EventWriter<PeerMessage>,
+    mut commander: EventWriter<ChatCommand>,
 ) {
     if input_consumers.is_layer_active(&CHAT_INPUT_LAYER) {
         let return_pressed = keyboard.just_pressed(KeyCode::Return);
@@ -364,10 +446,7 @@ fn handle_chat_sending(
                 if return_pressed {
                     if let Some(message) = text.sections.last() {
                         if !message.value.is_empty() {
-                            let message = message.value.trim_end().to_string();
-                            messenger
-                                .send(ChatMessage::message(message.clone()).by("{You}".into()));
-                            broadcaster.send(PeerMessage::Chat { message });
+                            commander.send(ChatCommand::parse(message.value.trim_end()));
                             input.reset_text();
                         }
                     }
@@ -415,12 +494,27 @@ pub struct ChatPlugin;
 impl Plugin for ChatPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<ChatMessage>()
+            .add_event::<ChatCommand>()
             .insert_resource(ChatIsFading { is_fading: true })
             .add_system(setup_chat_display.in_schedule(OnExit(GameState::MainMenu)))
             // despawn_node::<ChatDisplay> -- handled by despawn_everything
             .add_system(handle_new_chat_messages.run_if(not(in_state(GameState::MainMenu))))
             .add_system(handle_chat_opening.run_if(not(in_state(GameState::MainMenu))))
             .add_system(handle_chat_sending.run_if(not(in_state(GameState::MainMenu))))
+            .add_system(
+                handle_chat_messaging
+                    .after(handle_chat_sending)
+                    .before(handle_chat_message_broadcast)
+                    .before(handle_direct_message_sending)
+                    .run_if(not(in_state(GameState::MainMenu))),
+            )
+            .add_system(
+                handle_chat_session_commands
+                    .after(handle_chat_sending)
+                    .before(handle_chat_message_broadcast)
+                    .before(handle_direct_message_sending)
+                    .run_if(not(in_state(GameState::MainMenu))),
+            )
             .add_system(handle_chat_message_fadeout.run_if(not(in_state(GameState::MainMenu))));
     }
 }
@@ -0,0 +1,385 @@
+//! Slash commands typed into the chat, such as `/team` or `/w`, and what comes of them.
+
+use crate::network::lobby::{is_host, LobbyPeers, LocalLobbyStatus};
+use crate::network::peers::{
+    ChatHistory, ChatRecord, DirectPeerMessage, KickedPeers, MutedPeers, PeerHandles, PeerMessage,
+    PeerNames,
+};
+use crate::network::players::PlayerRegistry;
+use crate::network::session::LocalPlayers;
+use crate::network::socket::SpaceballSocket;
+use crate::network::PeerId;
+use crate::teams::TeamNumber;
+use crate::ui::chat::{unix_timestamp, ChatChannel, ChatMessage};
+use crate::ui::user_settings::UserSettings;
+use crate::{GameState, LimboState, MenuState};
+use bevy::prelude::*;
+
+const HELP_MESSAGE: &str = "Commands: /team <message>, /w <name> <message>, /mute <name>, /kick <name> (host only), /rematch";
+
+/// What the player has typed into the chat, made sense of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatCommand {
+    /// Message to everyone.
+    Say(String),
+    /// Message to the teammates only: `/team <message>`.
+    Team(String),
+    /// Private message: `/w <name> <message>`.
+    /// The name is told apart from the message only against the names of the peers, as the names may have spaces.
+    Whisper(String),
+    /// Mute or unmute a peer on this machine: `/mute <name>`.
+    Mute(String),
+    /// Have a peer leave the session, if we are the host: `/kick <name>`.
+    Kick(String),
+    /// Call for another match once this one is over: `/rematch`.
+    Rematch,
+    /// List the commands: `/help`.
+    Help,
+    /// Anything else starting with a slash.
+    Unknown(String),
+}
+
+impl ChatCommand {
+    /// Make sense of the chat input. Anything not starting with a slash is just a message to everyone.
+    pub fn parse(input: &str) -> Self {
+        let Some(command) = input.strip_prefix('/') else {
+            return ChatCommand::Say(input.to_string());
+        };
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let argument = argument.trim().to_string();
+        match name.to_lowercase().as_str() {
+            "t" | "team" => ChatCommand::Team(argument),
+            "w" | "whisper" | "msg" => ChatCommand::Whisper(argument),
+            "mute" => ChatCommand::Mute(argument),
+            "kick" => ChatCommand::Kick(argument),
+            "rematch" => ChatCommand::Rematch,
+            "help" | "?" => ChatCommand::Help,
+            _ => ChatCommand::Unknown(name.to_string()),
+        }
+    }
+}
+
+/// Find the peer whose name the text starts with, and the rest of the text after the name.
+/// The longest name wins, should some names start alike.
+fn split_peer_name<'a>(text: &'a str, peer_names: &PeerNames) -> Option<(PeerId, &'a str)> {
+    peer_names
+        .map
+        .iter()
+        .filter(|(_, name)| !name.is_empty())
+        .filter_map(|(id, name)| {
+            let head = text.get(..name.len())?;
+            let rest = &text[name.len()..];
+            let is_whole_word = rest.is_empty() || rest.starts_with(char::is_whitespace);
+            (head.eq_ignore_ascii_case(name) && is_whole_word).then_some((*id, name.len(), rest))
+        })
+        .max_by_key(|(_, name_len, _)| *name_len)
+        .map(|(id, _, rest)| (id, rest.trim_start()))
+}
+
+/// Get the peers with a player on the same team as any of the players on this machine.
+fn teammates(
+    game_state: &GameState,
+    players: &PlayerRegistry,
+    peer_handles: &PeerHandles,
+    local_players: Option<&LocalPlayers>,
+    local_status: &LocalLobbyStatus,
+    lobby_peers: &LobbyPeers,
+) -> Vec<PeerId> {
+    if *game_state == GameState::InGame {
+        let team_of = |handle: &usize| players.0.get(*handle).map(|player| player.team.0);
+        let our_teams: Vec<TeamNumber> = local_players
+            .map(|local_players| local_players.handles.iter().filter_map(team_of).collect())
+            .unwrap_or_default();
+        peer_handles
+            .map
+            .iter()
+            .filter(|(_, handle)| team_of(*handle).is_some_and(|team| our_teams.contains(&team)))
+            .map(|(id, _)| *id)
+            .collect()
+    } else {
+        lobby_peers
+            .map
+            .iter()
+            .filter(|(_, lobby_peer)| {
+                lobby_peer
+                    .teams
+                    .iter()
+                    .any(|team| local_status.teams.contains(team))
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+/// System to send the messages typed into the chat to whomever they are meant for.
+pub fn handle_chat_messaging(
+    mut commands: EventReader<ChatCommand>,
+    settings: Res<UserSettings>,
+    game_state: Res<State<GameState>>,
+    peer_names: Res<PeerNames>,
+    peer_handles: Res<PeerHandles>,
+    players: Res<PlayerRegistry>,
+    local_players: Option<Res<LocalPlayers>>,
+    local_status: Res<LocalLobbyStatus>,
+    lobby_peers: Res<LobbyPeers>,
+    mut chat_history: ResMut<ChatHistory>,
+    mut messenger: EventWriter<ChatMessage>,
+    mut broadcaster: EventWriter<PeerMessage>,
+    mut direct_messenger: EventWriter<DirectPeerMessage>,
+) {
+    for command in commands.iter() {
+        let timestamp = unix_timestamp();
+        match command {
+            ChatCommand::Say(message) => {
+                if message.is_empty() {
+                    continue;
+                }
+                messenger.send(ChatMessage::message(message.clone()).by("{You}".into()));
+                broadcaster.send(PeerMessage::Chat {
+                    message: message.clone(),
+                    channel: ChatChannel::All,
+                    timestamp,
+                });
+                chat_history.push(ChatRecord {
+                    author: settings.player_name.clone(),
+                    message: message.clone(),
+                    timestamp,
+                });
+            }
+            ChatCommand::Team(message) => {
+                if message.is_empty() {
+                    continue;
+                }
+                let recipients = teammates(
+                    &game_state.0,
+                    &players,
+                    &peer_handles,
+                    local_players.as_deref(),
+                    &local_status,
+                    &lobby_peers,
+                );
+                if recipients.is_empty() {
+                    messenger.send(ChatMessage::message("There are no teammates to hear you."));
+                    continue;
+                }
+                messenger.send(
+                    ChatMessage::message(message.clone())
+                        .in_channel(ChatChannel::Team)
+                        .by("{You}".into()),
+                );
+                direct_messenger.send(DirectPeerMessage {
+                    recipients,
+                    message: PeerMessage::Chat {
+                        message: message.clone(),
+                        channel: ChatChannel::Team,
+                        timestamp,
+                    },
+                });
+            }
+            ChatCommand::Whisper(argument) => {
+                let Some((id, message)) = split_peer_name(argument, &peer_names) else {
+                    messenger.send(ChatMessage::message(
+                        "There is no one by that name to whisper to.",
+                    ));
+                    continue;
+                };
+                if message.is_empty() {
+                    continue;
+                }
+                messenger.send(
+                    ChatMessage::message(format!("(to {}) {}", peer_names.map[&id], message))
+                        .in_channel(ChatChannel::Whisper)
+                        .by("{You}".into()),
+                );
+                direct_messenger.send(DirectPeerMessage {
+                    recipients: vec![id],
+                    message: PeerMessage::Chat {
+                        message: message.to_string(),
+                        channel: ChatChannel::Whisper,
+                        timestamp,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System to carry out the chat commands that are not messages, but have a say in the session.
+pub fn handle_chat_session_commands(
+    mut commands: EventReader<ChatCommand>,
+    socket: Option<Res<SpaceballSocket>>,
+    menu_state: Res<State<MenuState>>,
+    peer_names: Res<PeerNames>,
+    mut muted_peers: ResMut<MutedPeers>,
+    mut kicked_peers: ResMut<KickedPeers>,
+    mut messenger: EventWriter<ChatMessage>,
+    mut broadcaster: EventWriter<PeerMessage>,
+    mut direct_messenger: EventWriter<DirectPeerMessage>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_limbo_state: ResMut<NextState<LimboState>>,
+) {
+    for command in commands.iter() {
+        match command {
+            ChatCommand::Mute(name) => {
+                let Some((id, _)) = split_peer_name(name, &peer_names) else {
+                    messenger.send(ChatMessage::message(
+                        "There is no one by that name to mute.",
+                    ));
+                    continue;
+                };
+                let message = if muted_peers.toggle(id) {
+                    format!("{} is muted.", peer_names.map[&id])
+                } else {
+                    format!("{} is no longer muted.", peer_names.map[&id])
+                };
+                messenger.send(ChatMessage::message(message));
+            }
+            ChatCommand::Kick(name) => {
+                if !socket.as_deref().is_some_and(is_host) {
+                    messenger.send(ChatMessage::message("Only the host can kick."));
+                    continue;
+                }
+                let Some((id, _)) = split_peer_name(name, &peer_names) else {
+                    messenger.send(ChatMessage::message(
+                        "There is no one by that name to kick.",
+                    ));
+                    continue;
+                };
+                direct_messenger.send(DirectPeerMessage {
+                    recipients: vec![id],
+                    message: PeerMessage::Kick,
+                });
+                // the kicked client may not go along with it, so they are dropped on our end too
+                kicked_peers.set.insert(id);
+                messenger.send(ChatMessage::message(format!(
+                    "{} has been kicked.",
+                    peer_names.map[&id]
+                )));
+            }
+            ChatCommand::Rematch => {
+                if menu_state.0 != MenuState::MatchEnd {
+                    messenger.send(ChatMessage::message(
+                        "A rematch can only be called once the match is over.",
+                    ));
+                    continue;
+                }
+                broadcaster.send(PeerMessage::Rematch);
+                // same as pressing the button
+                next_game_state.set(GameState::MainMenu);
+                next_limbo_state.set(LimboState::Limbo);
+            }
+            ChatCommand::Help => messenger.send(ChatMessage::message(HELP_MESSAGE)),
+            ChatCommand::Unknown(name) => messenger.send(ChatMessage::message(format!(
+                "Unknown command /{}. Type /help for the list of commands.",
+                name
+            ))),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::Uuid;
+
+    fn peer_names(names: &[&str]) -> PeerNames {
+        let mut peer_names = PeerNames::default();
+        for (i, name) in names.iter().enumerate() {
+            peer_names
+                .map
+                .insert(PeerId(Uuid::from_u128(i as u128 + 1)), name.to_string());
+        }
+        peer_names
+    }
+
+    fn peer_id(peer_names: &PeerNames, name: &str) -> PeerId {
+        *peer_names
+            .map
+            .iter()
+            .find(|(_, peer_name)| *peer_name == name)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn parses_plain_text_as_message_to_everyone() {
+        assert_eq!(
+            ChatCommand::parse("hello there"),
+            ChatCommand::Say("hello there".into())
+        );
+        assert_eq!(
+            ChatCommand::parse(" /team not a command"),
+            ChatCommand::Say(" /team not a command".into())
+        );
+    }
+
+    #[test]
+    fn parses_commands_and_their_aliases() {
+        assert_eq!(
+            ChatCommand::parse("/team push left"),
+            ChatCommand::Team("push left".into())
+        );
+        assert_eq!(
+            ChatCommand::parse("/T  push left "),
+            ChatCommand::Team("push left".into())
+        );
+        assert_eq!(
+            ChatCommand::parse("/msg Space Ball gg"),
+            ChatCommand::Whisper("Space Ball gg".into())
+        );
+        assert_eq!(ChatCommand::parse("/mute"), ChatCommand::Mute("".into()));
+        assert_eq!(
+            ChatCommand::parse("/kick Lone Star"),
+            ChatCommand::Kick("Lone Star".into())
+        );
+        assert_eq!(ChatCommand::parse("/REMATCH"), ChatCommand::Rematch);
+        assert_eq!(ChatCommand::parse("/?"), ChatCommand::Help);
+        assert_eq!(
+            ChatCommand::parse("/ludicrous speed"),
+            ChatCommand::Unknown("ludicrous".into())
+        );
+    }
+
+    #[test]
+    fn splits_names_with_spaces_off_whispers() {
+        let peer_names = peer_names(&["Space", "Space Ball", "Lone Star"]);
+        let whisper = |input: &str| {
+            let ChatCommand::Whisper(argument) = ChatCommand::parse(input) else {
+                panic!("{} is not a whisper", input);
+            };
+            split_peer_name(&argument, &peer_names).map(|(id, rest)| (id, rest.to_string()))
+        };
+
+        assert_eq!(
+            whisper("/w Space Ball may the schwartz be with you"),
+            Some((
+                peer_id(&peer_names, "Space Ball"),
+                "may the schwartz be with you".into()
+            ))
+        );
+        assert_eq!(
+            whisper("/w space hi"),
+            Some((peer_id(&peer_names, "Space"), "hi".into()))
+        );
+        assert_eq!(
+            whisper("/w lone star"),
+            Some((peer_id(&peer_names, "Lone Star"), "".into()))
+        );
+        assert_eq!(whisper("/w Spaceballs hi"), None);
+        assert_eq!(whisper("/w Lone"), None);
+    }
+
+    #[test]
+    fn ignores_peers_without_names() {
+        let peer_names = peer_names(&["", "Barf"]);
+        assert_eq!(split_peer_name(" hi", &peer_names), None);
+        assert_eq!(
+            split_peer_name("barf hi", &peer_names),
+            Some((peer_id(&peer_names, "Barf"), "hi"))
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use crate::network::confirmed::Confirmed;
+use crate::network::session::LocalPlayers;
+use crate::network::{PlayerHandle, PlayerRegistry};
+use crate::projectiles::DamageDealt;
+use crate::teams::{TeamColors, TeamNumber};
+use crate::ui::{despawn_node, fonts};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+const KILL_FEED_FONT_SIZE: f32 = 14.0;
+const MAX_KILL_FEED_ENTRIES: usize = 5;
+const KILL_FEED_ENTRY_LIFETIME_SECS: f32 = 6.0;
+
+const DAMAGE_NUMBER_FONT_SIZE: f32 = 20.0;
+const DAMAGE_NUMBER_LIFETIME_SECS: f32 = 0.8;
+/// Pixels per second the damage numbers float upwards with.
+const DAMAGE_NUMBER_RISING_SPEED: f32 = 40.0;
+/// Hits on the same victim within this time are summed up in one number, so that the railgun doesn't spam.
+const DAMAGE_NUMBER_MERGE_SECS: f32 = 0.25;
+/// Floating feedback is shown above everything else in the world.
+const FEEDBACK_Z_LAYER: f32 = 10.0;
+
+const HIT_MARKER_FONT_SIZE: f32 = 24.0;
+const HIT_MARKER_LIFETIME_SECS: f32 = 0.2;
+/// A projectile dealing damage to the same victim again within this time after the last is not marked anew.
+const HIT_MARKER_DEDUPE_SECS: f32 = 1.0;
+
+const DAMAGE_INDICATOR_LIFETIME_SECS: f32 = 1.0;
+/// Distance of the damage indicator from the center of the screen, in percent of the screen side.
+const DAMAGE_INDICATOR_DISTANCE_PERCENT: f32 = 40.0;
+const DAMAGE_INDICATOR_LENGTH_PX: f32 = 80.0;
+const DAMAGE_INDICATOR_WIDTH_PX: f32 = 8.0;
+
+/// Name shown for the characters not controlled by any player.
+const BOT_NAME: &str = "Bot";
+const NAMELESS_COLOR: Color = Color::WHITE;
+
+/// Marker of the panel listing the latest kills.
+#[derive(Component)]
+pub struct KillFeed;
+
+/// Component of a piece of feedback that fades out and disappears after its lifetime is over.
+#[derive(Component)]
+pub struct Fleeting {
+    pub lifetime: Timer,
+}
+
+impl Fleeting {
+    fn new(lifetime_secs: f32) -> Self {
+        Self {
+            lifetime: Timer::from_seconds(lifetime_secs, TimerMode::Once),
+        }
+    }
+
+    /// Get the alpha the feedback should have at this point of its life.
+    fn alpha(&self) -> f32 {
+        self.lifetime.percent_left()
+    }
+}
+
+/// Floating number of the damage dealt to a victim.
+#[derive(Component)]
+pub struct DamageNumber {
+    pub victim: Entity,
+    pub damage: f32,
+}
+
+/// Marker of the cross shown where a local player's shot has hit.
+#[derive(Component)]
+pub struct HitMarker;
+
+fn setup_kill_feed(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(35.0), Val::Percent(20.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(2.5),
+                    top: Val::Percent(2.5),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Start,
+                justify_content: JustifyContent::Start,
+                ..default()
+            },
+            ..default()
+        },
+        KillFeed,
+    ));
+}
+
+/// Get the name and the color of whoever is on either end of a hit.
+fn participant_name_and_color(
+    players: &PlayerRegistry,
+    team_colors: &TeamColors,
+    handle: Option<PlayerHandle>,
+    team: Option<TeamNumber>,
+) -> (String, Color) {
+    match handle.and_then(|handle| Some((handle, players.get(handle)?))) {
+        Some((handle, data)) => (
+            data.name.clone(),
+            team_colors.player_color(handle, data.team.0),
+        ),
+        None => (
+            BOT_NAME.to_string(),
+            team.map_or(NAMELESS_COLOR, |team| team_colors.team_color(team)),
+        ),
+    }
+}
+
+fn handle_kill_feed(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut damage_events: EventReader<Confirmed<DamageDealt>>,
+    players: Res<PlayerRegistry>,
+    team_colors: Res<TeamColors>,
+    kill_feed_query: Query<(Entity, Option<&Children>), With<KillFeed>>,
+) {
+    let Ok((kill_feed_entity, kill_feed_children)) = kill_feed_query.get_single() else {
+        return;
+    };
+    let mut entries_count = kill_feed_children.map_or(0, |children| children.len());
+    let mut oldest_entries = kill_feed_children
+        .into_iter()
+        .flat_map(|children| children.iter());
+
+    let text_style = TextStyle {
+        font: fonts::load(&asset_server, fonts::ULTRAGONIC),
+        font_size: KILL_FEED_FONT_SIZE,
+        color: Color::WHITE.with_a(0.8),
+    };
+
+    for event in damage_events.iter().filter(|event| event.is_fatal) {
+        let (killer_name, killer_color) = participant_name_and_color(
+            &players,
+            &team_colors,
+            event.shooter_handle,
+            Some(event.shooter_team),
+        );
+        let (victim_name, victim_color) = participant_name_and_color(
+            &players,
+            &team_colors,
+            event.victim_handle,
+            event.victim_team,
+        );
+
+        let text = Text::from_sections([
+            TextSection::new(
+                killer_name,
+                TextStyle {
+                    color: killer_color,
+                    ..text_style.clone()
+                },
+            ),
+            TextSection::new(
+                format!("  [{}]  ", event.gun_type.stats().name),
+                text_style.clone(),
+            ),
+            TextSection::new(
+                victim_name,
+                TextStyle {
+                    color: victim_color,
+                    ..text_style.clone()
+                },
+            ),
+        ]);
+
+        // Make room for the newest entry
+        if entries_count >= MAX_KILL_FEED_ENTRIES {
+            if let Some(&oldest_entry) = oldest_entries.next() {
+                commands.entity(oldest_entry).despawn_recursive();
+                entries_count -= 1;
+            }
+        }
+
+        commands.entity(kill_feed_entity).with_children(|parent| {
+            parent.spawn((
+                TextBundle { text, ..default() },
+                Fleeting::new(KILL_FEED_ENTRY_LIFETIME_SECS),
+            ));
+        });
+        entries_count += 1;
+    }
+}
+
+fn handle_damage_numbers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut damage_events: EventReader<Confirmed<DamageDealt>>,
+    local_players: Option<Res<LocalPlayers>>,
+    mut damage_number_query: Query<(&mut DamageNumber, &mut Fleeting, &mut Transform, &mut Text)>,
+) {
+    let Some(local_players) = local_players else {
+        damage_events.clear();
+        return;
+    };
+
+    // Sum up the hits on the same victim arriving together, in the order the victims were first hit
+    let mut victims = Vec::<Entity>::new();
+    let mut hits = HashMap::<Entity, (f32, Vec3, bool)>::default();
+    for event in damage_events.iter().filter(|event| {
+        event
+            .shooter_handle
+            .is_some_and(|handle| local_players.contains(handle))
+    }) {
+        let (damage, position, is_fatal) = hits.entry(event.victim).or_insert_with(|| {
+            victims.push(event.victim);
+            (0.0, event.position, false)
+        });
+        *damage += event.damage;
+        *position = event.position;
+        *is_fatal |= event.is_fatal;
+    }
+
+    for victim in victims {
+        let (damage, position, is_fatal) = hits[&victim];
+        let number_transform =
+            Transform::from_translation(position.truncate().extend(FEEDBACK_Z_LAYER));
+        let color = if is_fatal {
+            Color::ORANGE_RED
+        } else {
+            Color::GOLD
+        };
+
+        // ...and those in quick succession
+        if let Some((mut number, mut fleeting, mut transform, mut text)) = damage_number_query
+            .iter_mut()
+            .find(|(number, fleeting, ..)| {
+                number.victim == victim
+                    && fleeting.lifetime.elapsed_secs() < DAMAGE_NUMBER_MERGE_SECS
+            })
+        {
+            number.damage += damage;
+            fleeting.lifetime.reset();
+            *transform = number_transform;
+            text.sections[0].value = number.damage.ceil().to_string();
+            if is_fatal {
+                text.sections[0].style.color = color;
+            }
+            continue;
+        }
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    damage.ceil().to_string(),
+                    TextStyle {
+                        font: fonts::load(&asset_server, fonts::FIRA_SANS),
+                        font_size: DAMAGE_NUMBER_FONT_SIZE,
+                        color,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                transform: number_transform,
+                ..default()
+            },
+            DamageNumber { victim, damage },
+            Fleeting::new(DAMAGE_NUMBER_LIFETIME_SECS),
+        ));
+    }
+}
+
+fn handle_damage_number_rising(
+    time: Res<Time>,
+    mut damage_number_query: Query<&mut Transform, With<DamageNumber>>,
+) {
+    for mut transform in damage_number_query.iter_mut() {
+        transform.translation.y += DAMAGE_NUMBER_RISING_SPEED * time.delta_seconds();
+    }
+}
+
+fn handle_hit_markers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut damage_events: EventReader<Confirmed<DamageDealt>>,
+    local_players: Option<Res<LocalPlayers>>,
+    mut hit_marker_query: Query<&mut Text, With<HitMarker>>,
+    // markers by the projectiles and the victims they were shown for, with when those last dealt damage
+    mut shown_hits: Local<HashMap<(Entity, Entity), (Entity, f32)>>,
+) {
+    let now = time.elapsed_seconds();
+    shown_hits.retain(|_, (_, last_hit_at)| now - *last_hit_at < HIT_MARKER_DEDUPE_SECS);
+
+    let Some(local_players) = local_players else {
+        damage_events.clear();
+        return;
+    };
+
+    for event in damage_events.iter().filter(|event| {
+        event
+            .shooter_handle
+            .is_some_and(|handle| local_players.contains(handle))
+            && event.victim_handle != event.shooter_handle
+    }) {
+        let color = if event.is_fatal {
+            Color::RED
+        } else {
+            Color::WHITE
+        };
+
+        // a projectile dealing damage over several frames only gets one marker per victim
+        if let Some((marker, last_hit_at)) = shown_hits.get_mut(&(event.projectile, event.victim)) {
+            *last_hit_at = now;
+            if event.is_fatal {
+                if let Ok(mut text) = hit_marker_query.get_mut(*marker) {
+                    text.sections[0].style.color = color;
+                }
+            }
+            continue;
+        }
+
+        let marker = commands
+            .spawn((
+                Text2dBundle {
+                    text: Text::from_section(
+                        "x",
+                        TextStyle {
+                            font: fonts::load(&asset_server, fonts::FIRA_SANS),
+                            font_size: HIT_MARKER_FONT_SIZE,
+                            color,
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_translation(
+                        event.position.truncate().extend(FEEDBACK_Z_LAYER),
+                    ),
+                    ..default()
+                },
+                HitMarker,
+                Fleeting::new(HIT_MARKER_LIFETIME_SECS),
+            ))
+            .id();
+        shown_hits.insert((event.projectile, event.victim), (marker, now));
+    }
+}
+
+fn handle_damage_direction_indicator(
+    mut commands: Commands,
+    mut damage_events: EventReader<Confirmed<DamageDealt>>,
+    local_players: Option<Res<LocalPlayers>>,
+) {
+    let Some(local_players) = local_players else {
+        damage_events.clear();
+        return;
+    };
+
+    for event in damage_events.iter().filter(|event| {
+        event
+            .victim_handle
+            .is_some_and(|handle| local_players.contains(handle))
+    }) {
+        // The damage comes from where the projectile flew from
+        let source_direction = -event.direction;
+        if source_direction == Vec2::ZERO {
+            continue;
+        }
+
+        // A bar lying across the direction of the hit
+        let size = if source_direction.x.abs() > source_direction.y.abs() {
+            Size::new(
+                Val::Px(DAMAGE_INDICATOR_WIDTH_PX),
+                Val::Px(DAMAGE_INDICATOR_LENGTH_PX),
+            )
+        } else {
+            Size::new(
+                Val::Px(DAMAGE_INDICATOR_LENGTH_PX),
+                Val::Px(DAMAGE_INDICATOR_WIDTH_PX),
+            )
+        };
+
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    size,
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Percent(
+                            50.0 + source_direction.x * DAMAGE_INDICATOR_DISTANCE_PERCENT,
+                        ),
+                        top: Val::Percent(
+                            50.0 - source_direction.y * DAMAGE_INDICATOR_DISTANCE_PERCENT,
+                        ),
+                        ..default()
+                    },
+                    ..default()
+                },
+                background_color: Color::CRIMSON.with_a(0.8).into(),
+                ..default()
+            },
+            Fleeting::new(DAMAGE_INDICATOR_LIFETIME_SECS),
+        ));
+    }
+}
+
+fn handle_fleeting_feedback(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fleeting_query: Query<(
+        Entity,
+        &mut Fleeting,
+        Option<&mut Text>,
+        Option<&mut BackgroundColor>,
+    )>,
+) {
+    for (entity, mut fleeting, maybe_text, maybe_background) in fleeting_query.iter_mut() {
+        if fleeting.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = fleeting.alpha();
+        if let Some(mut text) = maybe_text {
+            for section in text.sections.iter_mut() {
+                section.style.color.set_a(alpha);
+            }
+        }
+        if let Some(mut background) = maybe_background {
+            background.0.set_a(alpha * 0.8);
+        }
+    }
+}
+
+pub(crate) struct CombatFeedbackPlugin;
+impl Plugin for CombatFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(setup_kill_feed.in_schedule(OnEnter(GameState::InGame)))
+            .add_systems((
+                handle_kill_feed.run_if(in_state(GameState::InGame)),
+                handle_damage_numbers.run_if(in_state(GameState::InGame)),
+                handle_damage_number_rising.run_if(in_state(GameState::InGame)),
+                handle_hit_markers.run_if(in_state(GameState::InGame)),
+                handle_damage_direction_indicator.run_if(in_state(GameState::InGame)),
+                handle_fleeting_feedback.run_if(in_state(GameState::InGame)),
+            ))
+            .add_system(despawn_node::<KillFeed>.in_schedule(OnExit(GameState::InGame)))
+            .add_system(despawn_node::<Fleeting>.in_schedule(OnExit(GameState::InGame)));
+    }
+}
@@ -1,114 +1,295 @@
-use crate::network::session::LocalPlayer;
+use crate::characters::PlayerControlled;
+use crate::classes::{AbilityCooldown, CharacterClass};
+use crate::network::session::{LocalPlayer, LocalPlayers};
+use crate::network::stats::ConnectionQuality;
+use crate::network::{PlayerHandle, PlayerRegistry};
 use crate::ui::menu_builder::outline_parent;
 use crate::ui::{despawn_node, fonts};
 use crate::{Equipped, GameState, Gun, Health};
-use bevy::ecs::query::QuerySingleError;
 use bevy::prelude::*;
 
 #[derive(Component)]
 pub struct HUDElement;
 
+/// Marker of the text warning about the connection being unstable.
 #[derive(Component)]
-pub struct HealthDisplay;
+struct ConnectionWarningDisplay;
 
-fn setup_player_health_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let font = fonts::load(&asset_server, fonts::SPACERUNNER);
+#[derive(Component)]
+pub struct HealthDisplay {
+    pub player_handle: PlayerHandle,
+}
+
+/// Marker of the text showing whether a local player's class ability is ready.
+#[derive(Component)]
+pub struct AbilityDisplay {
+    pub player_handle: PlayerHandle,
+}
+
+/// Corner of the screen to draw a local player's HUD in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HUDCorner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+impl HUDCorner {
+    /// Corners in the order they are handed out to the local players.
+    const ALL: [HUDCorner; 4] = [
+        HUDCorner::BottomLeft,
+        HUDCorner::BottomRight,
+        HUDCorner::TopLeft,
+        HUDCorner::TopRight,
+    ];
+
+    fn is_left(&self) -> bool {
+        matches!(self, HUDCorner::BottomLeft | HUDCorner::TopLeft)
+    }
+
+    fn is_bottom(&self) -> bool {
+        matches!(self, HUDCorner::BottomLeft | HUDCorner::BottomRight)
+    }
+
+    /// Spawn a node in the corner for the HUD elements to stack up in, starting from the screen's edge.
+    fn spawn_holder(&self, commands: &mut Commands) -> Entity {
+        let horizontal = Val::Percent(if self.is_left() { 2.5 } else { 3. });
+        let vertical = Val::Percent(2.5);
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: if self.is_left() {
+                                horizontal
+                            } else {
+                                Val::Auto
+                            },
+                            right: if self.is_left() {
+                                Val::Auto
+                            } else {
+                                horizontal
+                            },
+                            bottom: if self.is_bottom() {
+                                vertical
+                            } else {
+                                Val::Auto
+                            },
+                            top: if self.is_bottom() {
+                                Val::Auto
+                            } else {
+                                vertical
+                            },
+                        },
+                        flex_direction: if self.is_bottom() {
+                            FlexDirection::ColumnReverse
+                        } else {
+                            FlexDirection::Column
+                        },
+                        align_items: if self.is_left() {
+                            AlignItems::Start
+                        } else {
+                            AlignItems::End
+                        },
+                        ..default()
+                    },
+                    ..default()
+                },
+                HUDElement,
+            ))
+            .id()
+    }
+}
+
+/// Set up the HUD of every local player. A lone player has their health and guns spread over the bottom corners,
+/// while the players sharing the screen get a corner each.
+fn setup_player_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    local_players: Option<Res<LocalPlayers>>,
+) {
+    let Some(local_players) = local_players else {
+        return;
+    };
+
+    for (i, player_handle) in local_players.handles.iter().copied().enumerate() {
+        let (health_corner, guns_corner) = match local_players.handles.len() {
+            1 => (HUDCorner::BottomLeft, HUDCorner::BottomRight),
+            _ => {
+                let corner = HUDCorner::ALL[i % HUDCorner::ALL.len()];
+                (corner, corner)
+            }
+        };
+
+        let health_holder = health_corner.spawn_holder(&mut commands);
+        let guns_holder = if guns_corner == health_corner {
+            health_holder
+        } else {
+            guns_corner.spawn_holder(&mut commands)
+        };
+
+        spawn_player_health_hud(&mut commands, &asset_server, health_holder, player_handle);
+        spawn_player_guns_hud(&mut commands, guns_holder, guns_corner, player_handle);
+    }
+}
+
+fn spawn_player_health_hud(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    holder: Entity,
+    player_handle: PlayerHandle,
+) {
+    let font = fonts::load(asset_server, fonts::SPACERUNNER);
 
     // Spawn the health bar
-    commands
-        .spawn((
-            NodeBundle {
+    commands.entity(holder).with_children(|parent| {
+        parent
+            .spawn(NodeBundle {
                 style: Style {
                     size: Size::new(Val::Px(300.0), Val::Px(36.0)),
-                    position_type: PositionType::Absolute,
-                    position: UiRect {
-                        left: Val::Percent(2.5),
-                        bottom: Val::Percent(2.5),
-                        ..default()
-                    },
                     align_items: AlignItems::Center,
                     ..default()
                 },
                 background_color: Color::MAROON.with_a(0.1).into(),
                 ..default()
-            },
-            HUDElement,
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
-                        position_type: PositionType::Absolute,
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        background_color: Color::CRIMSON.with_a(0.8).into(),
                         ..default()
                     },
-                    background_color: Color::CRIMSON.with_a(0.8).into(),
-                    ..default()
-                },
-                HealthDisplay,
-            ));
-            parent.spawn((
-                TextBundle {
-                    style: Style {
-                        position_type: PositionType::Absolute,
-                        position: UiRect::left(Val::Percent(5.0)),
+                    HealthDisplay { player_handle },
+                ));
+                parent.spawn((
+                    TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect::left(Val::Percent(5.0)),
+                            ..default()
+                        },
+                        text: Text::from_section(
+                            "100", // Placeholder health value
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 27.0,
+                                color: Color::WHITE,
+                            },
+                        ),
                         ..default()
                     },
-                    text: Text::from_section(
-                        "100", // Placeholder health value
-                        TextStyle {
-                            font: font.clone(),
-                            font_size: 27.0,
-                            color: Color::WHITE,
-                        },
-                    ),
-                    ..default()
-                },
-                HealthDisplay,
-            ));
+                    HealthDisplay { player_handle },
+                ));
 
-            outline_parent(parent, Val::Px(2.), Color::WHITE, None);
-        });
+                outline_parent(parent, Val::Px(2.), Color::WHITE, None);
+            });
+
+        parent.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 19.0,
+                    color: Color::WHITE,
+                },
+            ),
+            AbilityDisplay { player_handle },
+        ));
+    });
 }
 
 fn handle_health_hud(
-    mut health_text_query: Query<&mut Text, With<HealthDisplay>>,
-    mut health_bar_query: Query<&mut Style, (With<HealthDisplay>, Without<Text>)>,
-    character_health_query: Query<&Health, (With<LocalPlayer>, Changed<Health>)>,
-    spare_character_query: Query<&LocalPlayer>,
+    mut health_text_query: Query<(&mut Text, &HealthDisplay)>,
+    mut health_bar_query: Query<(&mut Style, &HealthDisplay), Without<Text>>,
+    character_health_query: Query<
+        (&Health, &PlayerControlled),
+        (With<LocalPlayer>, Changed<Health>),
+    >,
+    spare_character_query: Query<&PlayerControlled, With<LocalPlayer>>,
 ) {
-    let health = match character_health_query.get_single() {
-        Ok(health) => health.hp(),
-        Err(err) => match err {
-            QuerySingleError::MultipleEntities(_) => {
-                error!("Multiple entities with `LocalPlayer` have changed `Health`!");
-                return;
-            }
-            _ => {
-                if spare_character_query.is_empty() {
-                    0.0
-                } else {
-                    return;
+    let mut health_changes = character_health_query
+        .iter()
+        .map(|(health, player)| (player.handle, health.hp(), health.max_hp()))
+        .collect::<Vec<_>>();
+
+    // the players without a character have no health to speak of
+    let characterless_players = health_text_query
+        .iter()
+        .map(|(_, display)| display.player_handle)
+        .filter(|handle| {
+            !spare_character_query
+                .iter()
+                .any(|player| player.handle == *handle)
+        })
+        .collect::<Vec<_>>();
+    health_changes.extend(
+        characterless_players
+            .into_iter()
+            .map(|handle| (handle, 0.0, 1.0)),
+    );
+
+    for (player_handle, health, max_health) in health_changes {
+        health_text_query
+            .iter_mut()
+            .filter(|(_, display)| display.player_handle == player_handle)
+            .for_each(|(mut text, _)| {
+                if let Some(section) = text.sections.last_mut() {
+                    section.value = health.max(0.0).ceil().to_string();
                 }
-            }
-        },
-    };
-
-    health_text_query.for_each_mut(|mut text| {
-        if let Some(section) = text.sections.last_mut() {
-            section.value = health.max(0.0).ceil().to_string();
-        }
-    });
+            });
 
-    // todo add outer bar change due to max hp
+        // the classes differ in max hp, so the bar shows the share left
+        let health_share = (health / max_health).clamp(0.0, 1.0);
+        health_bar_query
+            .iter_mut()
+            .filter(|(_, display)| display.player_handle == player_handle)
+            .for_each(|(mut style, _)| {
+                style.size.width = Val::Percent(health_share * 100.0);
+            });
+    }
+}
 
-    health_bar_query.for_each_mut(|mut style| {
-        style.size.width = Val::Percent(health);
-    });
+/// System to show the local players' class abilities, and the time left before they can be used again.
+fn handle_ability_hud(
+    mut ability_text_query: Query<(&mut Text, &AbilityDisplay)>,
+    character_query: Query<
+        (&CharacterClass, &AbilityCooldown, &PlayerControlled),
+        With<LocalPlayer>,
+    >,
+) {
+    for (mut text, display) in ability_text_query.iter_mut() {
+        let value = character_query
+            .iter()
+            .find(|(_, _, player)| player.handle == display.player_handle)
+            .map_or(String::new(), |(class, cooldown, _)| {
+                let ability = class.ability().name();
+                if cooldown.is_ready() {
+                    format!("{}: ready", ability)
+                } else {
+                    format!(
+                        "{}: {}s",
+                        ability,
+                        cooldown.remaining().as_secs_f32().ceil()
+                    )
+                }
+            });
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
 }
 
 #[derive(Component)]
-pub struct GunDisplayHolder;
+pub struct GunDisplayHolder {
+    pub player_handle: PlayerHandle,
+}
 
 #[derive(Component)]
 pub struct GunDisplay {
@@ -199,246 +380,253 @@ impl GunDisplay {
     }
 }
 
-fn setup_player_guns_hud(mut commands: Commands) {
-    commands.spawn((
-        NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
-                flex_direction: FlexDirection::RowReverse,
-                align_items: AlignItems::End,
-                position_type: PositionType::Absolute,
-                position: UiRect {
-                    right: Val::Percent(3.),
-                    bottom: Val::Percent(2.5),
+fn spawn_player_guns_hud(
+    commands: &mut Commands,
+    holder: Entity,
+    corner: HUDCorner,
+    player_handle: PlayerHandle,
+) {
+    commands.entity(holder).with_children(|parent| {
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: if corner.is_left() {
+                        FlexDirection::Row
+                    } else {
+                        FlexDirection::RowReverse
+                    },
+                    align_items: if corner.is_bottom() {
+                        AlignItems::End
+                    } else {
+                        AlignItems::Start
+                    },
                     ..default()
                 },
                 ..default()
             },
-            ..default()
-        },
-        HUDElement,
-        GunDisplayHolder,
-    ));
+            GunDisplayHolder { player_handle },
+        ));
+    });
 }
 
 fn handle_guns_hud_setup_change(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    gun_display_rack_query: Query<(Entity, Option<&Children>), With<GunDisplayHolder>>,
+    gun_display_rack_query: Query<(Entity, &GunDisplayHolder, Option<&Children>)>,
     mut gun_display_query: Query<(&mut GunDisplay, &mut Visibility)>,
     mut gun_text_query: Query<&mut Text>,
     mut gun_style_query: Query<&mut Style>,
-    character_query: Query<&Children, (With<LocalPlayer>, Changed<Children>)>,
+    character_query: Query<(&PlayerControlled, &Children), (With<LocalPlayer>, Changed<Children>)>,
     mut removed_children: RemovedComponents<Children>,
-    spare_character_query: Query<Entity, (With<LocalPlayer>, Without<Children>)>,
+    spare_character_query: Query<&PlayerControlled, (With<LocalPlayer>, Without<Children>)>,
     gun_query: Query<&Gun, With<Equipped>>,
 ) {
-    let guns = match character_query.get_single() {
-        Ok(children) /*if !children.is_empty()*/ => children.iter().cloned().collect::<Vec<_>>(),
-        Err(err) => match err {
-            QuerySingleError::MultipleEntities(_) => {
-                error!("Multiple entities with `LocalPlayer` have changed `Children`!");
-                return;
-            },
-            _ => {
-                match removed_children.iter().find(|&entity| spare_character_query.get(entity).is_ok()) {
-                    Some(_) => vec![],
-                    _ => return,
-                }
-            },
-        },
-    };
-
-    for (display_entity, display_children) in gun_display_rack_query.iter() {
-        let display_children = match display_children {
-            Some(children) => children.iter().cloned().collect::<Vec<_>>(),
-            _ => vec![],
-        };
-        let min_len = display_children.len().min(guns.len());
-
-        for i in 0..min_len {
-            let (mut display, mut visibility) = gun_display_query
-                .get_mut(display_children[i])
-                .expect("Could not find a child of `GunDisplayHolder` in `GunDisplay`s!");
-            let gun = gun_query
-                .get(guns[i])
-                .expect("Could not find an `Equipped` `Gun`!");
-            *visibility = Visibility::Visible;
-
-            display.gun_entity = guns[i];
-            display.update_gun_name_display(gun, &mut gun_text_query);
-            display.update_max_ammo_display(gun, &mut gun_text_query);
-            display.update_ammo_display(gun, &mut gun_text_query);
-            display.update_fire_cooldown(gun, &mut gun_style_query);
-            display.update_reload_display(gun, &mut gun_style_query);
-        }
-
-        for i in min_len..display_children.len() {
-            let (_, mut visibility) = gun_display_query
-                .get_mut(display_children[i])
-                .expect("Could not find a child of `GunDisplayHolder` in `GunDisplay`s!");
-            *visibility = Visibility::Hidden;
-        }
+    let mut gear_changes = character_query
+        .iter()
+        .map(|(player, children)| (player.handle, children.iter().cloned().collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    gear_changes.extend(
+        removed_children
+            .iter()
+            .filter_map(|entity| spare_character_query.get(entity).ok())
+            .map(|player| (player.handle, vec![])),
+    );
+
+    for (player_handle, guns) in gear_changes {
+        for (display_entity, _, display_children) in gun_display_rack_query
+            .iter()
+            .filter(|(_, holder, _)| holder.player_handle == player_handle)
+        {
+            let display_children = match display_children {
+                Some(children) => children.iter().cloned().collect::<Vec<_>>(),
+                _ => vec![],
+            };
+            let min_len = display_children.len().min(guns.len());
+
+            for i in 0..min_len {
+                let (mut display, mut visibility) = gun_display_query
+                    .get_mut(display_children[i])
+                    .expect("Could not find a child of `GunDisplayHolder` in `GunDisplay`s!");
+                let gun = gun_query
+                    .get(guns[i])
+                    .expect("Could not find an `Equipped` `Gun`!");
+                *visibility = Visibility::Visible;
+
+                display.gun_entity = guns[i];
+                display.update_gun_name_display(gun, &mut gun_text_query);
+                display.update_max_ammo_display(gun, &mut gun_text_query);
+                display.update_ammo_display(gun, &mut gun_text_query);
+                display.update_fire_cooldown(gun, &mut gun_style_query);
+                display.update_reload_display(gun, &mut gun_style_query);
+            }
 
-        if guns.len() > display_children.len() {
-            let font = fonts::load(&asset_server, fonts::SPACERUNNER);
-            let readable_font = fonts::load(&asset_server, fonts::FIRA_SANS);
-            let color = Color::WHITE.with_a(0.8);
-
-            commands.entity(display_entity).with_children(|parent| {
-                for i in display_children.len()..guns.len() {
-                    let gun_stats = match gun_query.get(guns[i]) {
-                        Ok(gun) => gun.preset.stats(),
-                        Err(_) => continue,
-                    };
-
-                    let mut name_id = Entity::PLACEHOLDER;
-                    let mut max_ammo_id = Entity::PLACEHOLDER;
-                    let mut current_ammo_id = Entity::PLACEHOLDER;
-                    let mut fire_cooldown_id = Entity::PLACEHOLDER;
-                    let mut reload_id = Entity::PLACEHOLDER;
-
-                    // Spawn a gun information panel
-                    let mut gun_display_panel = parent.spawn((NodeBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(DISPLAY_WIDTH_PX), Val::Px(200.)),
-                            margin: UiRect::left(Val::Px(DISPLAY_SPACING_PX)),
-                            flex_direction: FlexDirection::ColumnReverse,
-                            justify_content: JustifyContent::End,
-                            align_items: AlignItems::Center,
-                            ..default()
-                        },
-                        ..default()
-                    },));
-
-                    gun_display_panel.with_children(|parent| {
-                        // Name of the gun
-                        name_id = parent
-                            .spawn((TextBundle {
-                                text: Text::from_section(
-                                    gun_stats.name,
-                                    TextStyle {
-                                        font: font.clone(),
-                                        font_size: GUN_NON_READABLE_FONT_SIZE,
-                                        color,
-                                    },
-                                ),
-                                ..default()
-                            },))
-                            .id();
-
-                        // Maximum amount of ammo possible in the magazine
-                        max_ammo_id = parent
-                            .spawn(TextBundle {
-                                text: Text::from_section(
-                                    match gun_stats.shots_before_reload {
-                                        0 => "∞".to_string(),
-                                        _ => gun_stats.shots_before_reload.to_string(),
-                                    },
-                                    TextStyle {
-                                        font: readable_font.clone(),
-                                        font_size: GUN_READABLE_FONT_SIZE,
-                                        color,
-                                    },
-                                ),
-                                style: Style {
-                                    margin: UiRect::all(Val::Px(ELEMENT_SPACING_PX)),
-                                    ..default()
-                                },
-                                ..default()
-                            })
-                            .id();
+            for i in min_len..display_children.len() {
+                let (_, mut visibility) = gun_display_query
+                    .get_mut(display_children[i])
+                    .expect("Could not find a child of `GunDisplayHolder` in `GunDisplay`s!");
+                *visibility = Visibility::Hidden;
+            }
 
-                        // Horizontal separating line
-                        parent.spawn(NodeBundle {
+            if guns.len() > display_children.len() {
+                let font = fonts::load(&asset_server, fonts::SPACERUNNER);
+                let readable_font = fonts::load(&asset_server, fonts::FIRA_SANS);
+                let color = Color::WHITE.with_a(0.8);
+
+                commands.entity(display_entity).with_children(|parent| {
+                    for i in display_children.len()..guns.len() {
+                        let gun_stats = match gun_query.get(guns[i]) {
+                            Ok(gun) => gun.preset.stats(),
+                            Err(_) => continue,
+                        };
+
+                        let mut name_id = Entity::PLACEHOLDER;
+                        let mut max_ammo_id = Entity::PLACEHOLDER;
+                        let mut current_ammo_id = Entity::PLACEHOLDER;
+                        let mut fire_cooldown_id = Entity::PLACEHOLDER;
+                        let mut reload_id = Entity::PLACEHOLDER;
+
+                        // Spawn a gun information panel
+                        let mut gun_display_panel = parent.spawn((NodeBundle {
                             style: Style {
-                                size: Size::new(
-                                    Val::Px(HORIZONTAL_LINE_LENGTH_PX),
-                                    Val::Px(HORIZONTAL_LINE_WIDTH_PX),
-                                ),
+                                size: Size::new(Val::Px(DISPLAY_WIDTH_PX), Val::Px(200.)),
+                                margin: UiRect::left(Val::Px(DISPLAY_SPACING_PX)),
+                                flex_direction: FlexDirection::ColumnReverse,
+                                justify_content: JustifyContent::End,
+                                align_items: AlignItems::Center,
                                 ..default()
                             },
-                            background_color: color.into(),
                             ..default()
-                        });
-
-                        // Current amount of ammo in the magazine
-                        current_ammo_id = parent
-                            .spawn(TextBundle {
-                                text: Text::from_section(
-                                    match gun_stats.shots_before_reload {
-                                        0 => "∞".to_string(),
-                                        _ => gun_stats.shots_before_reload.to_string(),
-                                    },
-                                    TextStyle {
-                                        font: readable_font.clone(),
-                                        font_size: GUN_READABLE_FONT_SIZE,
-                                        color,
+                        },));
+
+                        gun_display_panel.with_children(|parent| {
+                            // Name of the gun
+                            name_id = parent
+                                .spawn((TextBundle {
+                                    text: Text::from_section(
+                                        gun_stats.name,
+                                        TextStyle {
+                                            font: font.clone(),
+                                            font_size: GUN_NON_READABLE_FONT_SIZE,
+                                            color,
+                                        },
+                                    ),
+                                    ..default()
+                                },))
+                                .id();
+
+                            // Maximum amount of ammo possible in the magazine
+                            max_ammo_id = parent
+                                .spawn(TextBundle {
+                                    text: Text::from_section(
+                                        match gun_stats.shots_before_reload {
+                                            0 => "∞".to_string(),
+                                            _ => gun_stats.shots_before_reload.to_string(),
+                                        },
+                                        TextStyle {
+                                            font: readable_font.clone(),
+                                            font_size: GUN_READABLE_FONT_SIZE,
+                                            color,
+                                        },
+                                    ),
+                                    style: Style {
+                                        margin: UiRect::all(Val::Px(ELEMENT_SPACING_PX)),
+                                        ..default()
                                     },
-                                ),
-                                style: Style {
-                                    margin: UiRect::bottom(Val::Px(ELEMENT_SPACING_PX * 1.5)),
                                     ..default()
-                                },
-                                ..default()
-                            })
-                            .id();
+                                })
+                                .id();
 
-                        // Vertical line of fire cooldown
-                        fire_cooldown_id = parent
-                            .spawn((NodeBundle {
+                            // Horizontal separating line
+                            parent.spawn(NodeBundle {
                                 style: Style {
-                                    position_type: PositionType::Absolute,
-                                    position: UiRect {
-                                        left: Val::Px(PROGRESS_BAR_X_OFFSET_PX),
-                                        bottom: Val::Px(PROGRESS_BAR_Y_OFFSET_PX),
-                                        ..default()
-                                    },
                                     size: Size::new(
-                                        Val::Px(PROGRESS_BAR_WIDTH_PX),
-                                        // Progress bar height
-                                        Val::Px(0.0),
+                                        Val::Px(HORIZONTAL_LINE_LENGTH_PX),
+                                        Val::Px(HORIZONTAL_LINE_WIDTH_PX),
                                     ),
                                     ..default()
                                 },
                                 background_color: color.into(),
                                 ..default()
-                            },))
-                            .id();
-
-                        // Vertical line of reload progress
-                        reload_id = parent
-                            .spawn((NodeBundle {
-                                style: Style {
-                                    position_type: PositionType::Absolute,
-                                    position: UiRect {
-                                        right: Val::Px(PROGRESS_BAR_X_OFFSET_PX),
-                                        bottom: Val::Px(PROGRESS_BAR_Y_OFFSET_PX),
+                            });
+
+                            // Current amount of ammo in the magazine
+                            current_ammo_id = parent
+                                .spawn(TextBundle {
+                                    text: Text::from_section(
+                                        match gun_stats.shots_before_reload {
+                                            0 => "∞".to_string(),
+                                            _ => gun_stats.shots_before_reload.to_string(),
+                                        },
+                                        TextStyle {
+                                            font: readable_font.clone(),
+                                            font_size: GUN_READABLE_FONT_SIZE,
+                                            color,
+                                        },
+                                    ),
+                                    style: Style {
+                                        margin: UiRect::bottom(Val::Px(ELEMENT_SPACING_PX * 1.5)),
                                         ..default()
                                     },
-                                    size: Size::new(
-                                        Val::Px(PROGRESS_BAR_WIDTH_PX),
-                                        // Progress bar height
-                                        Val::Px(0.0),
-                                    ),
                                     ..default()
-                                },
-                                background_color: color.into(),
-                                ..default()
-                            },))
-                            .id();
-                    });
-
-                    gun_display_panel.insert(GunDisplay {
-                        gun_entity: guns[i],
-                        name_display: name_id,
-                        max_ammo_display: max_ammo_id,
-                        ammo_display: current_ammo_id,
-                        fire_cooldown_display: fire_cooldown_id,
-                        reload_display: reload_id,
-                    });
-                }
-            });
+                                })
+                                .id();
+
+                            // Vertical line of fire cooldown
+                            fire_cooldown_id = parent
+                                .spawn((NodeBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        position: UiRect {
+                                            left: Val::Px(PROGRESS_BAR_X_OFFSET_PX),
+                                            bottom: Val::Px(PROGRESS_BAR_Y_OFFSET_PX),
+                                            ..default()
+                                        },
+                                        size: Size::new(
+                                            Val::Px(PROGRESS_BAR_WIDTH_PX),
+                                            // Progress bar height
+                                            Val::Px(0.0),
+                                        ),
+                                        ..default()
+                                    },
+                                    background_color: color.into(),
+                                    ..default()
+                                },))
+                                .id();
+
+                            // Vertical line of reload progress
+                            reload_id = parent
+                                .spawn((NodeBundle {
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        position: UiRect {
+                                            right: Val::Px(PROGRESS_BAR_X_OFFSET_PX),
+                                            bottom: Val::Px(PROGRESS_BAR_Y_OFFSET_PX),
+                                            ..default()
+                                        },
+                                        size: Size::new(
+                                            Val::Px(PROGRESS_BAR_WIDTH_PX),
+                                            // Progress bar height
+                                            Val::Px(0.0),
+                                        ),
+                                        ..default()
+                                    },
+                                    background_color: color.into(),
+                                    ..default()
+                                },))
+                                .id();
+                        });
+
+                        gun_display_panel.insert(GunDisplay {
+                            gun_entity: guns[i],
+                            name_display: name_id,
+                            max_ammo_display: max_ammo_id,
+                            ammo_display: current_ammo_id,
+                            fire_cooldown_display: fire_cooldown_id,
+                            reload_display: reload_id,
+                        });
+                    }
+                });
+            }
         }
     }
 }
@@ -461,19 +649,101 @@ fn handle_guns_hud_update(
     }
 }
 
+/// Set up the warning about the connection, hidden until there is something to warn about.
+fn setup_connection_warning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: fonts::load(&asset_server, fonts::FIRA_SANS),
+                    font_size: 20.,
+                    color: Color::ORANGE_RED,
+                },
+            )
+            .with_alignment(TextAlignment::Center),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Percent(2.5),
+                    left: Val::Percent(40.),
+                    ..default()
+                },
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ConnectionWarningDisplay,
+        HUDElement,
+    ));
+}
+
+/// System to show what is off with the connection, if anything.
+fn handle_connection_warning(
+    quality: Res<ConnectionQuality>,
+    players: Option<Res<PlayerRegistry>>,
+    mut warning_query: Query<(&mut Text, &mut Visibility), With<ConnectionWarningDisplay>>,
+) {
+    if !quality.is_changed() {
+        return;
+    }
+
+    let mut lagging_players: Vec<_> = quality
+        .players
+        .iter()
+        .filter(|(_, stats)| stats.is_lagging())
+        .map(|(handle, _)| *handle)
+        .collect();
+    lagging_players.sort();
+    let lagging_names: Vec<_> = lagging_players
+        .into_iter()
+        .map(|handle| {
+            players
+                .as_ref()
+                .and_then(|players| players.get(handle))
+                .map_or_else(
+                    || format!("Player {}", handle),
+                    |player| player.name.clone(),
+                )
+        })
+        .collect();
+
+    let mut warnings = vec![];
+    if !lagging_names.is_empty() {
+        warnings.push(format!("Lagging: {}", lagging_names.join(", ")));
+    }
+    if quality.is_rollback_deep() {
+        warnings.push(format!("Rolling back {} frames", quality.rollback_frames));
+    }
+
+    for (mut text, mut visibility) in warning_query.iter_mut() {
+        text.sections[0].value = warnings.join("\n");
+        *visibility = if warnings.is_empty() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
+
 pub(crate) struct HUDPlugin;
 impl Plugin for HUDPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems((
-            setup_player_health_hud.in_schedule(OnEnter(GameState::InGame)),
+            setup_player_hud.in_schedule(OnEnter(GameState::InGame)),
             handle_health_hud.run_if(in_state(GameState::InGame)),
+            handle_ability_hud.run_if(in_state(GameState::InGame)),
         ))
         // .add_system(setup_player_names_hud.in_schedule(OnEnter(GameState::InGame)))
         .add_systems((
-            setup_player_guns_hud.in_schedule(OnEnter(GameState::InGame)),
             handle_guns_hud_setup_change.run_if(in_state(GameState::InGame)),
             handle_guns_hud_update.run_if(in_state(GameState::InGame)),
         ))
+        .add_systems((
+            setup_connection_warning.in_schedule(OnEnter(GameState::InGame)),
+            handle_connection_warning.run_if(in_state(GameState::InGame)),
+        ))
         .add_system(despawn_node::<HUDElement>.in_schedule(OnExit(GameState::InGame)));
     }
 }
@@ -1,8 +1,311 @@
-use crate::network::peers::PeerNames;
+use crate::network::lobby::{is_host, LobbyPeers, LocalLobbyStatus, MatchSettings};
+use crate::network::peers::{PeerColors, PeerNames};
+use crate::network::socket::SpaceballSocket;
+use crate::teams::{TeamColors, TeamNumber};
+use crate::ui::color_interaction::ColorInteractionMap;
+use crate::ui::focus::Focus;
+use crate::ui::fonts;
 use crate::ui::menu::handle_menu_actions;
+use crate::ui::menu_builder::MenuBuildingEnvironment;
+use crate::ui::profile::{profile_color_text, Profile};
+use crate::ui::user_settings::UserSettings;
 use crate::{GameState, MenuState, PlayerCount};
 use bevy::prelude::*;
 
+/// Actions of the buttons in the pre-match lobby.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) enum LobbyButtonAction {
+    ToggleReady,
+    /// Switch the team of the local player with the index.
+    CycleTeam(usize),
+    /// Switch the class of the local player with the index.
+    CycleClass(usize),
+    /// Switch the color we'd like to be shown in.
+    CycleColor,
+    CycleScene,
+    CycleGameMode,
+    ChangeMatchDuration(i32),
+}
+
+/// Marker of the text showing the match settings.
+#[derive(Component)]
+pub(crate) struct MatchSettingsDisplay;
+
+/// Marker of the button to ready up with.
+#[derive(Component)]
+pub(crate) struct ReadyButton;
+
+/// Marker of the node listing the players in the lobby.
+#[derive(Component)]
+pub(crate) struct LobbyPlayerList;
+
+pub(crate) fn match_settings_text(match_settings: &MatchSettings, is_host: bool) -> String {
+    format!(
+        "Scene: {:?}   Mode: {}   Duration: {} min{}",
+        match_settings.scene,
+        match_settings.mode.name(),
+        match_settings.duration_minutes,
+        if is_host {
+            "\nYou are the host"
+        } else {
+            "\nThe host picks the match settings"
+        },
+    )
+}
+
+pub(crate) fn ready_text(local_status: &LocalLobbyStatus) -> &'static str {
+    if local_status.ready {
+        "Not ready"
+    } else {
+        "Ready"
+    }
+}
+
+fn team_text(team: TeamNumber) -> String {
+    format!("Team {}", team)
+}
+
+fn lobby_peer_text(ready: bool, ping_ms: Option<u32>) -> String {
+    let ready = if ready { "Ready" } else { "Not ready" };
+    match ping_ms {
+        Some(ping_ms) => format!("{}  {} ms", ready, ping_ms),
+        None => ready.to_string(),
+    }
+}
+
+/// System to act on the lobby buttons. The match settings are left alone unless we are the host.
+fn handle_lobby_actions(
+    interaction_query: Query<
+        (&Interaction, &LobbyButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    socket: Option<Res<SpaceballSocket>>,
+    mut local_status: ResMut<LocalLobbyStatus>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut profile: ResMut<Profile>,
+) {
+    let is_host = socket.is_some_and(|socket| is_host(&socket));
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *action {
+            LobbyButtonAction::ToggleReady => local_status.ready = !local_status.ready,
+            LobbyButtonAction::CycleTeam(index) => {
+                let mode = match_settings.mode;
+                if let Some(team) = local_status.teams.get_mut(index) {
+                    *team = mode.next_team(*team);
+                }
+            }
+            LobbyButtonAction::CycleClass(index) => {
+                if let Some(class) = local_status.classes.get_mut(index) {
+                    *class = class.next();
+                }
+            }
+            LobbyButtonAction::CycleColor => profile.cycle_color(),
+            LobbyButtonAction::CycleScene if is_host => {
+                match_settings.scene = match_settings.scene.next();
+                local_status.ready = false;
+            }
+            LobbyButtonAction::CycleGameMode if is_host => {
+                match_settings.mode = match_settings.mode.next();
+                local_status.ready = false;
+            }
+            LobbyButtonAction::ChangeMatchDuration(change) if is_host => {
+                let minutes = match_settings.duration_minutes as i32 + change;
+                match_settings.set_duration_minutes(minutes.max(0) as u32);
+                local_status.ready = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System to keep the match settings and the ready button up to date.
+fn handle_lobby_display_change(
+    socket: Option<Res<SpaceballSocket>>,
+    local_status: Res<LocalLobbyStatus>,
+    match_settings: Res<MatchSettings>,
+    mut text_query: Query<&mut Text>,
+    settings_text_query: Query<Entity, With<MatchSettingsDisplay>>,
+    ready_button_query: Query<&Children, With<ReadyButton>>,
+    added_settings_text_query: Query<(), Added<MatchSettingsDisplay>>,
+    mut was_host: Local<bool>,
+) {
+    // the host may change as the peers come and go
+    let is_host = socket.is_some_and(|socket| is_host(&socket));
+    if !(local_status.is_changed() || match_settings.is_changed() || *was_host != is_host)
+        && added_settings_text_query.is_empty()
+    {
+        return;
+    }
+    *was_host = is_host;
+    for entity in settings_text_query.iter() {
+        if let Ok(mut text) = text_query.get_mut(entity) {
+            text.sections[0].value = match_settings_text(&match_settings, is_host);
+        }
+    }
+    for children in ready_button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = ready_text(&local_status).to_string();
+        }
+    }
+}
+
+/// System to rebuild the list of the players in the lobby, with their teams, colors, readiness and latency.
+fn handle_lobby_player_list_change(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<UserSettings>,
+    profile: Res<Profile>,
+    team_colors: Res<TeamColors>,
+    local_status: Res<LocalLobbyStatus>,
+    lobby_peers: Res<LobbyPeers>,
+    peer_names: Res<PeerNames>,
+    peer_colors: Res<PeerColors>,
+    player_list_query: Query<Entity, With<LobbyPlayerList>>,
+    added_player_list_query: Query<(), Added<LobbyPlayerList>>,
+) {
+    if !(local_status.is_changed()
+        || lobby_peers.is_changed()
+        || peer_names.is_changed()
+        || peer_colors.is_changed()
+        || profile.is_changed()
+        || team_colors.is_changed())
+        && added_player_list_query.is_empty()
+    {
+        return;
+    }
+
+    for player_list in player_list_query.iter() {
+        commands.entity(player_list).despawn_descendants();
+        commands.entity(player_list).with_children(|parent| {
+            let mut menu_env = MenuBuildingEnvironment::default(&asset_server);
+            menu_env.button_width = Val::Px(180.0);
+            menu_env.button_height = Val::Px(36.0);
+            menu_env.button_font_size = 20.0;
+            let text_style = TextStyle {
+                font: fonts::load(&asset_server, fonts::FIRA_SANS),
+                font_size: 24.0,
+                color: Color::WHITE,
+            };
+            let row_style = Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                gap: Size::width(Val::Px(16.0)),
+                ..default()
+            };
+
+            for (index, team) in local_status.teams.iter().enumerate() {
+                let name = match index {
+                    0 => settings.player_name.clone(),
+                    n => format!("{} {}", settings.player_name, n + 1),
+                };
+                let team_color = team_colors.team_color(*team);
+                // the guests sharing the machine go with their teams' colors
+                let name_color = profile.color.filter(|_| index == 0).unwrap_or(team_color);
+                menu_env.button_color = team_color;
+                parent
+                    .spawn(NodeBundle {
+                        style: row_style.clone(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                color: name_color,
+                                ..text_style.clone()
+                            },
+                        ));
+                        crate::build_menu_item!(
+                            parent,
+                            menu_env,
+                            Buttons,
+                            (),
+                            (LobbyButtonAction::CycleTeam(index), team_text(*team))
+                        );
+                        if let Some(class) = local_status.classes.get(index) {
+                            crate::build_menu_item!(
+                                parent,
+                                menu_env,
+                                Buttons,
+                                (),
+                                (LobbyButtonAction::CycleClass(index), class.name())
+                            );
+                        }
+                        if index == 0 {
+                            menu_env.button_color = name_color;
+                            crate::build_menu_item!(
+                                parent,
+                                menu_env,
+                                Buttons,
+                                (),
+                                (
+                                    LobbyButtonAction::CycleColor,
+                                    format!("Color: {}", profile_color_text(&profile))
+                                )
+                            );
+                        }
+                        parent.spawn(TextBundle::from_section(
+                            lobby_peer_text(local_status.ready, None),
+                            text_style.clone(),
+                        ));
+                    });
+            }
+
+            for (peer, lobby_peer) in lobby_peers.map.iter() {
+                let name = peer_names
+                    .map
+                    .get(peer)
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown".to_string());
+                let teams = lobby_peer
+                    .teams
+                    .iter()
+                    .enumerate()
+                    .map(|(index, team)| match lobby_peer.classes.get(index) {
+                        Some(class) => format!("{} {}", team_text(*team), class.name()),
+                        None => team_text(*team),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let team_color = lobby_peer
+                    .teams
+                    .first()
+                    .map_or(Color::WHITE, |team| team_colors.team_color(*team));
+                let name_color = peer_colors.map.get(peer).copied().unwrap_or(Color::WHITE);
+                parent
+                    .spawn(NodeBundle {
+                        style: row_style.clone(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            name,
+                            TextStyle {
+                                color: name_color,
+                                ..text_style.clone()
+                            },
+                        ));
+                        parent.spawn(TextBundle::from_section(
+                            teams,
+                            TextStyle {
+                                color: team_color,
+                                ..text_style.clone()
+                            },
+                        ));
+                        parent.spawn(TextBundle::from_section(
+                            lobby_peer_text(lobby_peer.ready, lobby_peer.ping_ms),
+                            text_style.clone(),
+                        ));
+                    });
+            }
+        });
+    }
+}
+
 #[derive(Component)]
 pub struct PeerWaitingText {
     pub number_section_idx: usize,
@@ -55,6 +358,15 @@ pub(crate) struct LobbyPlugin;
 impl Plugin for LobbyPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(handle_peer_waiting_text.run_if(in_state(MenuState::MatchmakingLobby)))
+            .add_systems(
+                (
+                    handle_lobby_actions,
+                    handle_lobby_display_change,
+                    handle_lobby_player_list_change,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(MenuState::MatchmakingLobby)),
+            )
             .add_system(disable_matchmaking_menu_state.in_schedule(OnExit(GameState::Matchmaking)))
             .add_system(
                 intercept_matchmaking_menu_state_if_single_player
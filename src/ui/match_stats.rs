@@ -0,0 +1,638 @@
+//! Table of the players' detailed match stats on the match end screen, with the awards they have earned.
+
+use crate::match_stats::GunUsage;
+use crate::network::confirmed::FrameCount;
+use crate::network::players::PlayerData;
+use crate::network::{PlayerHandle, PlayerRegistry};
+use crate::teams::TeamColors;
+use crate::ui::chat::ChatMessage;
+use crate::ui::clipboard_util::Clipboard;
+use crate::ui::color_interaction::ColorInteractionMap;
+use crate::ui::focus::Focus;
+use crate::ui::menu_builder::{
+    DEFAULT_BUTTON_COLOR, DEFAULT_BUTTON_HOVERED_COLOR, DEFAULT_BUTTON_PRESSED_COLOR,
+    DEFAULT_TEXT_COLOR,
+};
+use crate::ui::{despawn_node, fonts};
+use crate::{GunPreset, MenuState};
+use bevy::prelude::*;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Fewest projectiles a player must have fired to be up for "Most Accurate".
+const MIN_PROJECTILES_FOR_ACCURACY_AWARD: u32 = 20;
+/// Shortest kill streak worth an award.
+const MIN_KILL_STREAK_FOR_AWARD: u32 = 3;
+
+const TABLE_FONT_SIZE: f32 = 17.;
+const AWARDS_FONT_SIZE: f32 = 20.;
+
+/// Column of the stats table, which it can be sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatColumn {
+    Name,
+    #[default]
+    Kills,
+    Deaths,
+    Accuracy,
+    DamageDealt,
+    DamageTaken,
+    LongestKillStreak,
+    TimeAlive,
+    Pickups,
+}
+
+impl StatColumn {
+    pub const ALL: [StatColumn; 9] = [
+        StatColumn::Name,
+        StatColumn::Kills,
+        StatColumn::Deaths,
+        StatColumn::Accuracy,
+        StatColumn::DamageDealt,
+        StatColumn::DamageTaken,
+        StatColumn::LongestKillStreak,
+        StatColumn::TimeAlive,
+        StatColumn::Pickups,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            StatColumn::Name => "Name",
+            StatColumn::Kills => "Kills",
+            StatColumn::Deaths => "Deaths",
+            StatColumn::Accuracy => "Accuracy",
+            StatColumn::DamageDealt => "Dmg dealt",
+            StatColumn::DamageTaken => "Dmg taken",
+            StatColumn::LongestKillStreak => "Best streak",
+            StatColumn::TimeAlive => "Time alive",
+            StatColumn::Pickups => "Pickups",
+        }
+    }
+
+    /// Share of the table's width the column takes, in percent.
+    fn width(&self) -> f32 {
+        match self {
+            StatColumn::Name => 20.,
+            _ => 10.,
+        }
+    }
+}
+
+/// Actions of the buttons of the stats table.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) enum MatchStatsButtonAction {
+    /// Show or hide the table.
+    ToggleTable,
+    /// Sort by the column, or flip the order if it is sorted by it already.
+    SortBy(StatColumn),
+    /// Copy the stats to the clipboard as JSON.
+    Export,
+}
+
+/// Resource of how the stats table is to be shown.
+#[derive(Resource, Debug, Default)]
+pub struct MatchStatsView {
+    pub shown: bool,
+    pub sort_by: StatColumn,
+    pub ascending: bool,
+}
+
+/// Marker of the panel holding the stats table.
+#[derive(Component)]
+struct MatchStatsPanel;
+
+/// Marker of the node holding the rows of the stats table.
+#[derive(Component)]
+struct MatchStatsTableBody;
+
+/// Marker of the header text of a column, to show the sorting order on.
+#[derive(Component)]
+struct MatchStatsColumnHeader(StatColumn);
+
+/// Marker of the text listing the awards.
+#[derive(Component)]
+struct MatchAwardsText;
+
+/// A player's stats for a row of the table, worked out.
+struct StatRow {
+    name: String,
+    color: Color,
+    kills: usize,
+    deaths: usize,
+    accuracy: f32,
+    damage_dealt: f32,
+    damage_taken: f32,
+    longest_kill_streak: u32,
+    time_alive_secs: f32,
+    pickups: u32,
+}
+
+impl StatRow {
+    fn new(
+        handle: PlayerHandle,
+        player: &PlayerData,
+        team_colors: &TeamColors,
+        frame: i32,
+    ) -> Self {
+        Self {
+            name: player.name.clone(),
+            color: team_colors.player_color(handle, player.team.0),
+            kills: player.kills,
+            deaths: player.deaths,
+            accuracy: player.stats.total().accuracy(),
+            damage_dealt: player.stats.damage_dealt,
+            damage_taken: player.stats.damage_taken,
+            longest_kill_streak: player.stats.longest_kill_streak,
+            time_alive_secs: player.stats.time_alive(frame).as_secs_f32(),
+            pickups: player.stats.pickups,
+        }
+    }
+
+    fn compare(&self, other: &Self, column: StatColumn) -> Ordering {
+        let by_float = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+        match column {
+            StatColumn::Name => self.name.to_lowercase().cmp(&other.name.to_lowercase()),
+            StatColumn::Kills => self.kills.cmp(&other.kills),
+            StatColumn::Deaths => self.deaths.cmp(&other.deaths),
+            StatColumn::Accuracy => by_float(self.accuracy, other.accuracy),
+            StatColumn::DamageDealt => by_float(self.damage_dealt, other.damage_dealt),
+            StatColumn::DamageTaken => by_float(self.damage_taken, other.damage_taken),
+            StatColumn::LongestKillStreak => {
+                self.longest_kill_streak.cmp(&other.longest_kill_streak)
+            }
+            StatColumn::TimeAlive => by_float(self.time_alive_secs, other.time_alive_secs),
+            StatColumn::Pickups => self.pickups.cmp(&other.pickups),
+        }
+    }
+
+    fn cell(&self, column: StatColumn) -> String {
+        match column {
+            StatColumn::Name => self.name.clone(),
+            StatColumn::Kills => self.kills.to_string(),
+            StatColumn::Deaths => self.deaths.to_string(),
+            StatColumn::Accuracy => format!("{:.0}%", self.accuracy * 100.),
+            StatColumn::DamageDealt => format!("{:.0}", self.damage_dealt),
+            StatColumn::DamageTaken => format!("{:.0}", self.damage_taken),
+            StatColumn::LongestKillStreak => self.longest_kill_streak.to_string(),
+            StatColumn::TimeAlive => {
+                let secs = self.time_alive_secs as u32;
+                format!("{}:{:02}", secs / 60, secs % 60)
+            }
+            StatColumn::Pickups => self.pickups.to_string(),
+        }
+    }
+}
+
+/// Award for standing out in some way, and the player it goes to.
+pub struct MatchAward {
+    pub title: &'static str,
+    pub player_handle: usize,
+}
+
+/// Find the player with the greatest score, if any of them have scored at all.
+fn best_player_by<T: PartialOrd + Default>(
+    players: &PlayerRegistry,
+    score: impl Fn(&PlayerData) -> Option<T>,
+) -> Option<usize> {
+    players
+        .iter()
+        .enumerate()
+        .filter_map(|(handle, player)| score(player).map(|score| (handle, score)))
+        .filter(|(_, score)| *score > T::default())
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map(|(handle, _)| handle)
+}
+
+/// Hand out the awards for the match.
+pub fn match_awards(players: &PlayerRegistry, frame: i32) -> Vec<MatchAward> {
+    let candidates: [(&'static str, Option<usize>); 6] = [
+        (
+            "Most Accurate",
+            best_player_by(players, |player| {
+                let total = player.stats.total();
+                (total.projectiles >= MIN_PROJECTILES_FOR_ACCURACY_AWARD)
+                    .then_some(total.accuracy())
+            }),
+        ),
+        (
+            "Railgun Artist",
+            best_player_by(players, |player| {
+                Some(player.stats.gun(GunPreset::RailGun).kills)
+            }),
+        ),
+        (
+            "Unstoppable",
+            best_player_by(players, |player| {
+                (player.stats.longest_kill_streak >= MIN_KILL_STREAK_FOR_AWARD)
+                    .then_some(player.stats.longest_kill_streak)
+            }),
+        ),
+        (
+            "Survivor",
+            best_player_by(players, |player| {
+                Some(player.stats.time_alive(frame).as_secs_f32())
+            }),
+        ),
+        (
+            "Scavenger",
+            best_player_by(players, |player| Some(player.stats.pickups)),
+        ),
+        (
+            "Bullet Sponge",
+            best_player_by(players, |player| Some(player.stats.damage_taken)),
+        ),
+    ];
+    candidates
+        .into_iter()
+        .filter_map(|(title, player_handle)| {
+            player_handle.map(|player_handle| MatchAward {
+                title,
+                player_handle,
+            })
+        })
+        .collect()
+}
+
+/// A player's stats the way they are exported.
+#[derive(Serialize)]
+struct PlayerStatsExport<'a> {
+    name: &'a str,
+    team: u64,
+    kills: usize,
+    deaths: usize,
+    damage_dealt: f32,
+    damage_taken: f32,
+    longest_kill_streak: u32,
+    time_alive_secs: f32,
+    pickups: u32,
+    guns: BTreeMap<&'static str, &'a GunUsage>,
+    awards: Vec<&'static str>,
+}
+
+/// Put the stats of the match into JSON.
+pub fn export_match_stats(players: &PlayerRegistry, frame: i32) -> serde_json::Result<String> {
+    let awards = match_awards(players, frame);
+    let players: Vec<_> = players
+        .iter()
+        .enumerate()
+        .map(|(handle, player)| PlayerStatsExport {
+            name: &player.name,
+            team: player.team.0 as u64,
+            kills: player.kills,
+            deaths: player.deaths,
+            damage_dealt: player.stats.damage_dealt,
+            damage_taken: player.stats.damage_taken,
+            longest_kill_streak: player.stats.longest_kill_streak,
+            time_alive_secs: player.stats.time_alive(frame).as_secs_f32(),
+            pickups: player.stats.pickups,
+            guns: player
+                .stats
+                .guns
+                .iter()
+                .map(|(gun_type, usage)| (gun_type.stats().name, usage))
+                .collect(),
+            awards: awards
+                .iter()
+                .filter(|award| award.player_handle == handle)
+                .map(|award| award.title)
+                .collect(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&players)
+}
+
+/// Spawn a button of the stats table, with its text.
+fn spawn_stats_button(
+    parent: &mut ChildBuilder,
+    action: MatchStatsButtonAction,
+    text: impl Into<String>,
+    text_style: TextStyle,
+    width: Val,
+    extra_text_components: impl Bundle,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    size: Size::new(width, Val::Px(text_style.font_size + 10.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            Focus::<Interaction>::None,
+            ColorInteractionMap::from([]),
+            action,
+        ))
+        .with_children(|button| {
+            button.spawn((
+                TextBundle::from_section(text, text_style),
+                ColorInteractionMap::from([
+                    (Interaction::None, Some(DEFAULT_BUTTON_COLOR)),
+                    (Interaction::Hovered, Some(DEFAULT_BUTTON_HOVERED_COLOR)),
+                    (Interaction::Clicked, Some(DEFAULT_BUTTON_PRESSED_COLOR)),
+                ]),
+                extra_text_components,
+            ));
+        });
+}
+
+/// Spawn the button to show the stats table with, among the match end buttons.
+pub(crate) fn spawn_match_stats_toggle_button(parent: &mut ChildBuilder, text_style: TextStyle) {
+    spawn_stats_button(
+        parent,
+        MatchStatsButtonAction::ToggleTable,
+        "Stats",
+        text_style,
+        Val::Percent(100.),
+        (),
+    );
+}
+
+fn setup_match_stats_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: fonts::load(&asset_server, fonts::ULTRAGONIC),
+        font_size: TABLE_FONT_SIZE,
+        color: DEFAULT_BUTTON_COLOR,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(80.), Val::Auto),
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Percent(10.),
+                        top: Val::Percent(10.),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(10.)),
+                    gap: Size::height(Val::Px(10.)),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgb(0.212, 0., 0.102).with_a(0.9).into(),
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            MatchStatsPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font_size: AWARDS_FONT_SIZE,
+                            color: DEFAULT_TEXT_COLOR,
+                            ..text_style.clone()
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    ..default()
+                },
+                MatchAwardsText,
+            ));
+
+            // column headers, sorting the table when clicked
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::width(Val::Percent(100.)),
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for column in StatColumn::ALL {
+                        spawn_stats_button(
+                            parent,
+                            MatchStatsButtonAction::SortBy(column),
+                            column.title(),
+                            text_style.clone(),
+                            Val::Percent(column.width()),
+                            MatchStatsColumnHeader(column),
+                        );
+                    }
+                });
+
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::width(Val::Percent(100.)),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                },
+                MatchStatsTableBody,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        gap: Size::width(Val::Px(30.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_stats_button(
+                        parent,
+                        MatchStatsButtonAction::Export,
+                        "Copy JSON",
+                        text_style.clone(),
+                        Val::Px(160.),
+                        (),
+                    );
+                    spawn_stats_button(
+                        parent,
+                        MatchStatsButtonAction::ToggleTable,
+                        "Close",
+                        text_style.clone(),
+                        Val::Px(160.),
+                        (),
+                    );
+                });
+        });
+}
+
+/// System to act on the buttons of the stats table.
+fn handle_match_stats_actions(
+    interaction_query: Query<
+        (&Interaction, &MatchStatsButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    players: Res<PlayerRegistry>,
+    frame: Res<FrameCount>,
+    mut view: ResMut<MatchStatsView>,
+    mut messenger: EventWriter<ChatMessage>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *action {
+            MatchStatsButtonAction::ToggleTable => view.shown = !view.shown,
+            MatchStatsButtonAction::SortBy(column) => {
+                if view.sort_by == column {
+                    view.ascending = !view.ascending;
+                } else {
+                    view.sort_by = column;
+                    // names read best from A to Z, the rest from the best to the worst
+                    view.ascending = column == StatColumn::Name;
+                }
+            }
+            MatchStatsButtonAction::Export => match export_match_stats(&players, frame.0) {
+                Ok(json) => {
+                    Clipboard::write(json);
+                    messenger.send(ChatMessage::message(
+                        "The match stats are copied to the clipboard.",
+                    ));
+                }
+                Err(err) => error!("Failed to export the match stats: {}", err),
+            },
+        }
+    }
+}
+
+/// System to show or hide the stats table, and to rebuild its rows in the order picked.
+fn handle_match_stats_display_change(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    view: Res<MatchStatsView>,
+    players: Res<PlayerRegistry>,
+    team_colors: Res<TeamColors>,
+    frame: Res<FrameCount>,
+    mut panel_query: Query<&mut Style, With<MatchStatsPanel>>,
+    body_query: Query<Entity, With<MatchStatsTableBody>>,
+    mut header_query: Query<(&mut Text, &MatchStatsColumnHeader), Without<MatchAwardsText>>,
+    mut awards_query: Query<&mut Text, With<MatchAwardsText>>,
+    added_body_query: Query<(), Added<MatchStatsTableBody>>,
+) {
+    if !(view.is_changed() || players.is_changed() || team_colors.is_changed())
+        && added_body_query.is_empty()
+    {
+        return;
+    }
+
+    for mut style in panel_query.iter_mut() {
+        style.display = if view.shown {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+
+    for (mut text, header) in header_query.iter_mut() {
+        let arrow = match (header.0 == view.sort_by, view.ascending) {
+            (false, _) => "",
+            (true, true) => " ^",
+            (true, false) => " v",
+        };
+        text.sections[0].value = format!("{}{}", header.0.title(), arrow);
+    }
+
+    let awards = match_awards(&players, frame.0)
+        .into_iter()
+        .filter_map(|award| {
+            let name = &players.get(award.player_handle)?.name;
+            Some(format!("{}: {}", award.title, name))
+        })
+        .collect::<Vec<_>>()
+        .join("   ");
+    for mut text in awards_query.iter_mut() {
+        text.sections[0].value = awards.clone();
+    }
+
+    let mut rows: Vec<_> = players
+        .iter()
+        .enumerate()
+        .map(|(handle, player)| StatRow::new(handle, player, &team_colors, frame.0))
+        .collect();
+    rows.sort_by(|a, b| {
+        let ordering = a.compare(b, view.sort_by);
+        if view.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    });
+
+    let font = fonts::load(&asset_server, fonts::ULTRAGONIC);
+    for body in body_query.iter() {
+        commands.entity(body).despawn_descendants();
+        commands.entity(body).with_children(|parent| {
+            for row in rows.iter() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::width(Val::Percent(100.)),
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for column in StatColumn::ALL {
+                            let color = if column == StatColumn::Name {
+                                row.color
+                            } else {
+                                Color::WHITE
+                            };
+                            parent.spawn(TextBundle {
+                                text: Text::from_section(
+                                    row.cell(column),
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: TABLE_FONT_SIZE,
+                                        color,
+                                    },
+                                )
+                                .with_alignment(TextAlignment::Center),
+                                style: Style {
+                                    size: Size::width(Val::Percent(column.width())),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            });
+                        }
+                    });
+            }
+        });
+    }
+}
+
+fn reset_match_stats_view(mut view: ResMut<MatchStatsView>) {
+    *view = MatchStatsView::default();
+}
+
+/// Plugin of the stats table on the match end screen.
+pub(crate) struct MatchStatsTablePlugin;
+impl Plugin for MatchStatsTablePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchStatsView>()
+            .add_systems(
+                (reset_match_stats_view, setup_match_stats_panel)
+                    .in_schedule(OnEnter(MenuState::MatchEnd)),
+            )
+            .add_system(despawn_node::<MatchStatsPanel>.in_schedule(OnExit(MenuState::MatchEnd)))
+            .add_systems(
+                (
+                    handle_match_stats_actions,
+                    handle_match_stats_display_change,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(MenuState::MatchEnd)),
+            );
+    }
+}
@@ -1,15 +1,35 @@
+use crate::controls::bindings::{InputAction, InputBindings};
+use crate::controls::devices::{ConnectedGamepads, LocalInputDevice};
+use crate::network::lobby::{LocalLobbyStatus, MatchSettings};
+use crate::network::moderation::MAX_PLAYER_NAME_LENGTH;
+use crate::network::rooms::{host_public_room, join_room, RoomInfo, RoomListing};
+use crate::network::session::SessionInterruption;
 use crate::network::PlayerCount;
 use crate::ui::color_interaction::ColorInteractionMap;
 use crate::ui::focus::{Focus, KeyToButtonBinding};
 use crate::ui::input_consumption::{MATCH_END_INPUT_LAYER, PAUSE_INPUT_LAYER};
-use crate::ui::lobby::PeerWaitingText;
+use crate::ui::lobby::{
+    match_settings_text, ready_text, LobbyButtonAction, LobbyPlayerList, MatchSettingsDisplay,
+    PeerWaitingText, ReadyButton,
+};
+use crate::ui::match_stats::spawn_match_stats_toggle_button;
 use crate::ui::menu_builder::{
     DEFAULT_BUTTON_COLOR, DEFAULT_BUTTON_HOVERED_COLOR, DEFAULT_BUTTON_PRESSED_COLOR,
     DEFAULT_FONT_SIZE, DEFAULT_OUTLINE_THICKNESS, DEFAULT_TEXT_COLOR, DEFAULT_TEXT_INPUT_MARGIN,
 };
+use crate::ui::profile::{
+    lifetime_totals_text, profile_color_text, recent_matches_text, LifetimeTotalsDisplay, Profile,
+    ProfileButtonAction, ProfileColorDisplay, RecentMatchesDisplay,
+};
+use crate::ui::rebinding::{
+    bindings_text, control_schemes_text, BindingCapture, BindingStatusDisplay, BindingsDisplay,
+    ControlSchemeDisplay,
+};
 use crate::ui::score::{TotalScoreDisplay, VictoryText};
 use crate::ui::text_input::TextInput;
-use crate::ui::user_settings::{transfer_setting_from_text_input, UserInputForm, UserSettings};
+use crate::ui::user_settings::{
+    transfer_setting_from_text_input, UserInputForm, UserSettings, VolumeChannel,
+};
 use crate::ui::{colors, despawn_node, fonts};
 use crate::{build_menu_plugin, GamePauseEvent, GameState, LimboState, SceneSelector};
 #[cfg(not(target_arch = "wasm32"))]
@@ -40,11 +60,14 @@ generate_menu_states!(
     SinglePlayer,
     MultiPlayer,
     MatchMaker,
-    // MatchBrowser,
+    MatchBrowser,
     // Test,
     Controls,
     // Tutorial,
     Settings,
+    SettingsSound,
+    SettingsTouch,
+    Profile,
     Pause,
     MatchEnd,
     MatchmakingLobby,
@@ -82,16 +105,36 @@ impl<T> Default for SingleMenuPlugin<T> {
 pub(crate) enum MenuButtonAction {
     SinglePlayer,
     MultiPlayer,
-    // Quickmatch?
-    // Match Browser?
+    QuickMatch,
+    MatchBrowser,
+    RefreshRooms,
+    JoinRoom(RoomInfo),
     JoinGame,
-    // HostGame,
+    ToggleRoomVisibility,
     SelectScene(SceneSelector),
+    ChangeLocalPlayerCount(isize),
     StartGame,
     Resume,
     Rematch,
     Controls,
+    ToggleControlScheme(usize),
+    Rebind(InputAction),
+    ClearBindings(InputAction),
+    ResetBindings,
     Settings,
+    SettingsSound,
+    ChangeVolume(VolumeChannel, f32),
+    ToggleReducedEffects,
+    ToggleAimPreview,
+    ToggleTeamPalette,
+    ToggleCharacterClass,
+    ToggleChatFilter,
+    ChangeInputDelay(isize),
+    SettingsTouch,
+    ToggleTouchControls,
+    ToggleTouchLayout,
+    ChangeTouchOpacity(f32),
+    Profile,
     BackToMenu,
     QuitToTitle,
     #[cfg(not(target_arch = "wasm32"))]
@@ -99,7 +142,7 @@ pub(crate) enum MenuButtonAction {
 }
 
 build_menu_plugin!(
-    (setup_main_menu, Main),
+    (setup_main_menu(interruption: Res<SessionInterruption>), Main),
     once align_items = AlignItems::Start.into(),
     once layout_height = Val::Percent(42.5).into(),
     Column {
@@ -124,6 +167,9 @@ build_menu_plugin!(
     },
     Bottom {
         Column {
+            once text_font_size = 24.0,
+            once text_color = colors::CORAL,
+            Text [ interruption.0.clone().unwrap_or_default(), ],
             Buttons [
                 (MenuButtonAction::SinglePlayer, "Singleplayer"),
                 (MenuButtonAction::MultiPlayer, "Multiplayer"),
@@ -133,6 +179,7 @@ build_menu_plugin!(
                 button_text_hovered_color = Some(colors::LEMON.into()),
                 button_font_size = 24.0,
                 Buttons [
+                    (MenuButtonAction::Profile, "Profile"),
                     (MenuButtonAction::Controls, "Controls"),
                     (MenuButtonAction::Settings, "Settings"),
                 ],
@@ -370,6 +417,8 @@ fn setup_match_end_menu(
                             ));
                         });
 
+                    spawn_match_stats_toggle_button(parent, button_text_style.clone());
+
                     parent
                         .spawn((
                             button_bundle,
@@ -401,8 +450,23 @@ impl Plugin for SingleMenuPlugin<menu_state::MatchEnd> {
     }
 }
 
+/// Marker of the text showing the number of local players and their input devices.
+#[derive(Component)]
+struct LocalPlayersDisplay;
+
+/// Format the number of local players along with the devices they'd be playing with.
+fn local_players_text(user_settings: &UserSettings, gamepads: &ConnectedGamepads) -> String {
+    let count = user_settings.local_player_count();
+    let devices = LocalInputDevice::assign(count, gamepads)
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} ({})", count, devices)
+}
+
 build_menu_plugin!(
-    (setup_singleplayer_menu, SinglePlayer),
+    (setup_singleplayer_menu(user_settings: Res<UserSettings>, gamepads: Res<ConnectedGamepads>), SinglePlayer),
     once align_self = AlignSelf::Start.into(),
     once layout_height = Val::Percent(50.).into(),
     Column {
@@ -426,6 +490,14 @@ build_menu_plugin!(
     },
     Bottom {
         Column {
+            Node {
+                button_width = Val::Px(65.0),
+                Text [ "Local players: ", local_players_text(&user_settings, &gamepads), ] + (LocalPlayersDisplay,),
+                Buttons [
+                    (MenuButtonAction::ChangeLocalPlayerCount(-1), "-"),
+                    (MenuButtonAction::ChangeLocalPlayerCount(1), "+"),
+                ],
+            },
             Buttons [
                 (MenuButtonAction::StartGame, "Start Game"),
                 (MenuButtonAction::BackToMenu, "Back") + (
@@ -458,7 +530,7 @@ build_menu_plugin!(
                 button_height = Val::Px(DEFAULT_FONT_SIZE + DEFAULT_TEXT_INPUT_MARGIN * 2.),
                 // font = fonts::FIRA_SANS,
                 TextInput [
-                    max_symbols: 24,
+                    max_symbols: MAX_PLAYER_NAME_LENGTH,
                     placeholder: "Anata no namae wa..?",
                     user_settings.player_name.clone(),
                 ] + (
@@ -481,6 +553,19 @@ build_menu_plugin!(
                     UserInputForm::ServerUrl,
                 ),
             },
+            Column {
+                margin = UiRect::all(Val::Px(DEFAULT_OUTLINE_THICKNESS * 0.5)).into(),
+                Text [ "Room listing URL", ],
+                button_width = Val::Percent(100.),
+                button_height = Val::Px(DEFAULT_FONT_SIZE + DEFAULT_TEXT_INPUT_MARGIN * 2.),
+                // font = fonts::FIRA_SANS,
+                TextInput [
+                    placeholder: "URL of the room listing",
+                    user_settings.room_listing_url.clone(),
+                ] + (
+                    UserInputForm::RoomListingUrl,
+                ),
+            },
             Column {
                 margin = UiRect::all(Val::Px(DEFAULT_OUTLINE_THICKNESS * 0.5)).into(),
                 Text [ "Room name", ],
@@ -498,8 +583,15 @@ build_menu_plugin!(
     },
     Bottom {
         Column {
+            Node {
+                button_width = Val::Px(270.0),
+                Buttons [
+                    (MenuButtonAction::QuickMatch, "Quick match") + (RoomListingButton),
+                    (MenuButtonAction::MatchBrowser, "Browse rooms") + (RoomListingButton),
+                    (MenuButtonAction::JoinGame, "Host or join"),
+                ],
+            },
             Buttons [
-                (MenuButtonAction::JoinGame, "Continue"),
                 (MenuButtonAction::BackToMenu, "Back") + (
                     KeyToButtonBinding(KeyCode::Escape)
                 ),
@@ -508,8 +600,162 @@ build_menu_plugin!(
     },
 );
 
+/// Marker of the buttons leading to the room listing, shown only while there is a listing to go to.
+#[derive(Component)]
+struct RoomListingButton;
+
+/// System to show the room listing buttons only while the room listing URL being typed in is not empty.
+fn handle_room_listing_buttons_display_change(
+    input_query: Query<(&TextInput, &UserInputForm), Changed<TextInput>>,
+    added_button_query: Query<(), Added<RoomListingButton>>,
+    all_input_query: Query<(&TextInput, &UserInputForm)>,
+    mut button_query: Query<&mut Style, With<RoomListingButton>>,
+) {
+    if input_query.is_empty() && added_button_query.is_empty() {
+        return;
+    }
+    let listing_enabled = all_input_query
+        .iter()
+        .find(|(_, form)| **form == UserInputForm::RoomListingUrl)
+        .map_or(false, |(input, _)| !input.text.trim().is_empty());
+    for mut style in button_query.iter_mut() {
+        style.display = if listing_enabled {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+/// Marker of the text telling how the fetching of the rooms went.
+#[derive(Component)]
+struct RoomListingStatusDisplay;
+
+/// Marker of the node listing the open rooms as buttons to join them with.
+#[derive(Component)]
+struct RoomListDisplay;
+
+/// Format a room to be displayed in the room list.
+fn room_text(room: &RoomInfo) -> String {
+    format!(
+        "{}  {}/{}  {:?}  {}",
+        room.name,
+        room.players,
+        room.max_players,
+        room.scene,
+        room.mode.name()
+    )
+}
+
 build_menu_plugin!(
-    (setup_multiplayer_creation_menu, MatchMaker),
+    (setup_match_browser_menu(room_listing: Res<RoomListing>), MatchBrowser),
+    Top {
+        Column {
+            Text [ "Rooms", ],
+        },
+    },
+    once layout_height = Val::Percent(50.).into(),
+    once layout_width = Val::Percent(75.).into(),
+    Column {
+        Text [ room_listing.status_text(), ] + (RoomListingStatusDisplay,),
+        ((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            RoomListDisplay,
+        )),
+    },
+    Bottom {
+        Column {
+            Node {
+                button_width = Val::Px(270.0),
+                Buttons [
+                    (MenuButtonAction::RefreshRooms, "Refresh"),
+                    (MenuButtonAction::QuickMatch, "Quick match"),
+                ],
+            },
+            Buttons [
+                (MenuButtonAction::MultiPlayer, "Back") + (
+                    KeyToButtonBinding(KeyCode::Escape)
+                ),
+            ],
+        },
+    },
+);
+
+/// System to fetch the rooms anew every time the room browser is opened.
+fn refresh_room_listing(mut room_listing: ResMut<RoomListing>, user_settings: Res<UserSettings>) {
+    room_listing.refresh(&user_settings.room_listing_url);
+}
+
+/// System to keep the room listing's status and the list of the rooms up to date.
+fn handle_room_listing_display_change(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    room_listing: Res<RoomListing>,
+    mut status_text_query: Query<&mut Text, With<RoomListingStatusDisplay>>,
+    room_list_query: Query<Entity, With<RoomListDisplay>>,
+    added_room_list_query: Query<(), Added<RoomListDisplay>>,
+) {
+    if !room_listing.is_changed() && added_room_list_query.is_empty() {
+        return;
+    }
+    for mut text in status_text_query.iter_mut() {
+        text.sections[0].value = room_listing.status_text();
+    }
+    for room_list in room_list_query.iter() {
+        commands.entity(room_list).despawn_descendants();
+        commands.entity(room_list).with_children(|parent| {
+            let mut menu_env =
+                crate::ui::menu_builder::MenuBuildingEnvironment::default(&asset_server);
+            menu_env.button_width = Val::Px(640.0);
+            menu_env.button_height = Val::Px(45.0);
+            menu_env.button_font_size = 20.0;
+            for room in room_listing.rooms.iter() {
+                crate::build_menu_item!(
+                    parent,
+                    menu_env,
+                    Buttons,
+                    (),
+                    (MenuButtonAction::JoinRoom(room.clone()), room_text(room))
+                );
+            }
+        });
+    }
+}
+
+/// Marker of the text showing whether the hosted rooms get listed.
+#[derive(Component)]
+struct RoomVisibilityDisplay;
+
+fn room_visibility_text(user_settings: &UserSettings) -> &'static str {
+    if user_settings.public_room {
+        "Public"
+    } else {
+        "Private"
+    }
+}
+
+/// System to keep the room visibility setting display up to date.
+fn handle_room_visibility_display_change(
+    user_settings: Res<UserSettings>,
+    mut visibility_text_query: Query<&mut Text, With<RoomVisibilityDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in visibility_text_query.iter_mut() {
+        text.sections[1].value = room_visibility_text(&user_settings).to_string();
+    }
+}
+
+build_menu_plugin!(
+    (setup_multiplayer_creation_menu(user_settings: Res<UserSettings>, gamepads: Res<ConnectedGamepads>), MatchMaker),
     once align_self = AlignSelf::Start.into(),
     once layout_height = Val::Percent(50.).into(),
     Column {
@@ -533,6 +779,19 @@ build_menu_plugin!(
     },
     Bottom {
         Column {
+            Node {
+                button_width = Val::Px(65.0),
+                Text [ "Local players: ", local_players_text(&user_settings, &gamepads), ] + (LocalPlayersDisplay,),
+                Buttons [
+                    (MenuButtonAction::ChangeLocalPlayerCount(-1), "-"),
+                    (MenuButtonAction::ChangeLocalPlayerCount(1), "+"),
+                ],
+            },
+            Node {
+                Text [ "Room: ", room_visibility_text(&user_settings), ] + (RoomVisibilityDisplay,),
+                once button_width = Val::Px(130.0),
+                Buttons [ (MenuButtonAction::ToggleRoomVisibility, "Toggle"), ],
+            },
             Buttons [
                 (MenuButtonAction::StartGame, "Start Game"),
                 (MenuButtonAction::MultiPlayer, "Back") + (
@@ -543,17 +802,106 @@ build_menu_plugin!(
     },
 );
 
+/// Marker of the text showing whether the visual effects are reduced.
+#[derive(Component)]
+struct ReducedEffectsDisplay;
+
+/// Marker of the text showing whether the aim preview is shown.
+#[derive(Component)]
+struct AimPreviewDisplay;
+
+/// Marker of the text showing the class the local players spawn as.
+#[derive(Component)]
+struct CharacterClassDisplay;
+
+/// Marker of the text showing the palette the teams are colored with.
+#[derive(Component)]
+struct TeamPaletteDisplay;
+
+/// Marker of the text showing whether the peers' chat messages are filtered.
+#[derive(Component)]
+struct ChatFilterDisplay;
+
+/// Marker of the text showing the input delay of the online matches.
+#[derive(Component)]
+struct InputDelayDisplay;
+
+fn input_delay_text(user_settings: &UserSettings) -> String {
+    match user_settings.input_delay {
+        Some(frames) => format!("{} frames", frames),
+        None => "Auto".to_string(),
+    }
+}
+
+fn reduced_effects_text(user_settings: &UserSettings) -> &'static str {
+    if user_settings.reduced_effects {
+        "Reduced"
+    } else {
+        "Full"
+    }
+}
+
+fn aim_preview_text(user_settings: &UserSettings) -> &'static str {
+    if user_settings.aim_preview {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn chat_filter_text(user_settings: &UserSettings) -> &'static str {
+    if user_settings.chat_filter {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
 build_menu_plugin!(
-    (setup_settings_menu, Settings),
+    (setup_settings_menu(user_settings: Res<UserSettings>), Settings),
+    Top {
+        Column {
+            Text [ "Settings", ],
+        },
+    },
     Column {
-        Text [
-            "Haha you expected settings, but it was me, ",
-            once text_color = colors::LEMON,
-            "Dio",
-            "!\n",
-            text_color = colors::LAVENDER,
-            "Go back to the playroom, stud.\n",
+        Buttons [
+            (MenuButtonAction::SettingsSound, "Sound"),
+            (MenuButtonAction::SettingsTouch, "Touch controls"),
         ],
+        Node {
+            Text [ "Effects: ", reduced_effects_text(&user_settings), ] + (ReducedEffectsDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleReducedEffects, "Toggle"), ],
+        },
+        Node {
+            Text [ "Aim preview: ", aim_preview_text(&user_settings), ] + (AimPreviewDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleAimPreview, "Toggle"), ],
+        },
+        Node {
+            Text [ "Class: ", user_settings.character_class.name(), ] + (CharacterClassDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleCharacterClass, "Toggle"), ],
+        },
+        Node {
+            Text [ "Team colors: ", user_settings.team_palette.name(), ] + (TeamPaletteDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleTeamPalette, "Toggle"), ],
+        },
+        Node {
+            Text [ "Chat filter: ", chat_filter_text(&user_settings), ] + (ChatFilterDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleChatFilter, "Toggle"), ],
+        },
+        Node {
+            button_width = Val::Px(65.0),
+            Text [ "Input delay: ", input_delay_text(&user_settings), ] + (InputDelayDisplay,),
+            Buttons [
+                (MenuButtonAction::ChangeInputDelay(-1), "-"),
+                (MenuButtonAction::ChangeInputDelay(1), "+"),
+            ],
+        },
     },
     Bottom {
         Buttons [
@@ -565,7 +913,42 @@ build_menu_plugin!(
 );
 
 build_menu_plugin!(
-    (setup_controls_menu, Controls),
+    (setup_profile_menu(user_settings: Res<UserSettings>, profile: Res<Profile>), Profile),
+    Top {
+        Column {
+            Text [ "Profile", ],
+        },
+    },
+    once layout_width = Val::Percent(75.).into(),
+    Column {
+        Column {
+            margin = UiRect::all(Val::Px(DEFAULT_OUTLINE_THICKNESS * 0.5)).into(),
+            Text [ "Player name", ],
+            button_height = Val::Px(DEFAULT_FONT_SIZE + DEFAULT_TEXT_INPUT_MARGIN * 2.),
+            TextInput [
+                max_symbols: MAX_PLAYER_NAME_LENGTH,
+                placeholder: "Anata no namae wa..?",
+                user_settings.player_name.clone(),
+            ] + (
+                UserInputForm::PlayerName,
+            ),
+        },
+        Node {
+            Text [ "Colour: ", profile_color_text(&profile), ] + (ProfileColorDisplay,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (ProfileButtonAction::CycleColor, "Change"), ],
+        },
+        Text [ lifetime_totals_text(&profile), ] + (LifetimeTotalsDisplay,),
+        once text_font_size = 22.0,
+        Text [ recent_matches_text(&profile, 0), ] + (RecentMatchesDisplay,),
+        Node {
+            button_width = Val::Px(65.0),
+            Buttons [
+                (ProfileButtonAction::ChangeMatchPage(-1), "<"),
+                (ProfileButtonAction::ChangeMatchPage(1), ">"),
+            ],
+        },
+    },
     Bottom {
         Buttons [
             (MenuButtonAction::BackToMenu, "Back") + (
@@ -575,15 +958,384 @@ build_menu_plugin!(
     },
 );
 
+/// Difference in volume a single press of a volume button makes.
+const VOLUME_STEP: f32 = 0.1;
+
+/// Format the volume of a channel to be displayed in the sound settings.
+fn volume_text(user_settings: &UserSettings, channel: VolumeChannel) -> String {
+    format!("{:.0}%", user_settings.volume(channel) * 100.)
+}
+
 build_menu_plugin!(
-    (setup_matchmaking_lobby_menu, MatchmakingLobby),
+    (setup_sound_settings_menu(user_settings: Res<UserSettings>), SettingsSound),
+    Top {
+        Column {
+            Text [ "Sound", ],
+        },
+    },
+    once layout_width = Val::Percent(75.).into(),
     Column {
-        Text [ "Waiting for ", "", " more peer", "s", " to join...", ] + (
-            PeerWaitingText { number_section_idx: 1, plurality_section_idx: 3 },
-        ),
+        button_width = Val::Px(65.0),
+        Node {
+            Text [ "Master", ],
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Master, -VOLUME_STEP), "-"), ],
+            Text [ volume_text(&user_settings, VolumeChannel::Master), ] + (VolumeChannel::Master,),
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Master, VOLUME_STEP), "+"), ],
+        },
+        Node {
+            Text [ "Effects", ],
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Effects, -VOLUME_STEP), "-"), ],
+            Text [ volume_text(&user_settings, VolumeChannel::Effects), ] + (VolumeChannel::Effects,),
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Effects, VOLUME_STEP), "+"), ],
+        },
+        Node {
+            Text [ "Music", ],
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Music, -VOLUME_STEP), "-"), ],
+            Text [ volume_text(&user_settings, VolumeChannel::Music), ] + (VolumeChannel::Music,),
+            Buttons [ (MenuButtonAction::ChangeVolume(VolumeChannel::Music, VOLUME_STEP), "+"), ],
+        },
+    },
+    Bottom {
+        Buttons [
+            (MenuButtonAction::Settings, "Back") + (
+                KeyToButtonBinding(KeyCode::Escape)
+            ),
+        ],
+    },
+);
+
+/// Difference in opacity a single press of an opacity button makes.
+const OPACITY_STEP: f32 = 0.1;
+
+/// Touch controls setting displayed by a text, as a component.
+#[derive(Component, Clone, Copy)]
+enum TouchControlsDisplay {
+    Mode,
+    Layout,
+    Opacity,
+}
+
+/// Format a touch controls setting to be displayed in the touch controls settings.
+fn touch_controls_text(user_settings: &UserSettings, display: TouchControlsDisplay) -> String {
+    match display {
+        TouchControlsDisplay::Mode => user_settings.touch_controls.name().to_string(),
+        TouchControlsDisplay::Layout => user_settings.touch_layout.name().to_string(),
+        TouchControlsDisplay::Opacity => format!("{:.0}%", user_settings.touch_opacity * 100.),
+    }
+}
+
+build_menu_plugin!(
+    (setup_touch_settings_menu(user_settings: Res<UserSettings>), SettingsTouch),
+    Top {
+        Column {
+            Text [ "Touch controls", ],
+        },
+    },
+    once layout_width = Val::Percent(75.).into(),
+    Column {
+        Node {
+            Text [ "Show: ", touch_controls_text(&user_settings, TouchControlsDisplay::Mode), ] + (TouchControlsDisplay::Mode,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleTouchControls, "Toggle"), ],
+        },
+        Node {
+            Text [ "Layout: ", touch_controls_text(&user_settings, TouchControlsDisplay::Layout), ] + (TouchControlsDisplay::Layout,),
+            once button_width = Val::Px(130.0),
+            Buttons [ (MenuButtonAction::ToggleTouchLayout, "Toggle"), ],
+        },
+        Node {
+            button_width = Val::Px(65.0),
+            Text [ "Opacity: ", touch_controls_text(&user_settings, TouchControlsDisplay::Opacity), ] + (TouchControlsDisplay::Opacity,),
+            Buttons [
+                (MenuButtonAction::ChangeTouchOpacity(-OPACITY_STEP), "-"),
+                (MenuButtonAction::ChangeTouchOpacity(OPACITY_STEP), "+"),
+            ],
+        },
     },
     Bottom {
         Buttons [
+            (MenuButtonAction::Settings, "Back") + (
+                KeyToButtonBinding(KeyCode::Escape)
+            ),
+        ],
+    },
+);
+
+/// System to keep the touch controls settings displays up to date.
+fn handle_touch_controls_display_change(
+    user_settings: Res<UserSettings>,
+    mut touch_text_query: Query<(&mut Text, &TouchControlsDisplay)>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for (mut text, display) in touch_text_query.iter_mut() {
+        text.sections[1].value = touch_controls_text(&user_settings, *display);
+    }
+}
+
+/// System to keep the visual effects setting display up to date.
+fn handle_reduced_effects_display_change(
+    user_settings: Res<UserSettings>,
+    mut effects_text_query: Query<&mut Text, With<ReducedEffectsDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in effects_text_query.iter_mut() {
+        text.sections[1].value = reduced_effects_text(&user_settings).to_string();
+    }
+}
+
+/// System to keep the aim preview setting display up to date.
+fn handle_aim_preview_display_change(
+    user_settings: Res<UserSettings>,
+    mut preview_text_query: Query<&mut Text, With<AimPreviewDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in preview_text_query.iter_mut() {
+        text.sections[1].value = aim_preview_text(&user_settings).to_string();
+    }
+}
+
+/// System to keep the character class setting display up to date.
+fn handle_character_class_display_change(
+    user_settings: Res<UserSettings>,
+    mut class_text_query: Query<&mut Text, With<CharacterClassDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in class_text_query.iter_mut() {
+        text.sections[1].value = user_settings.character_class.name().to_string();
+    }
+}
+
+/// System to keep the team palette setting display up to date.
+fn handle_team_palette_display_change(
+    user_settings: Res<UserSettings>,
+    mut palette_text_query: Query<&mut Text, With<TeamPaletteDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in palette_text_query.iter_mut() {
+        text.sections[1].value = user_settings.team_palette.name().to_string();
+    }
+}
+
+/// System to keep the chat filter setting display up to date.
+fn handle_chat_filter_display_change(
+    user_settings: Res<UserSettings>,
+    mut filter_text_query: Query<&mut Text, With<ChatFilterDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in filter_text_query.iter_mut() {
+        text.sections[1].value = chat_filter_text(&user_settings).to_string();
+    }
+}
+
+/// System to keep the input delay setting display up to date.
+fn handle_input_delay_display_change(
+    user_settings: Res<UserSettings>,
+    mut input_delay_text_query: Query<&mut Text, With<InputDelayDisplay>>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for mut text in input_delay_text_query.iter_mut() {
+        text.sections[1].value = input_delay_text(&user_settings);
+    }
+}
+
+/// System to keep the number of local players within what the connected devices can serve,
+/// so that nobody is left without a device to play with.
+fn cap_local_player_count(
+    mut user_settings: ResMut<UserSettings>,
+    gamepads: Res<ConnectedGamepads>,
+) {
+    if !(user_settings.is_changed() || gamepads.is_changed()) {
+        return;
+    }
+    let max_count = LocalInputDevice::max_local_players(&gamepads);
+    if user_settings.local_player_count() > max_count {
+        user_settings.set_local_player_count(max_count);
+    }
+}
+
+/// System to keep the local players display up to date, as the count changes and gamepads come and go.
+fn handle_local_players_display_change(
+    user_settings: Res<UserSettings>,
+    gamepads: Res<ConnectedGamepads>,
+    mut local_players_text_query: Query<&mut Text, With<LocalPlayersDisplay>>,
+) {
+    if !(user_settings.is_changed() || gamepads.is_changed()) {
+        return;
+    }
+    for mut text in local_players_text_query.iter_mut() {
+        text.sections[1].value = local_players_text(&user_settings, &gamepads);
+    }
+}
+
+/// System to keep the volume displays in the sound settings up to date.
+fn handle_volume_display_change(
+    user_settings: Res<UserSettings>,
+    mut volume_text_query: Query<(&mut Text, &VolumeChannel)>,
+) {
+    if !user_settings.is_changed() {
+        return;
+    }
+    for (mut text, channel) in volume_text_query.iter_mut() {
+        text.sections[0].value = volume_text(&user_settings, *channel);
+    }
+}
+
+build_menu_plugin!(
+    (setup_controls_menu(user_settings: Res<UserSettings>, binding_capture: Res<BindingCapture>), Controls),
+    Top {
+        Column {
+            Text [ "Controls", ],
+        },
+    },
+    once layout_width = Val::Percent(90.).into(),
+    Column {
+        text_font_size = 20.0,
+        button_font_size = 20.0,
+        button_width = Val::Px(90.0),
+        button_height = Val::Px(32.0),
+        Node {
+            Text [ "Scheme: ", control_schemes_text(&user_settings), ] + (ControlSchemeDisplay,),
+            Buttons [
+                (MenuButtonAction::ToggleControlScheme(0), "P1"),
+                (MenuButtonAction::ToggleControlScheme(1), "P2"),
+                (MenuButtonAction::ToggleControlScheme(2), "P3"),
+                (MenuButtonAction::ToggleControlScheme(3), "P4"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Forward.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Forward), ] + (BindingsDisplay(InputAction::Forward),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Forward), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Forward), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Backward.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Backward), ] + (BindingsDisplay(InputAction::Backward),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Backward), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Backward), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::TurnRight.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::TurnRight), ] + (BindingsDisplay(InputAction::TurnRight),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::TurnRight), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::TurnRight), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::TurnLeft.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::TurnLeft), ] + (BindingsDisplay(InputAction::TurnLeft),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::TurnLeft), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::TurnLeft), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Fire.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Fire), ] + (BindingsDisplay(InputAction::Fire),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Fire), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Fire), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Reload.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Reload), ] + (BindingsDisplay(InputAction::Reload),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Reload), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Reload), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Interact1.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Interact1), ] + (BindingsDisplay(InputAction::Interact1),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Interact1), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Interact1), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Interact2.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Interact2), ] + (BindingsDisplay(InputAction::Interact2),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Interact2), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Interact2), "Clear"),
+            ],
+        },
+        Node {
+            Text [ InputAction::Ability.name(), ],
+            Text [ bindings_text(&user_settings, InputAction::Ability), ] + (BindingsDisplay(InputAction::Ability),),
+            Buttons [
+                (MenuButtonAction::Rebind(InputAction::Ability), "Bind"),
+                (MenuButtonAction::ClearBindings(InputAction::Ability), "Clear"),
+            ],
+        },
+        Text [ binding_capture.status.clone(), ] + (BindingStatusDisplay,),
+    },
+    Bottom {
+        Buttons [
+            (MenuButtonAction::ResetBindings, "Reset to defaults"),
+            (MenuButtonAction::BackToMenu, "Back") + (
+                KeyToButtonBinding(KeyCode::Escape)
+            ),
+        ],
+    },
+);
+
+build_menu_plugin!(
+    (setup_matchmaking_lobby_menu(local_status: Res<LocalLobbyStatus>, match_settings: Res<MatchSettings>), MatchmakingLobby),
+    Top {
+        Column {
+            text_font_size = 28.0,
+            Text [ "Waiting for ", "", " more peer", "s", " to join...", ] + (
+                PeerWaitingText { number_section_idx: 1, plurality_section_idx: 3 },
+            ),
+            Text [ match_settings_text(&match_settings, false), ] + (MatchSettingsDisplay,),
+            Node {
+                button_width = Val::Px(180.0),
+                button_height = Val::Px(40.0),
+                button_font_size = 20.0,
+                Buttons [
+                    (LobbyButtonAction::CycleScene, "Scene"),
+                    (LobbyButtonAction::CycleGameMode, "Mode"),
+                    (LobbyButtonAction::ChangeMatchDuration(-1), "- Minute"),
+                    (LobbyButtonAction::ChangeMatchDuration(1), "+ Minute"),
+                ],
+            },
+        },
+    },
+    ((
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        LobbyPlayerList,
+    )),
+    Bottom {
+        Buttons [
+            (LobbyButtonAction::ToggleReady, ready_text(&local_status)) + (ReadyButton,),
             (MenuButtonAction::QuitToTitle, "Quit to Main Menu") + (
                 KeyToButtonBinding(KeyCode::Escape)
             )
@@ -606,9 +1358,12 @@ impl PluginGroup for MenuSetupPlugins {
             .add(SingleMenuPlugin::<SinglePlayer>::default())
             .add(SingleMenuPlugin::<MultiPlayer>::default())
             .add(SingleMenuPlugin::<MatchMaker>::default())
-            //.add(SingleMenuPlugin::<MatchBrowser>::default())
+            .add(SingleMenuPlugin::<MatchBrowser>::default())
             .add(SingleMenuPlugin::<Controls>::default())
             .add(SingleMenuPlugin::<Settings>::default())
+            .add(SingleMenuPlugin::<SettingsSound>::default())
+            .add(SingleMenuPlugin::<SettingsTouch>::default())
+            .add(SingleMenuPlugin::<Profile>::default())
     }
 }
 
@@ -679,6 +1434,12 @@ pub(crate) fn handle_menu_actions(
     mut game_state: ResMut<NextState<GameState>>,
     mut limbo_state: ResMut<NextState<LimboState>>,
     current_game_state: Res<State<GameState>>,
+    current_menu_state: Res<State<MenuState>>,
+    player_count: Option<Res<PlayerCount>>,
+    mut user_settings: ResMut<UserSettings>,
+    mut binding_capture: ResMut<BindingCapture>,
+    mut room_listing: ResMut<RoomListing>,
+    gamepads: Res<ConnectedGamepads>,
 ) {
     for (interaction, menu_button_action, entity) in &interaction_query {
         if *interaction == Interaction::Clicked {
@@ -693,7 +1454,26 @@ pub(crate) fn handle_menu_actions(
                     commands.insert_resource(PlayerCount(2));
                     menu_state.set(MenuState::MultiPlayer)
                 }
+                MenuButtonAction::QuickMatch => {
+                    if current_menu_state.0 == MenuState::MatchBrowser {
+                        room_listing.quick_match(&user_settings.room_listing_url);
+                    } else {
+                        // the listing gets fetched on entering the browser, once the settings are in
+                        room_listing.quick_match = true;
+                        menu_state.set(MenuState::MatchBrowser);
+                    }
+                }
+                MenuButtonAction::MatchBrowser => menu_state.set(MenuState::MatchBrowser),
+                MenuButtonAction::RefreshRooms => {
+                    room_listing.refresh(&user_settings.room_listing_url);
+                }
+                MenuButtonAction::JoinRoom(room) => {
+                    join_room(&mut commands, room, &mut game_state, &mut menu_state)
+                }
                 MenuButtonAction::JoinGame => menu_state.set(MenuState::MatchMaker),
+                MenuButtonAction::ToggleRoomVisibility => {
+                    user_settings.public_room = !user_settings.public_room;
+                }
                 MenuButtonAction::SelectScene(scene) => {
                     for mut focus in scene_focus_query.iter_mut() {
                         if let Focus::Focused(_) = *focus {
@@ -704,6 +1484,13 @@ pub(crate) fn handle_menu_actions(
                         .entity(entity)
                         .insert(Focus::<SceneSelector>::focused(*scene));
                 }
+                MenuButtonAction::ChangeLocalPlayerCount(step) => {
+                    let count = user_settings
+                        .local_player_count()
+                        .saturating_add_signed(*step)
+                        .min(LocalInputDevice::max_local_players(&gamepads));
+                    user_settings.set_local_player_count(count);
+                }
                 MenuButtonAction::StartGame => {
                     let scene_arg = scene_focus_query
                         .iter()
@@ -711,6 +1498,17 @@ pub(crate) fn handle_menu_actions(
 
                     match scene_arg {
                         Some(context) => {
+                            if current_menu_state.0 == MenuState::MatchMaker
+                                && user_settings.public_room
+                                && user_settings.room_listing_enabled()
+                            {
+                                host_public_room(
+                                    &mut commands,
+                                    &user_settings,
+                                    context,
+                                    player_count.as_ref().map_or(2, |count| count.0),
+                                );
+                            }
                             commands.insert_resource(context);
 
                             game_state.set(GameState::Matchmaking);
@@ -727,7 +1525,55 @@ pub(crate) fn handle_menu_actions(
                     limbo_state.set(LimboState::Limbo);
                 }
                 MenuButtonAction::Controls => menu_state.set(MenuState::Controls),
+                MenuButtonAction::ToggleControlScheme(local_player) => {
+                    user_settings.toggle_control_scheme(*local_player);
+                }
+                MenuButtonAction::Rebind(action) => binding_capture.start(*action),
+                MenuButtonAction::ClearBindings(action) => user_settings.bindings.clear(*action),
+                MenuButtonAction::ResetBindings => {
+                    user_settings.bindings = InputBindings::default();
+                    binding_capture.status = "Controls reset to defaults".to_string();
+                }
                 MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
+                MenuButtonAction::SettingsSound => menu_state.set(MenuState::SettingsSound),
+                MenuButtonAction::ChangeVolume(channel, step) => {
+                    let volume = user_settings.volume(*channel) + step;
+                    // round off the float errors accumulated from stepping
+                    user_settings
+                        .set_volume(*channel, (volume / VOLUME_STEP).round() * VOLUME_STEP);
+                }
+                MenuButtonAction::ToggleReducedEffects => {
+                    user_settings.reduced_effects = !user_settings.reduced_effects;
+                }
+                MenuButtonAction::ToggleAimPreview => {
+                    user_settings.aim_preview = !user_settings.aim_preview;
+                }
+                MenuButtonAction::ToggleCharacterClass => {
+                    user_settings.character_class = user_settings.character_class.next();
+                }
+                MenuButtonAction::ToggleTeamPalette => {
+                    user_settings.team_palette = user_settings.team_palette.next();
+                }
+                MenuButtonAction::ToggleChatFilter => {
+                    user_settings.chat_filter = !user_settings.chat_filter;
+                }
+                MenuButtonAction::ChangeInputDelay(change) => {
+                    user_settings.change_input_delay(*change);
+                }
+                MenuButtonAction::SettingsTouch => menu_state.set(MenuState::SettingsTouch),
+                MenuButtonAction::ToggleTouchControls => {
+                    user_settings.touch_controls = user_settings.touch_controls.next();
+                }
+                MenuButtonAction::ToggleTouchLayout => {
+                    user_settings.touch_layout = user_settings.touch_layout.next();
+                }
+                MenuButtonAction::ChangeTouchOpacity(step) => {
+                    let opacity = user_settings.touch_opacity + step;
+                    // round off the float errors accumulated from stepping
+                    user_settings
+                        .set_touch_opacity((opacity / OPACITY_STEP).round() * OPACITY_STEP);
+                }
+                MenuButtonAction::Profile => menu_state.set(MenuState::Profile),
                 MenuButtonAction::BackToMenu => {
                     if current_game_state.0 == GameState::InGame {
                         menu_state.set(MenuState::Pause)
@@ -769,6 +1615,37 @@ impl Plugin for MenuPlugin {
             .add_systems((
                 transfer_setting_from_text_input.in_schedule(OnExit(MenuState::Settings)),
                 transfer_setting_from_text_input.in_schedule(OnExit(MenuState::MultiPlayer)),
+                transfer_setting_from_text_input.in_schedule(OnExit(MenuState::Profile)),
+            ))
+            .add_system(
+                handle_room_listing_buttons_display_change.run_if(in_state(MenuState::MultiPlayer)),
+            )
+            .add_system(refresh_room_listing.in_schedule(OnEnter(MenuState::MatchBrowser)))
+            .add_system(
+                handle_room_listing_display_change.run_if(in_state(MenuState::MatchBrowser)),
+            )
+            .add_system(
+                handle_room_visibility_display_change.run_if(in_state(MenuState::MatchMaker)),
+            )
+            .add_system(handle_reduced_effects_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_aim_preview_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_character_class_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_team_palette_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_chat_filter_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_input_delay_display_change.run_if(in_state(MenuState::Settings)))
+            .add_system(handle_volume_display_change.run_if(in_state(MenuState::SettingsSound)))
+            .add_system(
+                handle_touch_controls_display_change.run_if(in_state(MenuState::SettingsTouch)),
+            )
+            .add_system(
+                cap_local_player_count
+                    .before(handle_local_players_display_change)
+                    .run_if(
+                        in_state(MenuState::SinglePlayer).or_else(in_state(MenuState::MatchMaker)),
+                    ),
+            )
+            .add_system(handle_local_players_display_change.run_if(
+                in_state(MenuState::SinglePlayer).or_else(in_state(MenuState::MatchMaker)),
             ));
         // Systems to handle the display settings screen
         /*.add_systems(
@@ -786,16 +1663,6 @@ impl Plugin for MenuPlugin {
             OnExit(MenuState::SettingsDisplay),
             despawn_screen::<OnDisplaySettingsMenuScreen>,
         )
-        // Systems to handle the sound settings screen
-        .add_systems(OnEnter(MenuState::SettingsSound), sound_settings_menu_setup)
-        .add_systems(
-            CoreSet::Update,
-            setting_button::<Volume>.run_if(in_state(MenuState::SettingsSound)),
-        )
-        .add_systems(
-            OnExit(MenuState::SettingsSound),
-            despawn_screen::<OnSoundSettingsMenuScreen>,
-        )
         // Common systems to all screens that handles buttons behavior
         .add_systems(
             CoreSet::Update,
@@ -2,26 +2,36 @@ use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 use chat::ChatPlugin;
 use color_interaction::ColorInteractionPlugin;
+use combat_feedback::CombatFeedbackPlugin;
 use focus::FocusPlugin;
 use hud::HUDPlugin;
 use input_consumption::InputConsumptionPlugin;
 use lobby::LobbyPlugin;
+use match_stats::MatchStatsTablePlugin;
 use menu::MenuPlugin;
+use profile::ProfilePlugin;
+use rebinding::RebindingPlugin;
 use score::PlayerScorePlugin;
 use text_input::TextInputPlugin;
+use touch::TouchControlsPlugin;
 use user_settings::UserSettingsPlugin;
 
 pub mod chat;
 pub mod clipboard_util;
 pub mod color_interaction;
+pub mod combat_feedback;
 pub mod focus;
 pub mod hud;
 pub mod input_consumption;
 pub mod lobby;
+pub mod match_stats;
 pub mod menu;
 mod menu_builder;
+pub mod profile;
+mod rebinding;
 pub mod score;
 pub mod text_input;
+pub mod touch;
 pub mod user_settings;
 
 pub use menu::MenuState;
@@ -39,15 +49,20 @@ impl PluginGroup for UIPlugins {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
             .add(UserSettingsPlugin)
+            .add(ProfilePlugin)
             .add(InputConsumptionPlugin)
             .add(MenuPlugin)
+            .add(RebindingPlugin)
             .add(LobbyPlugin)
             .add(FocusPlugin)
             .add(ColorInteractionPlugin)
             .add(TextInputPlugin)
             .add(ChatPlugin)
             .add(PlayerScorePlugin)
+            .add(MatchStatsTablePlugin)
             .add(HUDPlugin)
+            .add(CombatFeedbackPlugin)
+            .add(TouchControlsPlugin)
     }
 }
 
@@ -0,0 +1,392 @@
+//! The local player's profile, carrying their lifetime totals and the latest matches between launches.
+
+use crate::network::lobby::{GameMode, MatchSettings};
+use crate::network::players::PlayerData;
+use crate::network::session::LocalPlayers;
+use crate::network::PlayerRegistry;
+use crate::teams::TeamNumber;
+use crate::ui::chat::unix_timestamp;
+use crate::ui::colors;
+use crate::ui::user_settings::{storage, UserSettings};
+use crate::{MenuState, SceneSelector};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Name the profile is stored under.
+const PROFILE_STORAGE_NAME: &str = "profile";
+
+/// Number of the latest matches the profile remembers.
+const MAX_RECENT_MATCHES: usize = 50;
+/// Number of matches shown at once on the profile screen.
+pub const MATCHES_PER_PAGE: usize = 5;
+
+/// Colours the player can pick to be shown in.
+pub const PROFILE_COLORS: [Color; 8] = [
+    Color::CYAN,
+    Color::CRIMSON,
+    Color::LIME_GREEN,
+    Color::GOLD,
+    Color::PURPLE,
+    colors::NEON_PINK,
+    Color::ORANGE_RED,
+    Color::WHITE,
+];
+
+/// How a finished match went for the player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchResult {
+    Victory,
+    Defeat,
+    Draw,
+}
+
+impl MatchResult {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            MatchResult::Victory => "Victory",
+            MatchResult::Defeat => "Defeat",
+            MatchResult::Draw => "Draw",
+        }
+    }
+}
+
+/// Record of a finished match.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchRecord {
+    /// Seconds since the Unix epoch when the match ended.
+    pub finished_at: f64,
+    pub scene: SceneSelector,
+    pub mode: GameMode,
+    pub result: MatchResult,
+    pub kills: usize,
+    pub deaths: usize,
+    /// Name of the gun the player has killed the most with in the match, if any.
+    pub favourite_gun: Option<String>,
+}
+
+/// Totals of all the finished matches.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeTotals {
+    pub matches: u32,
+    pub wins: u32,
+    pub kills: usize,
+    pub deaths: usize,
+    /// Kills by the name of the gun they were made with.
+    pub gun_kills: BTreeMap<String, u32>,
+}
+
+impl LifetimeTotals {
+    /// Name of the gun the player has killed the most with, if they have killed at all.
+    pub fn favourite_gun(&self) -> Option<&str> {
+        self.gun_kills
+            .iter()
+            .filter(|(_, kills)| **kills > 0)
+            .max_by_key(|(_, kills)| **kills)
+            .map(|(gun, _)| gun.as_str())
+    }
+}
+
+/// Resource of the local player's profile. Stored between launches, like the settings.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Colour the player would rather be shown in, or `None` to go with the team's.
+    pub color: Option<Color>,
+    pub totals: LifetimeTotals,
+    /// The latest finished matches, the most recent first.
+    pub recent_matches: VecDeque<MatchRecord>,
+}
+
+impl Profile {
+    /// Add the finished match to the totals and to the latest matches.
+    pub fn record_match(&mut self, record: MatchRecord, player: &PlayerData) {
+        self.totals.matches += 1;
+        if record.result == MatchResult::Victory {
+            self.totals.wins += 1;
+        }
+        self.totals.kills += record.kills;
+        self.totals.deaths += record.deaths;
+        for (gun_type, usage) in player.stats.guns.iter() {
+            *self
+                .totals
+                .gun_kills
+                .entry(gun_type.stats().name.to_string())
+                .or_default() += usage.kills;
+        }
+
+        self.recent_matches.push_front(record);
+        self.recent_matches.truncate(MAX_RECENT_MATCHES);
+    }
+
+    /// Switch to the next of the [`PROFILE_COLORS`], with the team's colour before the first one.
+    pub fn cycle_color(&mut self) {
+        let index = self
+            .color
+            .and_then(|color| PROFILE_COLORS.iter().position(|c| *c == color));
+        self.color = match index {
+            None if self.color.is_none() => Some(PROFILE_COLORS[0]),
+            Some(index) if index + 1 < PROFILE_COLORS.len() => Some(PROFILE_COLORS[index + 1]),
+            _ => None,
+        };
+    }
+
+    /// Number of the pages the latest matches take up on the profile screen.
+    pub fn match_pages(&self) -> usize {
+        self.recent_matches.len().div_ceil(MATCHES_PER_PAGE).max(1)
+    }
+
+    /// Read the profile saved during one of the previous launches, if there is one and it is readable.
+    pub fn load() -> Option<Self> {
+        let serialized = storage::read(PROFILE_STORAGE_NAME)?;
+        match ron::from_str::<Profile>(&serialized) {
+            Ok(profile) => Some(profile),
+            Err(error) => {
+                warn!(
+                    "Could not read the stored profile, starting anew: {}",
+                    error
+                );
+                None
+            }
+        }
+    }
+
+    /// Write the profile down to be loaded during the next launch.
+    pub fn save(&self) {
+        match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(serialized) => {
+                if let Err(error) = storage::write(PROFILE_STORAGE_NAME, &serialized) {
+                    warn!("Could not save the profile: {}", error);
+                }
+            }
+            Err(error) => warn!("Could not serialize the profile: {}", error),
+        }
+    }
+}
+
+/// Get the team with the most kills, if no other team has as many.
+pub fn winning_team(players: &PlayerRegistry) -> Option<TeamNumber> {
+    let mut team_kills = HashMap::<TeamNumber, usize>::default();
+    for player in players.iter() {
+        *team_kills.entry(player.team.0).or_default() += player.kills;
+    }
+    let best_kills = team_kills.values().copied().max()?;
+    let mut best_teams = team_kills.iter().filter(|(_, kills)| **kills == best_kills);
+    match (best_teams.next(), best_teams.next()) {
+        (Some((team, _)), None) => Some(*team),
+        _ => None,
+    }
+}
+
+/// Format how long ago the time in seconds since the Unix epoch was.
+fn time_ago_text(timestamp: f64) -> String {
+    let minutes = ((unix_timestamp() - timestamp).max(0.0) / 60.0) as u64;
+    match minutes {
+        0 => "just now".to_string(),
+        1..=59 => format!("{} min ago", minutes),
+        60..=1439 => format!("{} h ago", minutes / 60),
+        _ => format!("{} d ago", minutes / 1440),
+    }
+}
+
+pub(crate) fn profile_color_text(profile: &Profile) -> &'static str {
+    match profile.color {
+        Some(_) => "Own",
+        None => "Team",
+    }
+}
+
+pub(crate) fn lifetime_totals_text(profile: &Profile) -> String {
+    let totals = &profile.totals;
+    format!(
+        "Matches: {}   Wins: {}\nKills: {}   Deaths: {}\nFavourite gun: {}",
+        totals.matches,
+        totals.wins,
+        totals.kills,
+        totals.deaths,
+        totals.favourite_gun().unwrap_or("-"),
+    )
+}
+
+pub(crate) fn recent_matches_text(profile: &Profile, page: usize) -> String {
+    if profile.recent_matches.is_empty() {
+        return "No matches played yet".to_string();
+    }
+    let mut lines: Vec<String> = profile
+        .recent_matches
+        .iter()
+        .skip(page * MATCHES_PER_PAGE)
+        .take(MATCHES_PER_PAGE)
+        .map(|record| {
+            format!(
+                "{}  {:?} {}  {}/{}  {}",
+                record.result.name(),
+                record.scene,
+                record.mode.name(),
+                record.kills,
+                record.deaths,
+                time_ago_text(record.finished_at),
+            )
+        })
+        .collect();
+    lines.push(format!("Page {}/{}", page + 1, profile.match_pages()));
+    lines.join("\n")
+}
+
+/// Actions of the buttons on the profile screen.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) enum ProfileButtonAction {
+    CycleColor,
+    /// Flip through the pages of the latest matches.
+    ChangeMatchPage(isize),
+}
+
+/// Resource of the page of the latest matches shown on the profile screen.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct MatchHistoryPage(pub usize);
+
+/// Marker of the text showing the profile's colour.
+#[derive(Component)]
+pub(crate) struct ProfileColorDisplay;
+
+/// Marker of the text showing the lifetime totals.
+#[derive(Component)]
+pub(crate) struct LifetimeTotalsDisplay;
+
+/// Marker of the text listing the latest matches.
+#[derive(Component)]
+pub(crate) struct RecentMatchesDisplay;
+
+/// System to add the match that has just ended to the profile, as it went for the first player on this machine.
+fn record_finished_match(
+    players: Res<PlayerRegistry>,
+    local_players: Option<Res<LocalPlayers>>,
+    match_settings: Res<MatchSettings>,
+    scene: Option<Res<SceneSelector>>,
+    mut profile: ResMut<Profile>,
+) {
+    let Some(player) = local_players
+        .and_then(|local_players| local_players.handles.first().copied())
+        .and_then(|handle| players.get(handle))
+    else {
+        return;
+    };
+
+    let result = match winning_team(&players) {
+        Some(team) if team == player.team.0 => MatchResult::Victory,
+        Some(_) => MatchResult::Defeat,
+        None => MatchResult::Draw,
+    };
+    let favourite_gun = player
+        .stats
+        .guns
+        .iter()
+        .filter(|(_, usage)| usage.kills > 0)
+        .max_by_key(|(_, usage)| usage.kills)
+        .map(|(gun_type, _)| gun_type.stats().name.to_string());
+    let record = MatchRecord {
+        finished_at: unix_timestamp(),
+        scene: scene.map_or(match_settings.scene, |scene| *scene),
+        mode: match_settings.mode,
+        result,
+        kills: player.kills,
+        deaths: player.deaths,
+        favourite_gun,
+    };
+    profile.record_match(record, player);
+}
+
+/// System to keep the profile's name the same as the player name in the settings.
+fn sync_profile_name(settings: Res<UserSettings>, mut profile: ResMut<Profile>) {
+    if settings.is_changed() && profile.name != settings.player_name {
+        profile.name = settings.player_name.clone();
+    }
+}
+
+/// System to act on the buttons of the profile screen.
+fn handle_profile_actions(
+    interaction_query: Query<
+        (&Interaction, &ProfileButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut profile: ResMut<Profile>,
+    mut page: ResMut<MatchHistoryPage>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match *action {
+            ProfileButtonAction::CycleColor => profile.cycle_color(),
+            ProfileButtonAction::ChangeMatchPage(change) => {
+                let last_page = profile.match_pages() - 1;
+                page.0 = page.0.saturating_add_signed(change).min(last_page);
+            }
+        }
+    }
+}
+
+/// System to keep the profile screen up to date.
+fn handle_profile_display_change(
+    profile: Res<Profile>,
+    page: Res<MatchHistoryPage>,
+    mut color_text_query: Query<
+        &mut Text,
+        (
+            With<ProfileColorDisplay>,
+            Without<LifetimeTotalsDisplay>,
+            Without<RecentMatchesDisplay>,
+        ),
+    >,
+    mut totals_text_query: Query<
+        &mut Text,
+        (With<LifetimeTotalsDisplay>, Without<RecentMatchesDisplay>),
+    >,
+    mut matches_text_query: Query<&mut Text, With<RecentMatchesDisplay>>,
+) {
+    if !(profile.is_changed() || page.is_changed()) {
+        return;
+    }
+    for mut text in color_text_query.iter_mut() {
+        text.sections[1].value = profile_color_text(&profile).to_string();
+        text.sections[1].style.color = profile.color.unwrap_or(text.sections[0].style.color);
+    }
+    for mut text in totals_text_query.iter_mut() {
+        text.sections[0].value = lifetime_totals_text(&profile);
+    }
+    for mut text in matches_text_query.iter_mut() {
+        text.sections[0].value = recent_matches_text(&profile, page.0);
+    }
+}
+
+fn reset_match_history_page(mut page: ResMut<MatchHistoryPage>) {
+    page.0 = 0;
+}
+
+/// System to save the profile whenever it changes.
+fn save_profile_on_change(profile: Res<Profile>) {
+    if profile.is_changed() && !profile.is_added() {
+        profile.save();
+    }
+}
+
+/// Plugin handling the [`Profile`] resource: loading it, adding the finished matches to it, saving it, and showing it.
+pub(crate) struct ProfilePlugin;
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Profile::load().unwrap_or_default())
+            .init_resource::<MatchHistoryPage>()
+            .add_system(record_finished_match.in_schedule(OnEnter(MenuState::MatchEnd)))
+            .add_system(sync_profile_name)
+            .add_system(reset_match_history_page.in_schedule(OnEnter(MenuState::Profile)))
+            .add_systems(
+                (handle_profile_actions, handle_profile_display_change)
+                    .chain()
+                    .distributive_run_if(in_state(MenuState::Profile)),
+            )
+            .add_system(save_profile_on_change.in_base_set(CoreSet::Last));
+    }
+}
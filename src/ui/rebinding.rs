@@ -0,0 +1,215 @@
+use crate::controls::bindings::{AxisDirection, InputAction, InputBinding};
+use crate::controls::devices::ConnectedGamepads;
+use crate::ui::menu::MenuState;
+use crate::ui::user_settings::UserSettings;
+use bevy::input::gamepad::{GamepadAxis, GamepadAxisType, GamepadButton};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+/// How far a gamepad axis has to be pushed to be captured as a binding, so that stick drift doesn't get in the way.
+const AXIS_CAPTURE_THRESHOLD: f32 = 0.6;
+
+const AXES_TO_CAPTURE: [GamepadAxisType; 6] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::LeftZ,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+    GamepadAxisType::RightZ,
+];
+
+const REBINDING_HINT: &str = "Press Bind to add an input to an action";
+
+/// Resource keeping track of which action, if any, is waiting for the next pressed input to be bound to it.
+#[derive(Resource, Debug)]
+pub(crate) struct BindingCapture {
+    pub awaiting: Option<InputAction>,
+    /// Message telling the player how the rebinding went.
+    pub status: String,
+}
+
+impl Default for BindingCapture {
+    fn default() -> Self {
+        Self {
+            awaiting: None,
+            status: REBINDING_HINT.to_string(),
+        }
+    }
+}
+
+impl BindingCapture {
+    /// Start waiting for an input to bind to the action.
+    pub fn start(&mut self, action: InputAction) {
+        self.awaiting = Some(action);
+        self.status = format!(
+            "Press any input to bind to {}, or Escape to cancel",
+            action.name()
+        );
+    }
+}
+
+/// Marker of the text listing the inputs bound to an action.
+#[derive(Component, Debug)]
+pub(crate) struct BindingsDisplay(pub InputAction);
+
+/// Marker of the text showing the control schemes of the local players.
+#[derive(Component, Debug)]
+pub(crate) struct ControlSchemeDisplay;
+
+/// Marker of the text telling the player how the rebinding went.
+#[derive(Component, Debug)]
+pub(crate) struct BindingStatusDisplay;
+
+/// Get the text listing the inputs bound to an action.
+pub(crate) fn bindings_text(settings: &UserSettings, action: InputAction) -> String {
+    let bindings = settings.bindings.bindings(action);
+    if bindings.is_empty() {
+        return "-".to_string();
+    }
+    bindings
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Get the text naming the control scheme of every local player, or just the scheme if there's one player.
+pub(crate) fn control_schemes_text(settings: &UserSettings) -> String {
+    let count = settings.local_player_count();
+    if count == 1 {
+        return settings.control_scheme(0).name().to_string();
+    }
+    (0..count)
+        .map(|index| format!("P{} {}", index + 1, settings.control_scheme(index).name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// System to bind the next pressed key, button or pushed gamepad axis to the action awaiting it.
+/// Captured inputs are consumed, so that they don't press anything else on the way.
+fn capture_next_binding(
+    mut capture: ResMut<BindingCapture>,
+    mut settings: ResMut<UserSettings>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    gamepads: Res<ConnectedGamepads>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    let Some(action) = capture.awaiting else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        keyboard.clear_just_pressed(KeyCode::Escape);
+        capture.awaiting = None;
+        capture.status = "Rebinding cancelled".to_string();
+        return;
+    }
+
+    let pressed_key = keyboard.get_just_pressed().next().copied();
+    let pressed_mouse_button = mouse.get_just_pressed().next().copied();
+    let pressed_gamepad_button = gamepad_buttons
+        .get_just_pressed()
+        .find(|button| gamepads.0.contains(&button.gamepad))
+        .copied();
+    let pushed_gamepad_axis = gamepads.0.iter().find_map(|&gamepad| {
+        AXES_TO_CAPTURE.into_iter().find_map(|axis_type| {
+            let value = gamepad_axes.get(GamepadAxis { gamepad, axis_type })?;
+            if value > AXIS_CAPTURE_THRESHOLD {
+                Some((axis_type, AxisDirection::Positive))
+            } else if value < -AXIS_CAPTURE_THRESHOLD {
+                Some((axis_type, AxisDirection::Negative))
+            } else {
+                None
+            }
+        })
+    });
+
+    let binding = if let Some(key) = pressed_key {
+        keyboard.clear_just_pressed(key);
+        InputBinding::Key(key)
+    } else if let Some(button) = pressed_mouse_button {
+        mouse.clear_just_pressed(button);
+        InputBinding::Mouse(button)
+    } else if let Some(button) = pressed_gamepad_button {
+        gamepad_buttons.clear_just_pressed(button);
+        InputBinding::GamepadButton(button.button_type)
+    } else if let Some((axis_type, direction)) = pushed_gamepad_axis {
+        InputBinding::GamepadAxis(axis_type, direction)
+    } else {
+        return;
+    };
+
+    capture.awaiting = None;
+    capture.status = match settings.bindings.bind(action, binding) {
+        Some(conflicting_action) => format!(
+            "{} moved from {} to {}",
+            binding,
+            conflicting_action.name(),
+            action.name()
+        ),
+        None => format!("{} bound to {}", binding, action.name()),
+    };
+}
+
+/// System to keep the lists of bound inputs up to date.
+fn handle_bindings_display_change(
+    settings: Res<UserSettings>,
+    mut bindings_text_query: Query<(&mut Text, &BindingsDisplay)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, display) in bindings_text_query.iter_mut() {
+        text.sections[0].value = bindings_text(&settings, display.0);
+    }
+}
+
+/// System to keep the control scheme display up to date.
+fn handle_control_scheme_display_change(
+    settings: Res<UserSettings>,
+    mut scheme_text_query: Query<&mut Text, With<ControlSchemeDisplay>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut text in scheme_text_query.iter_mut() {
+        text.sections[1].value = control_schemes_text(&settings);
+    }
+}
+
+/// System to show how the rebinding went.
+fn handle_binding_status_display_change(
+    capture: Res<BindingCapture>,
+    mut status_text_query: Query<&mut Text, With<BindingStatusDisplay>>,
+) {
+    if !capture.is_changed() {
+        return;
+    }
+    for mut text in status_text_query.iter_mut() {
+        text.sections[0].value = capture.status.clone();
+    }
+}
+
+fn reset_binding_capture(mut capture: ResMut<BindingCapture>) {
+    *capture = BindingCapture::default();
+}
+
+/// Plugin handling the capture of inputs to rebind the controls with.
+pub(crate) struct RebindingPlugin;
+impl Plugin for RebindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BindingCapture>()
+            .add_system(
+                capture_next_binding
+                    .run_if(in_state(MenuState::Controls))
+                    .after(InputSystem)
+                    .in_base_set(CoreSet::PreUpdate),
+            )
+            .add_system(handle_bindings_display_change.run_if(in_state(MenuState::Controls)))
+            .add_system(handle_binding_status_display_change.run_if(in_state(MenuState::Controls)))
+            .add_system(handle_control_scheme_display_change.run_if(in_state(MenuState::Controls)))
+            .add_system(reset_binding_capture.in_schedule(OnExit(MenuState::Controls)));
+    }
+}
@@ -1,6 +1,8 @@
 use crate::network::players::MatchTime;
-use crate::network::session::LocalPlayerHandle;
+use crate::network::session::LocalPlayers;
+use crate::network::stats::ConnectionQuality;
 use crate::network::{PlayerHandle, PlayerRegistry};
+use crate::teams::TeamColors;
 use crate::ui::fonts;
 use crate::ui::input_consumption::{ActiveInputConsumerLayers, PLAYER_SCORE_VIEW_LAYER};
 use crate::{GameState, MenuState};
@@ -29,6 +31,10 @@ const STAT_FONT_SIZE: f32 = 17.;
 const PLAYER_SCORE_NAME_IDX: usize = 0;
 const PLAYER_SCORE_KILLS_IDX: usize = 1;
 const PLAYER_SCORE_DEATHS_IDX: usize = 2;
+const PLAYER_SCORE_NETWORK_IDX: usize = 3;
+
+/// Color of the network stats of the players whose connection is lagging.
+const LAGGING_STAT_COLOR: Color = Color::ORANGE_RED;
 
 fn util_create_individual_score_display_bundle(height_offset: f32) -> NodeBundle {
     NodeBundle {
@@ -48,6 +54,7 @@ fn util_setup_individual_score_display(
     name: impl Into<String>,
     kills: impl Into<String>,
     deaths: impl Into<String>,
+    network: impl Into<String>,
     name_style: TextStyle,
     stat_style: TextStyle,
 ) {
@@ -56,7 +63,7 @@ fn util_setup_individual_score_display(
         TextBundle {
             text: Text::from_section(name, name_style).with_alignment(TextAlignment::Left),
             style: Style {
-                size: Size::new(Val::Percent(50.), Val::Percent(100.)),
+                size: Size::new(Val::Percent(40.), Val::Percent(100.)),
                 ..default()
             },
             ..default()
@@ -69,7 +76,7 @@ fn util_setup_individual_score_display(
         TextBundle {
             text: Text::from_section(kills, stat_style.clone()),
             style: Style {
-                size: Size::new(Val::Percent(25.), Val::Percent(100.)),
+                size: Size::new(Val::Percent(15.), Val::Percent(100.)),
                 ..default()
             },
             ..default()
@@ -80,9 +87,9 @@ fn util_setup_individual_score_display(
     // Deaths section -- index must be corresponding to [`PLAYER_SCORE_DEATHS_IDX`]
     parent.spawn((
         TextBundle {
-            text: Text::from_section(deaths, stat_style),
+            text: Text::from_section(deaths, stat_style.clone()),
             style: Style {
-                size: Size::new(Val::Percent(25.), Val::Percent(100.)),
+                size: Size::new(Val::Percent(15.), Val::Percent(100.)),
                 ..default()
             },
             ..default()
@@ -90,7 +97,18 @@ fn util_setup_individual_score_display(
         PlayerScoreStat,
     ));
 
-    // Ping would be nice
+    // Network section -- index must be corresponding to [`PLAYER_SCORE_NETWORK_IDX`]
+    parent.spawn((
+        TextBundle {
+            text: Text::from_section(network, stat_style),
+            style: Style {
+                size: Size::new(Val::Percent(30.), Val::Percent(100.)),
+                ..default()
+            },
+            ..default()
+        },
+        PlayerScoreStat,
+    ));
 }
 
 fn should_show_score_display(keyboard: &Res<Input<KeyCode>>) -> bool {
@@ -106,7 +124,7 @@ fn setup_score_display(
         .spawn((
             NodeBundle {
                 style: Style {
-                    size: Size::new(Val::Percent(35.0), Val::Percent(30.0)),
+                    size: Size::new(Val::Percent(45.0), Val::Percent(30.0)),
                     position_type: PositionType::Absolute,
                     position: UiRect {
                         right: Val::Percent(2.5),
@@ -181,6 +199,7 @@ fn setup_score_display(
                         "Name",
                         "Kills",
                         "Deaths",
+                        "Ping",
                         descriptor_style.clone(),
                         descriptor_style,
                     );
@@ -195,8 +214,9 @@ fn populate_score_display(
     individual_score_display_query: Query<(&PlayerScoreDisplay, &Children)>,
     mut player_score_stats_query: Query<&mut Text, With<PlayerScoreStat>>,
     players: Res<PlayerRegistry>,
+    team_colors: Res<TeamColors>,
 ) {
-    if !players.is_changed() {
+    if !(players.is_changed() || team_colors.is_changed()) {
         return;
     }
 
@@ -225,7 +245,13 @@ fn populate_score_display(
                             "Failed to fetch child display for player {}'s name",
                             player_display.0
                         ));
-                    name_text.sections[0].value = player_data.name.to_string();
+                    name_text.sections[0].value = if player_data.disconnected {
+                        format!("{} (left)", player_data.name)
+                    } else {
+                        player_data.name.to_string()
+                    };
+                    name_text.sections[0].style.color =
+                        team_colors.player_color(player_display.0, player_data.team.0);
 
                     // update the sacred texts; the books
                     let mut kills_text = player_score_stats_query
@@ -262,7 +288,7 @@ fn populate_score_display(
                         let name_style = TextStyle {
                             font: fonts::load(&asset_server, fonts::ULTRAGONIC),
                             font_size: STAT_FONT_SIZE,
-                            color: player_data.team.color(),
+                            color: team_colors.player_color(i, player_data.team.0),
                         };
 
                         parent
@@ -276,6 +302,7 @@ fn populate_score_display(
                                     player_data.name.clone(),
                                     player_data.kills.to_string(),
                                     player_data.deaths.to_string(),
+                                    "-",
                                     name_style,
                                     stats_style.clone(),
                                 );
@@ -286,6 +313,43 @@ fn populate_score_display(
     }
 }
 
+/// System to keep the players' ping, frame advantage and bandwidth on the scoreboard up to date.
+fn handle_network_stats_display(
+    quality: Res<ConnectionQuality>,
+    individual_score_display_query: Query<(&PlayerScoreDisplay, &Children)>,
+    mut player_score_stats_query: Query<&mut Text, With<PlayerScoreStat>>,
+) {
+    if !quality.is_changed() {
+        return;
+    }
+
+    for (player_display, children) in individual_score_display_query.iter() {
+        let Some(mut network_text) = children
+            .get(PLAYER_SCORE_NETWORK_IDX)
+            .and_then(|child| player_score_stats_query.get_mut(*child).ok())
+        else {
+            continue;
+        };
+        // the local players have no network to speak of
+        let (text, color) = match quality.players.get(&player_display.0) {
+            Some(stats) => (
+                format!(
+                    "{} ms {:+}f {} kbps",
+                    stats.ping_ms, stats.frames_ahead, stats.kbps_sent
+                ),
+                if stats.is_lagging() {
+                    LAGGING_STAT_COLOR
+                } else {
+                    Color::WHITE
+                },
+            ),
+            None => ("-".to_string(), Color::WHITE),
+        };
+        network_text.sections[0].value = text;
+        network_text.sections[0].style.color = color;
+    }
+}
+
 fn handle_showing_score_display(
     keyboard: Res<Input<KeyCode>>,
     input_consumers: Res<ActiveInputConsumerLayers>,
@@ -346,7 +410,8 @@ fn handle_match_time_display(
 
 fn handle_victory_text(
     players: Res<PlayerRegistry>,
-    you: Res<LocalPlayerHandle>,
+    you: Res<LocalPlayers>,
+    team_colors: Res<TeamColors>,
     mut victory_text_query: Query<&mut Text, With<VictoryText>>,
     mut parsed_victory: Local<bool>,
 ) {
@@ -370,12 +435,12 @@ fn handle_victory_text(
         }
     }
 
-    let victor_strings = if you.0 == victor_idx {
+    let victor_strings = if you.contains(victor_idx) {
         ("You".to_string(), "\nare victorious!")
     } else {
         (players[victor_idx].name.clone(), "\nis victorious!")
     };
-    let victor_color = players[victor_idx].team.color();
+    let victor_color = team_colors.player_color(victor_idx, players[victor_idx].team.0);
 
     for mut victory_text in victory_text_query.iter_mut() {
         victory_text.sections[0].value = victor_strings.0.clone();
@@ -390,6 +455,7 @@ impl Plugin for PlayerScorePlugin {
         app.add_system(setup_score_display.in_schedule(OnEnter(GameState::InGame)))
             // despawn_node::<TotalScoreDisplay> -- handled by despawn_everything
             .add_system(populate_score_display.run_if(in_state(GameState::InGame)))
+            .add_system(handle_network_stats_display.run_if(in_state(GameState::InGame)))
             .add_system(handle_showing_score_display.run_if(in_state(GameState::InGame)))
             .add_system(handle_match_time_display.run_if(in_state(GameState::InGame)))
             .add_system(handle_victory_text.run_if(in_state(GameState::InGame)));
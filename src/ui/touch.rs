@@ -0,0 +1,438 @@
+//! On-screen virtual joystick and action buttons, for playing on phones and tablets.
+
+use crate::controls::bindings::InputAction;
+use crate::controls::CharacterActionInput;
+use crate::ui::user_settings::UserSettings;
+use crate::ui::{despawn_node, fonts};
+use crate::GameState;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const JOYSTICK_RADIUS_PX: f32 = 75.0;
+const KNOB_RADIUS_PX: f32 = 30.0;
+const BUTTON_SIZE_PX: f32 = 70.0;
+const BUTTON_SPACING_PX: f32 = 10.0;
+const BUTTON_FONT_SIZE: f32 = 16.0;
+const SCREEN_MARGIN_PERCENT: f32 = 5.0;
+
+/// How see-through the pads are in comparison to the knob and the labels, at rest and when pressed.
+const PAD_ALPHA_SHARE: f32 = 0.3;
+const PRESSED_PAD_ALPHA_SHARE: f32 = 0.7;
+
+/// Action buttons by rows, the thumb's home row last.
+const BUTTON_ROWS: [&[InputAction]; 3] = [
+    &[InputAction::Ability],
+    &[InputAction::Interact1, InputAction::Interact2],
+    &[InputAction::Reload, InputAction::Fire],
+];
+
+/// When to show the touch controls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TouchControlsMode {
+    /// Only on devices with a touch screen.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl TouchControlsMode {
+    /// Human-readable name of the mode.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            TouchControlsMode::Auto => "Auto",
+            TouchControlsMode::Always => "Always",
+            TouchControlsMode::Never => "Never",
+        }
+    }
+
+    /// Get the mode to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            TouchControlsMode::Auto => TouchControlsMode::Always,
+            TouchControlsMode::Always => TouchControlsMode::Never,
+            TouchControlsMode::Never => TouchControlsMode::Auto,
+        }
+    }
+
+    /// Whether the controls are to be shown, given whether the device has turned out to have a touch screen.
+    pub const fn is_shown(&self, touch_screen_detected: bool) -> bool {
+        match self {
+            TouchControlsMode::Auto => touch_screen_detected,
+            TouchControlsMode::Always => true,
+            TouchControlsMode::Never => false,
+        }
+    }
+}
+
+/// Which side of the screen the touch controls are on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TouchLayout {
+    /// Joystick on the left, buttons on the right.
+    #[default]
+    RightHanded,
+    /// Buttons on the left, joystick on the right.
+    LeftHanded,
+}
+
+impl TouchLayout {
+    /// Human-readable name of the layout.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            TouchLayout::RightHanded => "Right-handed",
+            TouchLayout::LeftHanded => "Left-handed",
+        }
+    }
+
+    /// Get the layout to switch to from this one.
+    pub const fn next(&self) -> Self {
+        match self {
+            TouchLayout::RightHanded => TouchLayout::LeftHanded,
+            TouchLayout::LeftHanded => TouchLayout::RightHanded,
+        }
+    }
+}
+
+/// Resource with the state of the touch controls, to be mixed into the player's input.
+#[derive(Resource, Debug, Default, PartialEq)]
+pub struct TouchActions {
+    /// Joystick deflection, up to the length of 1.
+    pub movement: Vec2,
+    pub fire: bool,
+    pub reload: bool,
+    pub interact_1: bool,
+    pub interact_2: bool,
+    pub ability: bool,
+}
+
+impl TouchActions {
+    /// Add the touch controls' state to a player's character action input.
+    pub fn apply(&self, actions: &mut CharacterActionInput) {
+        actions.up += self.movement.y;
+        actions.right += self.movement.x;
+        actions.fire |= self.fire;
+        actions.reload |= self.reload;
+        actions.interact_1 |= self.interact_1;
+        actions.interact_2 |= self.interact_2;
+        actions.ability |= self.ability;
+    }
+}
+
+/// Resource keeping track of whether the device has a touch screen, learnt either from the browser or from the first touch.
+#[derive(Resource, Debug)]
+struct TouchScreen {
+    detected: bool,
+}
+
+impl Default for TouchScreen {
+    fn default() -> Self {
+        Self {
+            detected: is_touch_screen_reported(),
+        }
+    }
+}
+
+/// Whether the browser has told of the device being a phone or a tablet.
+#[cfg(target_arch = "wasm32")]
+fn is_touch_screen_reported() -> bool {
+    crate::js_interop::is_mobile()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_touch_screen_reported() -> bool {
+    false
+}
+
+/// Marker of the root nodes of the touch controls.
+#[derive(Component)]
+struct TouchControlsElement;
+
+#[derive(Component, Debug, Default)]
+struct VirtualJoystick {
+    /// Touch steering the joystick, which keeps doing so even when it slides off.
+    touch_id: Option<u64>,
+    value: Vec2,
+}
+
+#[derive(Component)]
+struct VirtualJoystickKnob;
+
+#[derive(Component, Debug)]
+struct VirtualButton {
+    action: InputAction,
+    pressed: bool,
+}
+
+/// Whether the point on the screen lies within the node.
+fn node_contains(node: &Node, transform: &GlobalTransform, point: Vec2) -> bool {
+    let offset = point - transform.translation().truncate();
+    offset.abs().cmple(node.size() / 2.0).all()
+}
+
+/// Get the position of the joystick's knob inside its base, deflected by the joystick's value.
+fn knob_position(value: Vec2) -> UiRect {
+    let rest = JOYSTICK_RADIUS_PX - KNOB_RADIUS_PX;
+    UiRect {
+        left: Val::Px(rest + value.x * rest),
+        // the screen's y axis points down
+        top: Val::Px(rest - value.y * rest),
+        ..default()
+    }
+}
+
+fn spawn_touch_controls(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &UserSettings,
+) {
+    let font = fonts::load(asset_server, fonts::FIRA_SANS);
+    let opacity = settings.touch_opacity;
+    let margin = Val::Percent(SCREEN_MARGIN_PERCENT);
+    let (left_side, right_side) = (
+        UiRect {
+            left: margin,
+            bottom: margin,
+            ..default()
+        },
+        UiRect {
+            right: margin,
+            bottom: margin,
+            ..default()
+        },
+    );
+    let (joystick_position, buttons_position) = match settings.touch_layout {
+        TouchLayout::RightHanded => (left_side, right_side),
+        TouchLayout::LeftHanded => (right_side, left_side),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: joystick_position,
+                    size: Size::new(
+                        Val::Px(JOYSTICK_RADIUS_PX * 2.),
+                        Val::Px(JOYSTICK_RADIUS_PX * 2.),
+                    ),
+                    ..default()
+                },
+                background_color: Color::WHITE.with_a(opacity * PAD_ALPHA_SHARE).into(),
+                ..default()
+            },
+            VirtualJoystick::default(),
+            TouchControlsElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: knob_position(Vec2::ZERO),
+                        size: Size::new(Val::Px(KNOB_RADIUS_PX * 2.), Val::Px(KNOB_RADIUS_PX * 2.)),
+                        ..default()
+                    },
+                    background_color: Color::WHITE.with_a(opacity).into(),
+                    ..default()
+                },
+                VirtualJoystickKnob,
+            ));
+        });
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: buttons_position,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            TouchControlsElement,
+        ))
+        .with_children(|parent| {
+            for row in BUTTON_ROWS {
+                parent.spawn(NodeBundle::default()).with_children(|parent| {
+                    for action in row.iter().copied() {
+                        parent
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        size: Size::new(
+                                            Val::Px(BUTTON_SIZE_PX),
+                                            Val::Px(BUTTON_SIZE_PX),
+                                        ),
+                                        margin: UiRect::all(Val::Px(BUTTON_SPACING_PX / 2.)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::WHITE
+                                        .with_a(opacity * PAD_ALPHA_SHARE)
+                                        .into(),
+                                    ..default()
+                                },
+                                VirtualButton {
+                                    action,
+                                    pressed: false,
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    action.name(),
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: BUTTON_FONT_SIZE,
+                                        color: Color::WHITE.with_a(opacity),
+                                    },
+                                ));
+                            });
+                    }
+                });
+            }
+        });
+}
+
+/// System to notice the touch screen the first time it's touched.
+fn detect_touch_screen(touches: Res<Touches>, mut touch_screen: ResMut<TouchScreen>) {
+    if !touch_screen.detected && touches.any_just_pressed() {
+        touch_screen.detected = true;
+    }
+}
+
+/// System to show or hide the touch controls, and to rebuild them whenever their settings change.
+fn handle_touch_controls_spawning(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<UserSettings>,
+    touch_screen: Res<TouchScreen>,
+    mut touch_actions: ResMut<TouchActions>,
+    touch_controls_query: Query<Entity, With<TouchControlsElement>>,
+) {
+    let shown = settings.touch_controls.is_shown(touch_screen.detected);
+    if !(settings.is_changed()
+        || touch_screen.is_changed()
+        || (shown && touch_controls_query.is_empty()))
+    {
+        return;
+    }
+
+    for entity in touch_controls_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    *touch_actions = TouchActions::default();
+
+    if shown {
+        spawn_touch_controls(&mut commands, &asset_server, &settings);
+    }
+}
+
+/// System to read the touches on the joystick and the buttons into the touch actions.
+/// The joystick holds onto the touch that started on it, while the buttons are pressed by any touch over them.
+fn handle_touch_controls_input(
+    touches: Res<Touches>,
+    settings: Res<UserSettings>,
+    mut joystick_query: Query<(&Node, &GlobalTransform, &mut VirtualJoystick, &Children)>,
+    mut knob_query: Query<&mut Style, With<VirtualJoystickKnob>>,
+    mut button_query: Query<(
+        &Node,
+        &GlobalTransform,
+        &mut VirtualButton,
+        &mut BackgroundColor,
+    )>,
+    mut touch_actions: ResMut<TouchActions>,
+) {
+    let mut actions = TouchActions::default();
+    let mut held_touches = vec![];
+
+    for (node, transform, mut joystick, children) in joystick_query.iter_mut() {
+        if joystick
+            .touch_id
+            .is_some_and(|id| touches.get_pressed(id).is_none())
+        {
+            joystick.touch_id = None;
+        }
+        if joystick.touch_id.is_none() {
+            joystick.touch_id = touches
+                .iter_just_pressed()
+                .find(|touch| node_contains(node, transform, touch.position()))
+                .map(|touch| touch.id());
+        }
+
+        let value = joystick
+            .touch_id
+            .and_then(|id| touches.get_pressed(id))
+            .map_or(Vec2::ZERO, |touch| {
+                let offset = touch.position() - transform.translation().truncate();
+                // the screen's y axis points down
+                (Vec2::new(offset.x, -offset.y) / (node.size().x / 2.)).clamp_length_max(1.0)
+            });
+        if joystick.value != value {
+            joystick.value = value;
+            let mut knobs = knob_query.iter_many_mut(children);
+            while let Some(mut style) = knobs.fetch_next() {
+                style.position = knob_position(value);
+            }
+        }
+
+        held_touches.extend(joystick.touch_id);
+        actions.movement += value;
+    }
+    actions.movement = actions.movement.clamp_length_max(1.0);
+
+    for (node, transform, mut button, mut background) in button_query.iter_mut() {
+        let pressed = touches.iter().any(|touch| {
+            !held_touches.contains(&touch.id()) && node_contains(node, transform, touch.position())
+        });
+        if button.pressed != pressed {
+            button.pressed = pressed;
+            let alpha_share = if pressed {
+                PRESSED_PAD_ALPHA_SHARE
+            } else {
+                PAD_ALPHA_SHARE
+            };
+            background.0.set_a(settings.touch_opacity * alpha_share);
+        }
+        if !pressed {
+            continue;
+        }
+        match button.action {
+            InputAction::Fire => actions.fire = true,
+            InputAction::Reload => actions.reload = true,
+            InputAction::Interact1 => actions.interact_1 = true,
+            InputAction::Interact2 => actions.interact_2 = true,
+            InputAction::Ability => actions.ability = true,
+            _ => {}
+        }
+    }
+
+    if *touch_actions != actions {
+        *touch_actions = actions;
+    }
+}
+
+fn reset_touch_actions(mut touch_actions: ResMut<TouchActions>) {
+    *touch_actions = TouchActions::default();
+}
+
+/// Plugin of the on-screen touch controls, shown in-game on touch devices.
+pub(crate) struct TouchControlsPlugin;
+impl Plugin for TouchControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchActions>()
+            .init_resource::<TouchScreen>()
+            .add_system(detect_touch_screen)
+            .add_system(handle_touch_controls_spawning.run_if(in_state(GameState::InGame)))
+            .add_system(
+                handle_touch_controls_input
+                    .run_if(in_state(GameState::InGame))
+                    .after(InputSystem)
+                    .in_base_set(CoreSet::PreUpdate),
+            )
+            .add_system(despawn_node::<TouchControlsElement>.in_schedule(OnExit(GameState::InGame)))
+            .add_system(reset_touch_actions.in_schedule(OnExit(GameState::InGame)));
+    }
+}
@@ -1,13 +1,63 @@
+use crate::classes::CharacterClass;
+use crate::controls::bindings::{ControlScheme, InputAction, InputBindings};
+use crate::controls::devices::MAX_LOCAL_PLAYERS;
+use crate::network::ggrs_config::MAX_INPUT_DELAY;
+use crate::teams::TeamPalette;
 use crate::ui::text_input::TextInput;
+use crate::ui::touch::{TouchControlsMode, TouchLayout};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Version of the stored settings' layout, the first one being 1.
+/// Bump it and add a step to [`migrate_settings`] whenever a setting changes its meaning or format.
+const SETTINGS_VERSION: u32 = 3;
+
+/// Name the settings are stored under.
+const SETTINGS_STORAGE_NAME: &str = "settings";
+
+/// The least opacity the touch controls can be set to, so that they can still be found.
+const MIN_TOUCH_OPACITY: f32 = 0.1;
 
 /// Resource encapsulating a set of the application's user settings.
 /// Each setting is identified by a `UserInputForm` and could be retrieved with it.
-#[derive(Resource, Debug)]
+///
+/// Settings are stored between launches. Missing ones are filled with defaults, so new settings can be added freely.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UserSettings {
     pub player_name: String,
+    /// Class the local players spawn as, unless they pick another one in the lobby.
+    pub character_class: CharacterClass,
     pub server_url: String,
     pub room_name: String,
+    /// Address of the service listing the public rooms, or none to neither browse nor list rooms.
+    pub room_listing_url: String,
+    /// Whether the rooms hosted from here get listed for everyone to see.
+    pub public_room: bool,
+    pub master_volume: f32,
+    pub effects_volume: f32,
+    pub music_volume: f32,
+    /// Whether to tone down the cosmetic effects, like screen shake and particles.
+    pub reduced_effects: bool,
+    /// Whether to show the local players where their shots would fly, for the guns that allow it.
+    pub aim_preview: bool,
+    /// Colors the teams are told apart by.
+    pub team_palette: TeamPalette,
+    pub bindings: InputBindings,
+    /// Steering scheme of each of the local players, in their order, so that they can pick differently.
+    pub control_schemes: [ControlScheme; MAX_LOCAL_PLAYERS],
+    pub touch_controls: TouchControlsMode,
+    pub touch_layout: TouchLayout,
+    /// Opacity of the touch controls, from barely visible to fully opaque.
+    pub touch_opacity: f32,
+    /// Number of players sharing this machine, each with their own input device.
+    pub local_players: usize,
+    /// Frames to hold the inputs back for in online matches, or `None` to pick it from the peers' latency.
+    pub input_delay: Option<usize>,
+    /// Whether to mask the filtered words in the peers' chat messages.
+    pub chat_filter: bool,
+    /// Words to mask in the peers' chat messages when the chat filter is on, on top of the bundled ones.
+    pub filtered_words: Vec<String>,
 }
 
 /// Enum representing different forms of user input, each associated with a unique setting.
@@ -17,14 +67,41 @@ pub enum UserInputForm {
     PlayerName,
     ServerUrl,
     RoomName,
+    RoomListingUrl,
+}
+
+/// Audio channel whose volume is adjusted separately. Placed on an entity as a component, marks it as displaying the volume.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChannel {
+    Master,
+    Effects,
+    Music,
 }
 
 impl Default for UserSettings {
     fn default() -> Self {
         Self {
             player_name: "Player".into(),
+            character_class: CharacterClass::default(),
             server_url: "wss://match-0-6.helsing.studio".into(),
             room_name: "".into(),
+            room_listing_url: "".into(),
+            public_room: true,
+            master_volume: 1.0,
+            effects_volume: 0.8,
+            music_volume: 0.5,
+            reduced_effects: false,
+            aim_preview: true,
+            team_palette: TeamPalette::default(),
+            bindings: InputBindings::default(),
+            control_schemes: [ControlScheme::default(); MAX_LOCAL_PLAYERS],
+            touch_controls: TouchControlsMode::default(),
+            touch_layout: TouchLayout::default(),
+            touch_opacity: 0.5,
+            local_players: 1,
+            input_delay: None,
+            chat_filter: false,
+            filtered_words: vec![],
         }
     }
 }
@@ -37,6 +114,7 @@ impl UserSettings {
             UserInputForm::PlayerName => self.player_name = value,
             UserInputForm::ServerUrl => self.server_url = value,
             UserInputForm::RoomName => self.room_name = value,
+            UserInputForm::RoomListingUrl => self.room_listing_url = value,
         }
     }
 
@@ -46,8 +124,236 @@ impl UserSettings {
             UserInputForm::PlayerName => self.player_name.clone(),
             UserInputForm::ServerUrl => self.server_url.clone(),
             UserInputForm::RoomName => self.room_name.clone(),
+            UserInputForm::RoomListingUrl => self.room_listing_url.clone(),
+        }
+    }
+
+    /// Retrieves the volume of the channel as set, not counting in the master volume.
+    pub fn volume(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master_volume,
+            VolumeChannel::Effects => self.effects_volume,
+            VolumeChannel::Music => self.music_volume,
+        }
+    }
+
+    /// Sets the volume of the channel, clamped between silence and full volume.
+    pub fn set_volume(&mut self, channel: VolumeChannel, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        match channel {
+            VolumeChannel::Master => self.master_volume = volume,
+            VolumeChannel::Effects => self.effects_volume = volume,
+            VolumeChannel::Music => self.music_volume = volume,
+        }
+    }
+
+    /// Whether there is a room listing to browse the public rooms in and to list the hosted ones on.
+    pub fn room_listing_enabled(&self) -> bool {
+        !self.room_listing_url.trim().is_empty()
+    }
+
+    /// Volume at which sound effects should actually be played.
+    pub fn effective_effects_volume(&self) -> f32 {
+        self.master_volume * self.effects_volume
+    }
+
+    /// Volume at which music should actually be played.
+    pub fn effective_music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    /// Sets the opacity of the touch controls, never letting them vanish completely.
+    pub fn set_touch_opacity(&mut self, opacity: f32) {
+        self.touch_opacity = opacity.clamp(MIN_TOUCH_OPACITY, 1.0);
+    }
+
+    /// Number of players sharing this machine, kept within what the game can handle.
+    pub fn local_player_count(&self) -> usize {
+        self.local_players.clamp(1, MAX_LOCAL_PLAYERS)
+    }
+
+    /// Sets the number of players sharing this machine, kept within what the game can handle.
+    pub fn set_local_player_count(&mut self, count: usize) {
+        self.local_players = count.clamp(1, MAX_LOCAL_PLAYERS);
+    }
+
+    /// Steering scheme of the local player of the order.
+    pub fn control_scheme(&self, local_player: usize) -> ControlScheme {
+        self.control_schemes
+            .get(local_player)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Switches the local player of the order over to the next steering scheme.
+    pub fn toggle_control_scheme(&mut self, local_player: usize) {
+        if let Some(scheme) = self.control_schemes.get_mut(local_player) {
+            *scheme = scheme.next();
         }
     }
+
+    /// Steps the input delay up or down, with the automatic one coming before no delay at all.
+    pub fn change_input_delay(&mut self, change: isize) {
+        let step = self.input_delay.map_or(-1, |delay| delay as isize) + change;
+        self.input_delay = match step {
+            ..=-1 => None,
+            step => Some((step as usize).min(MAX_INPUT_DELAY)),
+        };
+    }
+}
+
+/// The settings as they are written down, along with the version of their layout.
+#[derive(Serialize, Deserialize)]
+struct StoredSettings<S> {
+    version: u32,
+    settings: S,
+}
+
+/// Bring settings stored by an older version of the game up to date.
+fn migrate_settings(stored: StoredSettings<UserSettings>) -> UserSettings {
+    let StoredSettings {
+        mut version,
+        mut settings,
+    } = stored;
+
+    if version > SETTINGS_VERSION {
+        warn!(
+            "Settings were saved by a newer version of the game ({} > {}), reading what we can",
+            version, SETTINGS_VERSION
+        );
+    }
+
+    while version < SETTINGS_VERSION {
+        match version {
+            // The class ability came along with no inputs bound to it
+            1 => settings.bindings.bind_defaults(InputAction::Ability),
+            // The local stand-in for the room listing used to be the default, reachable by almost no one
+            2 => {
+                if settings.room_listing_url == "http://localhost:3537/rooms" {
+                    settings.room_listing_url.clear();
+                }
+            }
+            _ => {
+                warn!(
+                    "No way to migrate the settings from version {}, reading what we can",
+                    version
+                );
+                break;
+            }
+        }
+        version += 1;
+    }
+
+    settings
+}
+
+impl UserSettings {
+    /// Read the settings saved during one of the previous launches, if there are any and they are readable.
+    pub fn load() -> Option<Self> {
+        let serialized = storage::read(SETTINGS_STORAGE_NAME)?;
+        match ron::from_str::<StoredSettings<UserSettings>>(&serialized) {
+            Ok(stored) => Some(migrate_settings(stored)),
+            Err(error) => {
+                warn!(
+                    "Could not read the stored settings, falling back to defaults: {}",
+                    error
+                );
+                None
+            }
+        }
+    }
+
+    /// Write the settings down to be loaded during the next launch.
+    pub fn save(&self) {
+        let stored = StoredSettings {
+            version: SETTINGS_VERSION,
+            settings: self,
+        };
+        match ron::ser::to_string_pretty(&stored, ron::ser::PrettyConfig::default()) {
+            Ok(serialized) => {
+                if let Err(error) = storage::write(SETTINGS_STORAGE_NAME, &serialized) {
+                    warn!("Could not save the settings: {}", error);
+                }
+            }
+            Err(error) => warn!("Could not serialize the settings: {}", error),
+        }
+    }
+}
+
+/// Settings and the like are kept in files in the platform's config directory, one per name.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod storage {
+    use std::path::PathBuf;
+
+    const APP_DIRECTORY_NAME: &str = "cosmic-spaceball-tactical-action-arena";
+
+    /// Get the directory where applications keep their configs on this platform.
+    fn platform_config_dir() -> Option<PathBuf> {
+        #[cfg(target_os = "windows")]
+        {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            std::env::var_os("HOME").map(|home| {
+                PathBuf::from(home)
+                    .join("Library")
+                    .join("Application Support")
+            })
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+                })
+        }
+    }
+
+    fn storage_path(name: &str) -> Option<PathBuf> {
+        platform_config_dir().map(|dir| dir.join(APP_DIRECTORY_NAME).join(format!("{}.ron", name)))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(storage_path(name)?).ok()
+    }
+
+    pub fn write(name: &str, serialized: &str) -> std::io::Result<()> {
+        let path = storage_path(name).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory found")
+        })?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serialized)
+    }
+}
+
+/// Settings and the like are kept in the browser's local storage, one key per name.
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod storage {
+    use crate::js_interop;
+
+    fn storage_key(name: &str) -> String {
+        format!("spaceballs-{}", name)
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        js_interop::load_from_js_storage(&storage_key(name))
+    }
+
+    pub fn write(name: &str, serialized: &str) -> Result<(), std::convert::Infallible> {
+        js_interop::save_to_js_storage(&storage_key(name), serialized);
+        Ok(())
+    }
+}
+
+/// System to save the settings whenever they change.
+fn save_settings_on_change(settings: Res<UserSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
 }
 
 /// System responsible for recording data from text inputs into settings.
@@ -65,10 +371,11 @@ pub fn transfer_setting_from_text_input(
     }
 }
 
-/// Plugin handling the [`UserSettings`] resource insertion.
+/// Plugin handling the [`UserSettings`] resource insertion, loading the stored settings if there are any, and saving them.
 pub(crate) struct UserSettingsPlugin;
 impl Plugin for UserSettingsPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(UserSettings::default());
+        app.insert_resource(UserSettings::load().unwrap_or_default())
+            .add_system(save_settings_on_change.in_base_set(CoreSet::Last));
     }
 }